@@ -0,0 +1,24 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+    /// Prices swap routes through pallet-liquidity-pool. A runtime implements
+    /// it by calling `Pallet::quote_exact_in` and `Pallet::quote_exact_out`.
+    pub trait LiquidityPoolApi<AssetId, Balance>
+    where
+        AssetId: Codec,
+        Balance: Codec,
+    {
+        /// Amount of the last asset of `path` received for selling
+        /// `amount_in` of the first, `None` if a pool on the path is missing
+        /// or too shallow
+        fn quote_exact_in(path: Vec<AssetId>, amount_in: Balance) -> Option<Balance>;
+
+        /// Amount of the first asset of `path` needed to buy `amount_out` of
+        /// the last, fees included, `None` if a pool on the path is missing or
+        /// too shallow
+        fn quote_exact_out(path: Vec<AssetId>, amount_out: Balance) -> Option<Balance>;
+    }
+}
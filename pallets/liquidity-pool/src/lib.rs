@@ -2,19 +2,28 @@
 
 pub use pallet::*;
 
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod tests;
+
 #[frame_support::pallet]
 pub mod pallet {
     use frame_support::{
         dispatch::DispatchResultWithPostInfo,
         pallet_prelude::*,
-        traits::{Currency, ReservableCurrency, Get, ExistenceRequirement},
-        Parameter, 
+        traits::{
+            tokens::fungibles::{self, Transfer as _},
+            Currency, ReservableCurrency, Get, ExistenceRequirement,
+        },
+        Parameter, PalletId,
         sp_runtime::{
-            traits::{AtLeast32BitUnsigned, CheckedDiv, CheckedMul, MaybeSerializeDeserialize, Member, Zero},
-            FixedPointNumber, FixedU128, Perquintill,
+            traits::{AccountIdConversion, AtLeast32BitUnsigned, MaybeSerializeDeserialize, Member, Zero},
+            FixedPointNumber, FixedU128, PerThing, Perquintill,
         },
     };
     use frame_system::pallet_prelude::*;
+    use sp_core::U256;
     use sp_std::prelude::*;
 
     /// Configure the pallet by specifying the parameters and types on which it depends.
@@ -23,19 +32,35 @@ pub mod pallet {
         /// Because this pallet emits events, it depends on the runtime's definition of an event.
         type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
         
-        /// The currency type this pallet will be using
+        /// The native currency, traded in pools as `NativeAssetId`
         type Currency: ReservableCurrency<Self::AccountId>;
         
         /// Asset ID type
-        type AssetId: Parameter + Member + Copy + MaybeSerializeDeserialize + Ord + Default + AtLeast32BitUnsigned;
+        type AssetId: Parameter + Member + Copy + MaybeSerializeDeserialize + Ord + Default + AtLeast32BitUnsigned + MaxEncodedLen;
+        
+        /// Every other asset, e.g. pallet-assets
+        type Assets: fungibles::Inspect<Self::AccountId, AssetId = Self::AssetId, Balance = BalanceOf<Self>>
+            + fungibles::Transfer<Self::AccountId>;
+        
+        /// Asset ID that stands for the native currency rather than an entry of `Assets`
+        #[pallet::constant]
+        type NativeAssetId: Get<Self::AssetId>;
+        
+        /// Account holding the pooled assets is derived from this ID
+        #[pallet::constant]
+        type PalletId: Get<PalletId>;
         
         /// The custom fee applied to all trades (0.369%)
         #[pallet::constant]
         type TradingFee: Get<Perquintill>;
         
-        /// The minimum liquidity required for a new pool
+        /// Liquidity minted on a pool's first deposit that is locked forever
         #[pallet::constant]
         type MinimumLiquidity: Get<BalanceOf<Self>>;
+        
+        /// Most assets a swap path may visit, its first and last included
+        #[pallet::constant]
+        type MaxPathLength: Get<u32>;
     }
 
     pub type BalanceOf<T> = <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
@@ -43,11 +68,31 @@ pub mod pallet {
     /// Nourish Chain Trading Fee set to 0.369%
     pub const NOURISH_CHAIN_FEE: Perquintill = Perquintill::from_parts(3_690_000_000_000_000); // 0.369%
 
+    /// Metadata recorded when a pool is created
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+    pub struct PoolInfo<AccountId, BlockNumber> {
+        /// Account that created the pool
+        pub creator: AccountId,
+        /// Block the pool was created in
+        pub created_at: BlockNumber,
+    }
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
     pub struct Pallet<T>(_);
 
-    /// Storage for liquidity pools. Maps (asset_a, asset_b) -> (reserve_a, reserve_b)
+    /// Registry of created pools. Keys are always ordered, asset_a < asset_b
+    #[pallet::storage]
+    #[pallet::getter(fn pools)]
+    pub type Pools<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        (T::AssetId, T::AssetId),
+        PoolInfo<T::AccountId, T::BlockNumber>,
+        OptionQuery
+    >;
+
+    /// Storage for liquidity pools. Maps (asset_a, asset_b) -> (reserve_a, reserve_b), asset_a < asset_b
     #[pallet::storage]
     #[pallet::getter(fn liquidity_pools)]
     pub type LiquidityPools<T: Config> = StorageMap<
@@ -58,7 +103,7 @@ pub mod pallet {
         ValueQuery
     >;
 
-    /// Storage for liquidity tokens. Maps (asset_a, asset_b, account) -> liquidity_tokens
+    /// Storage for liquidity tokens. Maps (asset_a, asset_b, account) -> liquidity_tokens, asset_a < asset_b
     #[pallet::storage]
     #[pallet::getter(fn liquidity_tokens)]
     pub type LiquidityTokens<T: Config> = StorageMap<
@@ -83,6 +128,9 @@ pub mod pallet {
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
+        /// A pool was created. [creator, asset_a, asset_b]
+        PoolCreated(T::AccountId, T::AssetId, T::AssetId),
+        
         /// Liquidity added to a pool. [who, asset_a, asset_b, amount_a, amount_b, liquidity]
         LiquidityAdded(T::AccountId, T::AssetId, T::AssetId, BalanceOf<T>, BalanceOf<T>, BalanceOf<T>),
         
@@ -113,6 +161,9 @@ pub mod pallet {
         /// Pool does not exist
         PoolDoesNotExist,
         
+        /// Pool has already been created
+        PoolAlreadyExists,
+        
         /// Liquidity pool calculation error
         CalculationError,
         
@@ -121,11 +172,42 @@ pub mod pallet {
         
         /// Deadline exceeded
         DeadlineExceeded,
+        
+        /// Swap path is too short or too long, or visits an asset twice
+        InvalidPath,
     }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        /// Add liquidity to a pool
+        /// Create an empty pool for an asset pair, in either order
+        #[pallet::weight(10_000)]
+        pub fn create_pool(
+            origin: OriginFor<T>,
+            asset_a: T::AssetId,
+            asset_b: T::AssetId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            // Ensure the asset pair is valid
+            ensure!(asset_a != asset_b, Error::<T>::InvalidAssetPair);
+
+            let ((asset_a, asset_b), _) = Self::sort_assets(asset_a, asset_b);
+            ensure!(!Pools::<T>::contains_key((asset_a, asset_b)), Error::<T>::PoolAlreadyExists);
+
+            Pools::<T>::insert((asset_a, asset_b), PoolInfo {
+                creator: who.clone(),
+                created_at: frame_system::Pallet::<T>::block_number(),
+            });
+
+            Self::deposit_event(Event::PoolCreated(who, asset_a, asset_b));
+
+            Ok(())
+        }
+
+        /// Add liquidity to a pool. Assets may be given in either order, the
+        /// amounts follow them. Past the first deposit the amounts are the most
+        /// taken of each: only what matches the pool's current ratio is
+        /// transferred, the scarcer side in full.
         #[pallet::weight(10_000)]
         pub fn add_liquidity(
             origin: OriginFor<T>,
@@ -134,8 +216,10 @@ pub mod pallet {
             amount_a: BalanceOf<T>,
             amount_b: BalanceOf<T>,
             min_liquidity: BalanceOf<T>,
+            deadline: T::BlockNumber,
         ) -> DispatchResult {
             let who = ensure_signed(origin)?;
+            Self::ensure_deadline(deadline)?;
 
             // Ensure the asset pair is valid
             ensure!(asset_a != asset_b, Error::<T>::InvalidAssetPair);
@@ -143,44 +227,53 @@ pub mod pallet {
             // Ensure the amounts are non-zero
             ensure!(amount_a > Zero::zero() && amount_b > Zero::zero(), Error::<T>::InvalidAmount);
 
+            // Work in the pool's canonical order from here on
+            let ((asset_a, asset_b), is_reversed) = Self::sort_assets(asset_a, asset_b);
+            let (amount_a, amount_b) = if is_reversed { (amount_b, amount_a) } else { (amount_a, amount_b) };
+            ensure!(Pools::<T>::contains_key((asset_a, asset_b)), Error::<T>::PoolDoesNotExist);
+
             // Fetch the current reserves
             let (reserve_a, reserve_b) = LiquidityPools::<T>::get((asset_a, asset_b));
 
-            // Calculate the liquidity to be added
-            let liquidity = if reserve_a.is_zero() && reserve_b.is_zero() {
-                // Initial liquidity provision
-                amount_a.min(amount_b)
+            let total_liquidity = TotalLiquidity::<T>::get((asset_a, asset_b));
+
+            // Calculate the amounts taken, the liquidity they add, and the part of it locked for good
+            let (amount_a, amount_b, liquidity, locked) = if total_liquidity.is_zero() {
+                // Initial liquidity provision: the geometric mean of the deposit, less
+                // `MinimumLiquidity` that is minted to an account nobody controls. Those
+                // shares keep removals from emptying the pool, as `get_amount_out` keeps
+                // swaps from doing so, and the first depositor cannot make a share so
+                // valuable that later deposits round away to nothing.
+                let minted = Self::narrow((Self::widen(amount_a)? * Self::widen(amount_b)?).integer_sqrt())?;
+                let locked = T::MinimumLiquidity::get();
+                ensure!(minted > locked, Error::<T>::InsufficientLiquidity);
+                (amount_a, amount_b, minted - locked, locked)
             } else {
-                // Calculate based on existing reserves
-                let liquidity_a = amount_a
-                    .checked_mul(&reserve_b)
-                    .ok_or(Error::<T>::CalculationError)?
-                    .checked_div(&reserve_a)
-                    .ok_or(Error::<T>::CalculationError)?;
-                let liquidity_b = amount_b
-                    .checked_mul(&reserve_a)
-                    .ok_or(Error::<T>::CalculationError)?
-                    .checked_div(&reserve_b)
-                    .ok_or(Error::<T>::CalculationError)?;
-                liquidity_a.min(liquidity_b)
+                // Take all of one side and as much of the other as the reserves'
+                // ratio asks for, rounded down
+                let amount_b_optimal = Self::mul_div(amount_a, reserve_b, reserve_a, false)?;
+                let (amount_a, amount_b) = if amount_b_optimal <= amount_b {
+                    (amount_a, amount_b_optimal)
+                } else {
+                    (Self::mul_div(amount_b, reserve_a, reserve_b, false)?, amount_b)
+                };
+
+                // Shares in proportion to the existing supply, by the scarcer side
+                let liquidity_a = Self::mul_div(amount_a, total_liquidity, reserve_a, false)?;
+                let liquidity_b = Self::mul_div(amount_b, total_liquidity, reserve_b, false)?;
+                (amount_a, amount_b, liquidity_a.min(liquidity_b), Zero::zero())
             };
 
-            // Ensure the liquidity meets the minimum requirement
-            ensure!(liquidity >= min_liquidity, Error::<T>::InsufficientLiquidity);
-
-            // Reserve (transfer) tokens from the user
-            T::Currency::transfer(
-                &who, 
-                &Self::account_id(), 
-                amount_a, 
-                ExistenceRequirement::KeepAlive
-            )?;
-            T::Currency::transfer(
-                &who, 
-                &Self::account_id(), 
-                amount_b, 
-                ExistenceRequirement::KeepAlive
-            )?;
+            // Ensure the liquidity meets the minimum requirement, and that the
+            // deposit is not a gift to the pool that mints nothing
+            ensure!(
+                liquidity > Zero::zero() && liquidity >= min_liquidity,
+                Error::<T>::InsufficientLiquidity
+            );
+
+            // Take both assets from the user
+            Self::transfer(asset_a, &who, &Self::account_id(), amount_a, true)?;
+            Self::transfer(asset_b, &who, &Self::account_id(), amount_b, true)?;
 
             // Update the liquidity pool reserves
             LiquidityPools::<T>::mutate((asset_a, asset_b), |reserves| {
@@ -190,10 +283,13 @@ pub mod pallet {
 
             // Mint liquidity tokens to the provider
             Self::mint_liquidity_tokens(&who, (asset_a, asset_b), liquidity)?;
+            if !locked.is_zero() {
+                Self::mint_liquidity_tokens(&Self::locked_liquidity_account(), (asset_a, asset_b), locked)?;
+            }
 
             // Update total liquidity
             TotalLiquidity::<T>::mutate((asset_a, asset_b), |total| {
-                *total = total.saturating_add(liquidity);
+                *total = total.saturating_add(liquidity).saturating_add(locked);
             });
 
             // Emit an event
@@ -202,7 +298,8 @@ pub mod pallet {
             Ok(())
         }
 
-        /// Remove liquidity from a pool
+        /// Remove liquidity from a pool. Assets may be given in either order,
+        /// the minimum amounts follow them.
         #[pallet::weight(10_000)]
         pub fn remove_liquidity(
             origin: OriginFor<T>,
@@ -211,8 +308,10 @@ pub mod pallet {
             liquidity: BalanceOf<T>,
             min_amount_a: BalanceOf<T>,
             min_amount_b: BalanceOf<T>,
+            deadline: T::BlockNumber,
         ) -> DispatchResult {
             let who = ensure_signed(origin)?;
+            Self::ensure_deadline(deadline)?;
 
             // Ensure the asset pair is valid
             ensure!(asset_a != asset_b, Error::<T>::InvalidAssetPair);
@@ -220,6 +319,14 @@ pub mod pallet {
             // Ensure the liquidity amount is non-zero
             ensure!(liquidity > Zero::zero(), Error::<T>::InvalidAmount);
 
+            // Work in the pool's canonical order from here on
+            let ((asset_a, asset_b), is_reversed) = Self::sort_assets(asset_a, asset_b);
+            let (min_amount_a, min_amount_b) = if is_reversed {
+                (min_amount_b, min_amount_a)
+            } else {
+                (min_amount_a, min_amount_b)
+            };
+
             // Fetch the current reserves
             let (reserve_a, reserve_b) = LiquidityPools::<T>::get((asset_a, asset_b));
 
@@ -231,16 +338,8 @@ pub mod pallet {
             ensure!(total_liquidity > Zero::zero(), Error::<T>::PoolDoesNotExist);
 
             // Calculate the amounts to return
-            let amount_a = liquidity
-                .checked_mul(&reserve_a)
-                .ok_or(Error::<T>::CalculationError)?
-                .checked_div(&total_liquidity)
-                .ok_or(Error::<T>::CalculationError)?;
-            let amount_b = liquidity
-                .checked_mul(&reserve_b)
-                .ok_or(Error::<T>::CalculationError)?
-                .checked_div(&total_liquidity)
-                .ok_or(Error::<T>::CalculationError)?;
+            let amount_a = Self::mul_div(liquidity, reserve_a, total_liquidity, false)?;
+            let amount_b = Self::mul_div(liquidity, reserve_b, total_liquidity, false)?;
 
             // Ensure minimum amounts are satisfied
             ensure!(amount_a >= min_amount_a, Error::<T>::SlippageExceeded);
@@ -261,18 +360,8 @@ pub mod pallet {
             });
 
             // Transfer the assets back to the user
-            T::Currency::transfer(
-                &Self::account_id(), 
-                &who, 
-                amount_a, 
-                ExistenceRequirement::AllowDeath
-            )?;
-            T::Currency::transfer(
-                &Self::account_id(), 
-                &who, 
-                amount_b, 
-                ExistenceRequirement::AllowDeath
-            )?;
+            Self::transfer(asset_a, &Self::account_id(), &who, amount_a, false)?;
+            Self::transfer(asset_b, &Self::account_id(), &who, amount_b, false)?;
 
             // Emit an event
             Self::deposit_event(Event::LiquidityRemoved(who, asset_a, asset_b, amount_a, amount_b, liquidity));
@@ -288,8 +377,10 @@ pub mod pallet {
             asset_out: T::AssetId,
             amount_in: BalanceOf<T>,
             min_amount_out: BalanceOf<T>,
+            deadline: T::BlockNumber,
         ) -> DispatchResult {
             let who = ensure_signed(origin)?;
+            Self::ensure_deadline(deadline)?;
 
             // Ensure the asset pair is valid
             ensure!(asset_in != asset_out, Error::<T>::InvalidAssetPair);
@@ -297,85 +388,278 @@ pub mod pallet {
             // Ensure the amount is non-zero
             ensure!(amount_in > Zero::zero(), Error::<T>::InvalidAmount);
 
-            // Determine the correct order of assets
-            let (asset_a, asset_b, is_reversed) = if asset_in < asset_out {
-                (asset_in, asset_out, false)
-            } else {
-                (asset_out, asset_in, true)
-            };
+            let path = [asset_in, asset_out];
+            let amounts = Self::amounts_out(&path, amount_in)?;
 
-            // Fetch the current reserves
-            let (mut reserve_a, mut reserve_b) = LiquidityPools::<T>::get((asset_a, asset_b));
+            // Ensure minimum amount out is satisfied
+            ensure!(amounts[1] >= min_amount_out, Error::<T>::SlippageExceeded);
+
+            Self::do_swap(&who, &path, &amounts)
+        }
+
+        /// Swap exactly `amount_in` of the first asset of `path` for as much of
+        /// the last as the pools between them give
+        #[pallet::weight(10_000 * T::MaxPathLength::get() as u64)]
+        pub fn swap_exact_in_path(
+            origin: OriginFor<T>,
+            path: BoundedVec<T::AssetId, T::MaxPathLength>,
+            amount_in: BalanceOf<T>,
+            min_amount_out: BalanceOf<T>,
+            deadline: T::BlockNumber,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::ensure_deadline(deadline)?;
+
+            // Ensure the amount is non-zero
+            ensure!(amount_in > Zero::zero(), Error::<T>::InvalidAmount);
+
+            let amounts = Self::amounts_out(&path, amount_in)?;
+
+            // Ensure minimum amount out is satisfied
+            let amount_out = amounts.last().copied().ok_or(Error::<T>::InvalidPath)?;
+            ensure!(amount_out >= min_amount_out, Error::<T>::SlippageExceeded);
+
+            Self::do_swap(&who, &path, &amounts)
+        }
+
+        /// Swap as little of the first asset of `path` as needed for exactly
+        /// `amount_out` of the last
+        #[pallet::weight(10_000 * T::MaxPathLength::get() as u64)]
+        pub fn swap_exact_out_path(
+            origin: OriginFor<T>,
+            path: BoundedVec<T::AssetId, T::MaxPathLength>,
+            amount_out: BalanceOf<T>,
+            max_amount_in: BalanceOf<T>,
+            deadline: T::BlockNumber,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            Self::ensure_deadline(deadline)?;
+
+            // Ensure the amount is non-zero
+            ensure!(amount_out > Zero::zero(), Error::<T>::InvalidAmount);
+
+            let amounts = Self::amounts_in(&path, amount_out)?;
+
+            // Ensure maximum amount in is respected
+            let amount_in = amounts.first().copied().ok_or(Error::<T>::InvalidPath)?;
+            ensure!(amount_in <= max_amount_in, Error::<T>::SlippageExceeded);
+
+            Self::do_swap(&who, &path, &amounts)
+        }
+    }
+
+    impl<T: Config> Pallet<T> {
+        /// Get the account ID for the pallet
+        fn account_id() -> T::AccountId {
+            T::PalletId::get().into_account_truncating()
+        }
+
+        /// Keyless account holding every pool's `MinimumLiquidity`
+        pub fn locked_liquidity_account() -> T::AccountId {
+            T::PalletId::get().into_sub_account_truncating(b"locked")
+        }
+
+        /// Output of selling `amount_in` along `path`, `None` if a hop cannot be priced
+        pub fn quote_exact_in(path: Vec<T::AssetId>, amount_in: BalanceOf<T>) -> Option<BalanceOf<T>> {
+            Self::amounts_out(&path, amount_in).ok().and_then(|amounts| amounts.last().copied())
+        }
+
+        /// Input needed to buy `amount_out` along `path`, `None` if a hop cannot be priced
+        pub fn quote_exact_out(path: Vec<T::AssetId>, amount_out: BalanceOf<T>) -> Option<BalanceOf<T>> {
+            Self::amounts_in(&path, amount_out).ok().and_then(|amounts| amounts.first().copied())
+        }
+
+        /// Ensure the current block is not past `deadline`
+        fn ensure_deadline(deadline: T::BlockNumber) -> DispatchResult {
+            ensure!(frame_system::Pallet::<T>::block_number() <= deadline, Error::<T>::DeadlineExceeded);
+            Ok(())
+        }
+
+        /// Ensure `path` has between two and `MaxPathLength` assets and
+        /// visits none of them twice, so no pool is traded twice per swap
+        fn ensure_valid_path(path: &[T::AssetId]) -> DispatchResult {
+            ensure!(
+                path.len() >= 2 && path.len() <= T::MaxPathLength::get() as usize,
+                Error::<T>::InvalidPath
+            );
+            for (i, asset) in path.iter().enumerate() {
+                ensure!(!path[i + 1..].contains(asset), Error::<T>::InvalidPath);
+            }
+            Ok(())
+        }
+
+        /// Reserves of the pool between two assets, in trading direction
+        fn reserves(
+            asset_in: T::AssetId,
+            asset_out: T::AssetId,
+        ) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+            let ((asset_a, asset_b), is_reversed) = Self::sort_assets(asset_in, asset_out);
+            let (reserve_a, reserve_b) = LiquidityPools::<T>::get((asset_a, asset_b));
 
             // Ensure the pool exists
             ensure!(reserve_a > Zero::zero() && reserve_b > Zero::zero(), Error::<T>::PoolDoesNotExist);
 
-            // Apply the reserves based on the swap direction
-            let (reserve_in, reserve_out) = if is_reversed {
-                (reserve_b, reserve_a)
-            } else {
-                (reserve_a, reserve_b)
-            };
+            Ok(if is_reversed { (reserve_b, reserve_a) } else { (reserve_a, reserve_b) })
+        }
 
+        /// Amount out of a pool for `amount_in`, after the trading fee
+        fn get_amount_out(
+            amount_in: BalanceOf<T>,
+            reserve_in: BalanceOf<T>,
+            reserve_out: BalanceOf<T>,
+        ) -> Result<BalanceOf<T>, DispatchError> {
             // Calculate fee amount (0.369% of amount_in)
-            let fee_amount = T::TradingFee::mul_floor(amount_in);
+            let fee_amount = T::TradingFee::get().mul_floor(amount_in);
             let amount_in_after_fee = amount_in.saturating_sub(fee_amount);
 
             // Calculate amount out using the constant product formula: x * y = k
             // (reserve_in + amount_in_after_fee) * (reserve_out - amount_out) = reserve_in * reserve_out
-            // So: amount_out = reserve_out - (reserve_in * reserve_out) / (reserve_in + amount_in_after_fee)
-            let numerator = reserve_in
-                .checked_mul(&reserve_out)
-                .ok_or(Error::<T>::CalculationError)?;
+            // So: amount_out = reserve_out * amount_in_after_fee / (reserve_in + amount_in_after_fee),
+            // rounded down, which is always less than reserve_out: no trade can empty the pool
             let denominator = reserve_in
                 .checked_add(&amount_in_after_fee)
                 .ok_or(Error::<T>::CalculationError)?;
-            let new_reserve_out = numerator
-                .checked_div(&denominator)
-                .ok_or(Error::<T>::CalculationError)?;
-            let amount_out = reserve_out.saturating_sub(new_reserve_out);
+            Self::mul_div(reserve_out, amount_in_after_fee, denominator, false)
+        }
 
-            // Ensure minimum amount out is satisfied
-            ensure!(amount_out >= min_amount_out, Error::<T>::SlippageExceeded);
+        /// Amount into a pool, fee included, that `get_amount_out` turns into
+        /// at least `amount_out`
+        fn get_amount_in(
+            amount_out: BalanceOf<T>,
+            reserve_in: BalanceOf<T>,
+            reserve_out: BalanceOf<T>,
+        ) -> Result<BalanceOf<T>, DispatchError> {
+            // The pool can never be emptied of the asset being bought
+            ensure!(amount_out < reserve_out, Error::<T>::InsufficientLiquidity);
+
+            // The inverse of the constant product formula, rounded up:
+            // amount_in_after_fee = reserve_in * amount_out / (reserve_out - amount_out)
+            let amount_in_after_fee = Self::mul_div(reserve_in, amount_out, reserve_out - amount_out, true)?;
+
+            // Gross the amount up by the fee, rounded up
+            Ok(T::TradingFee::get().left_from_one().saturating_reciprocal_mul_ceil(amount_in_after_fee))
+        }
 
-            // Reserve (transfer) tokens from the user
-            T::Currency::transfer(
-                &who, 
-                &Self::account_id(), 
-                amount_in, 
-                ExistenceRequirement::KeepAlive
-            )?;
+        /// Amounts at every step of selling `amount_in` along `path`
+        fn amounts_out(path: &[T::AssetId], amount_in: BalanceOf<T>) -> Result<Vec<BalanceOf<T>>, DispatchError> {
+            Self::ensure_valid_path(path)?;
+            let mut amounts = Vec::with_capacity(path.len());
+            amounts.push(amount_in);
+            for hop in path.windows(2) {
+                let (reserve_in, reserve_out) = Self::reserves(hop[0], hop[1])?;
+                let amount_in = amounts[amounts.len() - 1];
+                amounts.push(Self::get_amount_out(amount_in, reserve_in, reserve_out)?);
+            }
+            Ok(amounts)
+        }
 
-            // Update the reserves
-            if is_reversed {
-                reserve_b = reserve_b.saturating_add(amount_in);
-                reserve_a = reserve_a.saturating_sub(amount_out);
-            } else {
-                reserve_a = reserve_a.saturating_add(amount_in);
-                reserve_b = reserve_b.saturating_sub(amount_out);
+        /// Amounts at every step of buying `amount_out` along `path`
+        fn amounts_in(path: &[T::AssetId], amount_out: BalanceOf<T>) -> Result<Vec<BalanceOf<T>>, DispatchError> {
+            Self::ensure_valid_path(path)?;
+            let mut amounts = Vec::with_capacity(path.len());
+            amounts.push(amount_out);
+            for hop in path.windows(2).rev() {
+                let (reserve_in, reserve_out) = Self::reserves(hop[0], hop[1])?;
+                let amount_out = amounts[amounts.len() - 1];
+                amounts.push(Self::get_amount_in(amount_out, reserve_in, reserve_out)?);
+            }
+            amounts.reverse();
+            Ok(amounts)
+        }
+
+        /// Trade `amounts` along `path` for `who`: take the first amount, move
+        /// every pool's reserves and pay out the last amount. Intermediate assets
+        /// never leave the pallet account, which holds every pool's reserves.
+        fn do_swap(who: &T::AccountId, path: &[T::AssetId], amounts: &[BalanceOf<T>]) -> DispatchResult {
+            let (first, last) = (path[0], path[path.len() - 1]);
+
+            // Take the input asset from the user
+            Self::transfer(first, who, &Self::account_id(), amounts[0], true)?;
+
+            for (i, hop) in path.windows(2).enumerate() {
+                let (asset_in, asset_out) = (hop[0], hop[1]);
+                let (amount_in, amount_out) = (amounts[i], amounts[i + 1]);
+
+                // Update the reserves
+                let ((asset_a, asset_b), is_reversed) = Self::sort_assets(asset_in, asset_out);
+                LiquidityPools::<T>::mutate((asset_a, asset_b), |reserves| {
+                    if is_reversed {
+                        reserves.1 = reserves.1.saturating_add(amount_in);
+                        reserves.0 = reserves.0.saturating_sub(amount_out);
+                    } else {
+                        reserves.0 = reserves.0.saturating_add(amount_in);
+                        reserves.1 = reserves.1.saturating_sub(amount_out);
+                    }
+                });
+
+                // Emit events
+                let fee_amount = T::TradingFee::get().mul_floor(amount_in);
+                Self::deposit_event(Event::Swap(who.clone(), asset_in, asset_out, amount_in, amount_out, fee_amount));
+                Self::deposit_event(Event::FeeCollected(asset_in, fee_amount));
             }
-            LiquidityPools::<T>::insert((asset_a, asset_b), (reserve_a, reserve_b));
 
             // Transfer the output assets to the user
-            T::Currency::transfer(
-                &Self::account_id(), 
-                &who, 
-                amount_out, 
-                ExistenceRequirement::AllowDeath
-            )?;
+            Self::transfer(last, &Self::account_id(), who, amounts[amounts.len() - 1], false)
+        }
+
+        /// `a * b / c`, rounded up with `round_up` and down otherwise. The product
+        /// is taken in 256 bits, where no two balances overflow it, 18-decimal
+        /// ones included; only a quotient that does not fit a balance is an error.
+        fn mul_div(
+            a: BalanceOf<T>,
+            b: BalanceOf<T>,
+            c: BalanceOf<T>,
+            round_up: bool,
+        ) -> Result<BalanceOf<T>, DispatchError> {
+            let divisor = Self::widen(c)?;
+            ensure!(!divisor.is_zero(), Error::<T>::CalculationError);
+            let (quotient, remainder) = (Self::widen(a)? * Self::widen(b)?).div_mod(divisor);
+            if round_up && !remainder.is_zero() {
+                Self::narrow(quotient + U256::one())
+            } else {
+                Self::narrow(quotient)
+            }
+        }
 
-            // Emit events
-            Self::deposit_event(Event::Swap(who, asset_in, asset_out, amount_in, amount_out, fee_amount));
-            Self::deposit_event(Event::FeeCollected(asset_in, fee_amount));
+        fn widen(amount: BalanceOf<T>) -> Result<U256, DispatchError> {
+            let amount: u128 = amount.try_into().map_err(|_| Error::<T>::CalculationError)?;
+            Ok(U256::from(amount))
+        }
 
-            Ok(())
+        fn narrow(amount: U256) -> Result<BalanceOf<T>, DispatchError> {
+            ensure!(amount <= U256::from(u128::MAX), Error::<T>::CalculationError);
+            BalanceOf::<T>::try_from(amount.low_u128()).map_err(|_| Error::<T>::CalculationError.into())
         }
-    }
 
-    impl<T: Config> Pallet<T> {
-        /// Get the account ID for the pallet
-        fn account_id() -> T::AccountId {
-            T::PalletId::get().into_account_truncating()
+        /// The pool key for a pair, and whether the pair was given in reverse
+        fn sort_assets(asset_a: T::AssetId, asset_b: T::AssetId) -> ((T::AssetId, T::AssetId), bool) {
+            if asset_a < asset_b {
+                ((asset_a, asset_b), false)
+            } else {
+                ((asset_b, asset_a), true)
+            }
+        }
+
+        /// Move `amount` of `asset` through `T::Currency` if it is the native
+        /// asset and through `T::Assets` otherwise. With `keep_alive` the
+        /// transfer may not reap `from`.
+        fn transfer(
+            asset: T::AssetId,
+            from: &T::AccountId,
+            to: &T::AccountId,
+            amount: BalanceOf<T>,
+            keep_alive: bool,
+        ) -> DispatchResult {
+            if asset == T::NativeAssetId::get() {
+                let existence = if keep_alive {
+                    ExistenceRequirement::KeepAlive
+                } else {
+                    ExistenceRequirement::AllowDeath
+                };
+                T::Currency::transfer(from, to, amount, existence)
+            } else {
+                T::Assets::transfer(asset, from, to, amount, keep_alive).map(|_| ())
+            }
         }
 
         /// Mint liquidity tokens to a user
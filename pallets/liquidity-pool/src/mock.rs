@@ -0,0 +1,171 @@
+// Test runtime: the native currency from pallet-balances, every other asset
+// from pallet-assets
+
+use crate as pallet_liquidity_pool;
+use frame_support::{
+    parameter_types,
+    traits::{AsEnsureOriginWithArg, ConstU32, ConstU64, ConstU128},
+    BoundedVec, PalletId,
+};
+use frame_system::{EnsureRoot, EnsureSigned};
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{AccountIdConversion, BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+pub type AccountId = u64;
+pub type Balance = u128;
+pub type AssetId = u32;
+
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system,
+        Balances: pallet_balances,
+        Assets: pallet_assets,
+        LiquidityPool: pallet_liquidity_pool,
+    }
+);
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = frame_support::traits::Everything;
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type RuntimeOrigin = RuntimeOrigin;
+    type RuntimeCall = RuntimeCall;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = AccountId;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type RuntimeEvent = RuntimeEvent;
+    type BlockHashCount = ConstU64<250>;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<Balance>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = ();
+    type OnSetCode = ();
+    type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+    type MaxLocks = ();
+    type MaxReserves = ();
+    type ReserveIdentifier = [u8; 8];
+    type Balance = Balance;
+    type RuntimeEvent = RuntimeEvent;
+    type DustRemoval = ();
+    type ExistentialDeposit = ConstU128<1>;
+    type AccountStore = System;
+    type WeightInfo = ();
+}
+
+impl pallet_assets::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type Balance = Balance;
+    type AssetId = AssetId;
+    type AssetIdParameter = codec::Compact<AssetId>;
+    type Currency = Balances;
+    type CreateOrigin = AsEnsureOriginWithArg<EnsureSigned<AccountId>>;
+    type ForceOrigin = EnsureRoot<AccountId>;
+    type AssetDeposit = ConstU128<0>;
+    type AssetAccountDeposit = ConstU128<0>;
+    type MetadataDepositBase = ConstU128<0>;
+    type MetadataDepositPerByte = ConstU128<0>;
+    type ApprovalDeposit = ConstU128<0>;
+    type StringLimit = ConstU32<50>;
+    type Freezer = ();
+    type Extra = ();
+    type RemoveItemsLimit = ConstU32<1000>;
+    type WeightInfo = ();
+    #[cfg(feature = "runtime-benchmarks")]
+    type BenchmarkHelper = ();
+}
+
+/// NRSH is the native currency of the test chain
+pub const NATIVE: AssetId = 0;
+pub const USDT: AssetId = 1984;
+pub const ELXR: AssetId = 2;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+pub const CHARLIE: AccountId = 3;
+
+/// Deadline for trades in tests that are not about deadlines
+pub const DEADLINE: u64 = 100;
+
+/// Every account starts with this much of the native currency and of each asset
+pub const INITIAL_BALANCE: Balance = 1_000_000_000;
+
+parameter_types! {
+    pub const NativeAssetId: AssetId = NATIVE;
+    pub const LiquidityPoolPalletId: PalletId = PalletId(*b"nrsh/lqp");
+    pub const TradingFee: sp_runtime::Perquintill = pallet_liquidity_pool::NOURISH_CHAIN_FEE;
+}
+
+impl pallet_liquidity_pool::Config for Test {
+    type RuntimeEvent = RuntimeEvent;
+    type Currency = Balances;
+    type AssetId = AssetId;
+    type Assets = Assets;
+    type NativeAssetId = NativeAssetId;
+    type PalletId = LiquidityPoolPalletId;
+    type TradingFee = TradingFee;
+    type MinimumLiquidity = ConstU128<1_000>;
+    type MaxPathLength = ConstU32<4>;
+}
+
+pub fn pool_account() -> AccountId {
+    LiquidityPoolPalletId::get().into_account_truncating()
+}
+
+/// Native balance, or the `pallet-assets` balance of any other asset
+pub fn balance(asset: AssetId, who: AccountId) -> Balance {
+    if asset == NATIVE {
+        Balances::free_balance(who)
+    } else {
+        Assets::balance(asset, who)
+    }
+}
+
+/// `assets` as the path argument of the path extrinsics
+pub fn bounded_path(assets: &[AssetId]) -> BoundedVec<AssetId, <Test as pallet_liquidity_pool::Config>::MaxPathLength> {
+    assets.to_vec().try_into().unwrap()
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    let accounts = [ALICE, BOB, CHARLIE];
+    pallet_balances::GenesisConfig::<Test> {
+        balances: accounts.iter().map(|who| (*who, INITIAL_BALANCE)).collect(),
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+    pallet_assets::GenesisConfig::<Test> {
+        assets: vec![(USDT, ALICE, true, 1), (ELXR, ALICE, true, 1)],
+        metadata: vec![],
+        accounts: [USDT, ELXR]
+            .iter()
+            .flat_map(|asset| accounts.iter().map(move |who| (*asset, *who, INITIAL_BALANCE)))
+            .collect(),
+    }
+    .assimilate_storage(&mut storage)
+    .unwrap();
+
+    let mut ext = sp_io::TestExternalities::new(storage);
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
@@ -0,0 +1,765 @@
+use crate::{mock::*, Config, Error, Event, LiquidityPools, LiquidityTokens, Pallet, PoolInfo, Pools, TotalLiquidity};
+use frame_support::{assert_noop, assert_ok, BoundedVec};
+
+#[test]
+fn pools_hold_the_native_currency_and_an_asset_separately() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            NATIVE,
+            USDT,
+            100_000,
+            200_000,
+            0,
+            DEADLINE
+        ));
+
+        assert_eq!(balance(NATIVE, ALICE), INITIAL_BALANCE - 100_000);
+        assert_eq!(balance(USDT, ALICE), INITIAL_BALANCE - 200_000);
+        assert_eq!(balance(NATIVE, pool_account()), 100_000);
+        assert_eq!(balance(USDT, pool_account()), 200_000);
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (100_000, 200_000));
+        // Nothing of the other asset moved
+        assert_eq!(balance(ELXR, ALICE), INITIAL_BALANCE);
+        assert_eq!(balance(ELXR, pool_account()), 0);
+    });
+}
+
+#[test]
+fn swaps_move_each_side_through_its_own_ledger() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            NATIVE,
+            USDT,
+            100_000,
+            200_000,
+            0,
+            DEADLINE
+        ));
+
+        // NRSH in, USDT out: 36 of the 10_000 in is the fee
+        assert_ok!(LiquidityPool::swap(RuntimeOrigin::signed(BOB), NATIVE, USDT, 10_000, 18_122, DEADLINE));
+        assert_eq!(balance(NATIVE, BOB), INITIAL_BALANCE - 10_000);
+        assert_eq!(balance(USDT, BOB), INITIAL_BALANCE + 18_122);
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (110_000, 181_878));
+
+        // USDT in, NRSH out
+        assert_ok!(LiquidityPool::swap(RuntimeOrigin::signed(CHARLIE), USDT, NATIVE, 10_000, 0, DEADLINE));
+        let (reserve_native, reserve_usdt) = LiquidityPools::<Test>::get((NATIVE, USDT));
+        assert_eq!(reserve_usdt, 191_878);
+        assert_eq!(balance(USDT, CHARLIE), INITIAL_BALANCE - 10_000);
+        assert_eq!(balance(NATIVE, CHARLIE), INITIAL_BALANCE + (110_000 - reserve_native));
+
+        // The pool account holds exactly the reserves
+        assert_eq!(balance(NATIVE, pool_account()), reserve_native);
+        assert_eq!(balance(USDT, pool_account()), reserve_usdt);
+    });
+}
+
+#[test]
+fn pools_of_two_assets_leave_the_native_currency_alone() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), ELXR, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            ELXR,
+            USDT,
+            50_000,
+            50_000,
+            0,
+            DEADLINE
+        ));
+        assert_ok!(LiquidityPool::swap(RuntimeOrigin::signed(BOB), ELXR, USDT, 1_000, 0, DEADLINE));
+        assert_ok!(LiquidityPool::remove_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            ELXR,
+            USDT,
+            LiquidityTokens::<Test>::get((ELXR, USDT, ALICE)),
+            0,
+            0,
+            DEADLINE
+        ));
+
+        assert_eq!(balance(NATIVE, ALICE), INITIAL_BALANCE);
+        assert_eq!(balance(NATIVE, BOB), INITIAL_BALANCE);
+        assert_eq!(balance(NATIVE, pool_account()), 0);
+        // What Alice did not take back is the locked minimum's share of the pool
+        let (reserve_elxr, reserve_usdt) = LiquidityPools::<Test>::get((ELXR, USDT));
+        assert_eq!(balance(ELXR, pool_account()), reserve_elxr);
+        assert_eq!(balance(USDT, pool_account()), reserve_usdt);
+        assert_eq!(balance(ELXR, ALICE) + balance(ELXR, BOB) + reserve_elxr, 2 * INITIAL_BALANCE);
+        assert_eq!(balance(USDT, ALICE) + balance(USDT, BOB) + reserve_usdt, 2 * INITIAL_BALANCE);
+        // Alice leaves with most of Bob's 1_000 ELXR
+        assert!(balance(ELXR, ALICE) > INITIAL_BALANCE);
+    });
+}
+
+#[test]
+fn removing_liquidity_returns_both_assets() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            NATIVE,
+            USDT,
+            100_000,
+            200_000,
+            0,
+            DEADLINE
+        ));
+        // sqrt(100_000 * 200_000) shares, less the locked 1_000
+        let shares = LiquidityTokens::<Test>::get((NATIVE, USDT, ALICE));
+        assert_eq!(shares, 140_421);
+        assert_ok!(LiquidityPool::remove_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            NATIVE,
+            USDT,
+            shares,
+            99_292,
+            198_585,
+            DEADLINE
+        ));
+
+        assert_eq!(balance(NATIVE, ALICE), INITIAL_BALANCE - 708);
+        assert_eq!(balance(USDT, ALICE), INITIAL_BALANCE - 1_415);
+        assert_eq!(balance(NATIVE, pool_account()), 708);
+        assert_eq!(balance(USDT, pool_account()), 1_415);
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (708, 1_415));
+    });
+}
+
+#[test]
+fn deposits_need_a_balance_of_each_asset() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+        assert_noop!(
+            LiquidityPool::add_liquidity(RuntimeOrigin::signed(ALICE), NATIVE, USDT, 100, INITIAL_BALANCE + 1, 0, DEADLINE),
+            pallet_assets::Error::<Test>::BalanceLow
+        );
+        assert_eq!(balance(NATIVE, ALICE), INITIAL_BALANCE);
+        assert_noop!(
+            LiquidityPool::swap(RuntimeOrigin::signed(BOB), NATIVE, USDT, 100, 0, DEADLINE),
+            Error::<Test>::PoolDoesNotExist
+        );
+    });
+}
+
+#[test]
+fn pools_are_created_once_for_either_order() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(7);
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(BOB), USDT, NATIVE));
+        System::assert_last_event(Event::<Test>::PoolCreated(BOB, NATIVE, USDT).into());
+        assert_eq!(Pools::<Test>::get((NATIVE, USDT)), Some(PoolInfo { creator: BOB, created_at: 7 }));
+        assert_eq!(Pools::<Test>::get((USDT, NATIVE)), None);
+
+        assert_noop!(
+            LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT),
+            Error::<Test>::PoolAlreadyExists
+        );
+        assert_noop!(
+            LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), USDT, NATIVE),
+            Error::<Test>::PoolAlreadyExists
+        );
+        assert_noop!(
+            LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), USDT, USDT),
+            Error::<Test>::InvalidAssetPair
+        );
+    });
+}
+
+#[test]
+fn liquidity_needs_a_created_pool() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            LiquidityPool::add_liquidity(RuntimeOrigin::signed(ALICE), NATIVE, USDT, 100_000, 200_000, 0, DEADLINE),
+            Error::<Test>::PoolDoesNotExist
+        );
+        assert_noop!(
+            LiquidityPool::add_liquidity(RuntimeOrigin::signed(ALICE), USDT, NATIVE, 200_000, 100_000, 0, DEADLINE),
+            Error::<Test>::PoolDoesNotExist
+        );
+    });
+}
+
+#[test]
+fn liquidity_added_in_either_order_lands_in_the_same_pool() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            NATIVE,
+            USDT,
+            100_000,
+            200_000,
+            0,
+            DEADLINE
+        ));
+        // Bob lists the same pair the other way round, amounts following the assets
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(BOB),
+            USDT,
+            NATIVE,
+            20_000,
+            10_000,
+            0,
+            DEADLINE
+        ));
+
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (110_000, 220_000));
+        assert_eq!(LiquidityPools::<Test>::get((USDT, NATIVE)), (0, 0));
+        assert_eq!(balance(NATIVE, BOB), INITIAL_BALANCE - 10_000);
+        assert_eq!(balance(USDT, BOB), INITIAL_BALANCE - 20_000);
+        assert_eq!(LiquidityTokens::<Test>::get((NATIVE, USDT, BOB)), 14_142);
+        assert_eq!(TotalLiquidity::<Test>::get((NATIVE, USDT)), 155_563);
+        System::assert_last_event(Event::<Test>::LiquidityAdded(BOB, NATIVE, USDT, 10_000, 20_000, 14_142).into());
+
+        // Swaps in both directions read the reserves Bob added to
+        assert_ok!(LiquidityPool::swap(RuntimeOrigin::signed(CHARLIE), USDT, NATIVE, 1_000, 1, DEADLINE));
+        assert_ok!(LiquidityPool::swap(RuntimeOrigin::signed(CHARLIE), NATIVE, USDT, 1_000, 1, DEADLINE));
+    });
+}
+
+#[test]
+fn liquidity_removed_in_either_order_checks_the_matching_minimums() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), USDT, NATIVE));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            USDT,
+            NATIVE,
+            200_000,
+            100_000,
+            0,
+            DEADLINE
+        ));
+        let shares = LiquidityTokens::<Test>::get((NATIVE, USDT, ALICE));
+        assert_eq!(shares, 140_421);
+
+        // A quarter of Alice's shares is worth 49_646 USDT and 24_823 NRSH,
+        // so these minimums only hold in the order they are given
+        assert_noop!(
+            LiquidityPool::remove_liquidity(RuntimeOrigin::signed(ALICE), NATIVE, USDT, shares / 4, 49_646, 24_823, DEADLINE),
+            Error::<Test>::SlippageExceeded
+        );
+        assert_ok!(LiquidityPool::remove_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            USDT,
+            NATIVE,
+            shares / 4,
+            49_646,
+            24_823,
+            DEADLINE
+        ));
+        assert_ok!(LiquidityPool::remove_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            NATIVE,
+            USDT,
+            shares / 4,
+            24_823,
+            49_646,
+            DEADLINE
+        ));
+
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (50_354, 100_708));
+        assert_eq!(balance(NATIVE, ALICE), INITIAL_BALANCE - 50_354);
+        assert_eq!(balance(USDT, ALICE), INITIAL_BALANCE - 100_708);
+    });
+}
+
+#[test]
+fn first_deposits_mint_the_geometric_mean_and_lock_the_minimum() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            NATIVE,
+            USDT,
+            100_000,
+            400_000,
+            0,
+            DEADLINE
+        ));
+
+        let locked = Pallet::<Test>::locked_liquidity_account();
+        assert_eq!(TotalLiquidity::<Test>::get((NATIVE, USDT)), 200_000);
+        assert_eq!(LiquidityTokens::<Test>::get((NATIVE, USDT, ALICE)), 199_000);
+        assert_eq!(LiquidityTokens::<Test>::get((NATIVE, USDT, locked)), 1_000);
+        System::assert_last_event(Event::<Test>::LiquidityAdded(ALICE, NATIVE, USDT, 100_000, 400_000, 199_000).into());
+
+        // Later deposits lock nothing more
+        assert_ok!(LiquidityPool::add_liquidity(RuntimeOrigin::signed(BOB), NATIVE, USDT, 10_000, 40_000, 0, DEADLINE));
+        assert_eq!(LiquidityTokens::<Test>::get((NATIVE, USDT, BOB)), 20_000);
+        assert_eq!(LiquidityTokens::<Test>::get((NATIVE, USDT, locked)), 1_000);
+    });
+}
+
+#[test]
+fn first_deposits_must_exceed_the_locked_minimum() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+        for (amount_native, amount_usdt) in [(1, 1), (1_000, 1_000), (1, 1_000_000), (1_000_001, 1)] {
+            assert_noop!(
+                LiquidityPool::add_liquidity(RuntimeOrigin::signed(ALICE), NATIVE, USDT, amount_native, amount_usdt, 0, DEADLINE),
+                Error::<Test>::InsufficientLiquidity
+            );
+        }
+        assert_ok!(LiquidityPool::add_liquidity(RuntimeOrigin::signed(ALICE), NATIVE, USDT, 1_001, 1_001, 0, DEADLINE));
+        assert_eq!(LiquidityTokens::<Test>::get((NATIVE, USDT, ALICE)), 1);
+    });
+}
+
+#[test]
+fn deposits_that_mint_no_shares_are_rejected() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            NATIVE,
+            USDT,
+            100_000,
+            200_000,
+            0,
+            DEADLINE
+        ));
+        // One unit of USDT is worth less than a share
+        assert_noop!(
+            LiquidityPool::add_liquidity(RuntimeOrigin::signed(BOB), NATIVE, USDT, 1, 1, 0, DEADLINE),
+            Error::<Test>::InsufficientLiquidity
+        );
+    });
+}
+
+#[test]
+fn deposits_take_only_what_matches_the_pool_ratio() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            NATIVE,
+            USDT,
+            100_000,
+            200_000,
+            0,
+            DEADLINE
+        ));
+
+        // Too much USDT: all the NRSH and twice as much USDT are taken
+        assert_ok!(LiquidityPool::add_liquidity(RuntimeOrigin::signed(BOB), NATIVE, USDT, 10_000, 50_000, 0, DEADLINE));
+        System::assert_last_event(Event::<Test>::LiquidityAdded(BOB, NATIVE, USDT, 10_000, 20_000, 14_142).into());
+        // Too much NRSH: all the USDT and half as much NRSH are taken
+        assert_ok!(LiquidityPool::add_liquidity(RuntimeOrigin::signed(BOB), NATIVE, USDT, 30_000, 20_000, 0, DEADLINE));
+        System::assert_last_event(Event::<Test>::LiquidityAdded(BOB, NATIVE, USDT, 10_000, 20_000, 14_142).into());
+
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (120_000, 240_000));
+        assert_eq!(balance(NATIVE, BOB), INITIAL_BALANCE - 20_000);
+        assert_eq!(balance(USDT, BOB), INITIAL_BALANCE - 40_000);
+        assert_eq!(balance(NATIVE, pool_account()), 120_000);
+        assert_eq!(balance(USDT, pool_account()), 240_000);
+        assert_eq!(LiquidityTokens::<Test>::get((NATIVE, USDT, BOB)), 28_284);
+    });
+}
+
+#[test]
+fn eighteen_decimal_amounts_do_not_overflow() {
+    new_test_ext().execute_with(|| {
+        const UNIT: Balance = 1_000_000_000_000_000_000;
+        for asset in [USDT, ELXR] {
+            for who in [ALICE, BOB] {
+                assert_ok!(Assets::mint(RuntimeOrigin::signed(ALICE), asset.into(), who, 10_000_000 * UNIT));
+            }
+        }
+
+        // The products of these amounts are far beyond u128
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), ELXR, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            ELXR,
+            USDT,
+            1_000_000 * UNIT,
+            4_000_000 * UNIT,
+            0,
+            DEADLINE
+        ));
+        assert_eq!(TotalLiquidity::<Test>::get((ELXR, USDT)), 2_000_000 * UNIT);
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(BOB),
+            ELXR,
+            USDT,
+            500_000 * UNIT,
+            2_000_000 * UNIT,
+            1_000_000 * UNIT,
+            DEADLINE
+        ));
+        assert_eq!(LiquidityTokens::<Test>::get((ELXR, USDT, BOB)), 1_000_000 * UNIT);
+
+        // 6_000_000 USDT * 9_963.1 ELXR / 1_509_963.1 ELXR
+        assert_eq!(
+            LiquidityPool::quote_exact_in(vec![ELXR, USDT], 10_000 * UNIT),
+            Some(39_589_444_271_850_086_932_587)
+        );
+        assert_ok!(LiquidityPool::swap(RuntimeOrigin::signed(BOB), ELXR, USDT, 10_000 * UNIT, 0, DEADLINE));
+        assert_eq!(
+            LiquidityPools::<Test>::get((ELXR, USDT)),
+            (1_510_000 * UNIT, 5_960_410_555_728_149_913_067_413)
+        );
+
+        // A third of the supply takes a third of each reserve
+        assert_ok!(LiquidityPool::remove_liquidity(
+            RuntimeOrigin::signed(BOB),
+            ELXR,
+            USDT,
+            1_000_000 * UNIT,
+            0,
+            0,
+            DEADLINE
+        ));
+        System::assert_last_event(
+            Event::<Test>::LiquidityRemoved(
+                BOB,
+                ELXR,
+                USDT,
+                503_333_333_333_333_333_333_333,
+                1_986_803_518_576_049_971_022_471,
+                1_000_000 * UNIT,
+            )
+            .into(),
+        );
+    });
+}
+
+#[test]
+fn the_locked_minimum_keeps_emptied_pools_priced() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            NATIVE,
+            USDT,
+            100_000,
+            400_000,
+            0,
+            DEADLINE
+        ));
+        let shares = LiquidityTokens::<Test>::get((NATIVE, USDT, ALICE));
+        assert_ok!(LiquidityPool::remove_liquidity(RuntimeOrigin::signed(ALICE), NATIVE, USDT, shares, 0, 0, DEADLINE));
+
+        // Every provider left, yet the pool keeps its 1:4 price
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (500, 2_000));
+        assert_eq!(TotalLiquidity::<Test>::get((NATIVE, USDT)), 1_000);
+
+        // and the next depositor joins at that price
+        assert_ok!(LiquidityPool::add_liquidity(RuntimeOrigin::signed(BOB), NATIVE, USDT, 5_000, 20_000, 10_000, DEADLINE));
+        assert_eq!(LiquidityTokens::<Test>::get((NATIVE, USDT, BOB)), 10_000);
+        assert_ok!(LiquidityPool::swap(RuntimeOrigin::signed(CHARLIE), NATIVE, USDT, 100, 1, DEADLINE));
+    });
+}
+
+#[test]
+fn moving_the_price_no_longer_dilutes_the_next_depositor() {
+    new_test_ext().execute_with(|| {
+        // Bob seeds the pool and swaps it far away from its opening price
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(BOB), NATIVE, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(RuntimeOrigin::signed(BOB), NATIVE, USDT, 10_000, 10_000, 0, DEADLINE));
+        assert_ok!(LiquidityPool::swap(RuntimeOrigin::signed(BOB), NATIVE, USDT, 30_000, 0, DEADLINE));
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (40_000, 2_507));
+
+        // Charlie deposits four times the reserves at the current price
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(CHARLIE),
+            NATIVE,
+            USDT,
+            160_000,
+            10_028,
+            0,
+            DEADLINE
+        ));
+        let bob_shares = LiquidityTokens::<Test>::get((NATIVE, USDT, BOB));
+        assert_ok!(LiquidityPool::remove_liquidity(RuntimeOrigin::signed(BOB), NATIVE, USDT, bob_shares, 0, 0, DEADLINE));
+        let charlie_shares = LiquidityTokens::<Test>::get((NATIVE, USDT, CHARLIE));
+        assert_ok!(LiquidityPool::remove_liquidity(
+            RuntimeOrigin::signed(CHARLIE),
+            NATIVE,
+            USDT,
+            charlie_shares,
+            0,
+            0,
+            DEADLINE
+        ));
+
+        // When shares were priced off the reserve ratio instead of the supply,
+        // Charlie got a fraction of the 40_000 shares for this deposit and
+        // withdrew only part of the 160_000 NRSH, the rest going to Bob
+        assert_eq!(charlie_shares, 40_000);
+        assert_eq!(balance(NATIVE, CHARLIE), INITIAL_BALANCE);
+        assert_eq!(balance(USDT, CHARLIE), INITIAL_BALANCE);
+        assert!(balance(NATIVE, BOB) < INITIAL_BALANCE);
+        assert!(balance(USDT, BOB) < INITIAL_BALANCE);
+    });
+}
+
+#[test]
+fn first_depositor_cannot_skim_later_deposits() {
+    new_test_ext().execute_with(|| {
+        // Bob opens the pool with the smallest deposit that mints anything: one
+        // share for him, 1_000 locked
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(BOB), NATIVE, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(RuntimeOrigin::signed(BOB), NATIVE, USDT, 1_001, 1_001, 0, DEADLINE));
+        assert_eq!(LiquidityTokens::<Test>::get((NATIVE, USDT, BOB)), 1);
+
+        // and pushes the price as far as a million NRSH will take it
+        assert_ok!(LiquidityPool::swap(RuntimeOrigin::signed(BOB), NATIVE, USDT, 1_000_000, 0, DEADLINE));
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (1_001_001, 2));
+
+        // Charlie still expects the opening price: at the pushed one his 100_000
+        // NRSH are worth no USDT at all and would mint nothing, so it is refused
+        assert_noop!(
+            LiquidityPool::add_liquidity(
+                RuntimeOrigin::signed(CHARLIE),
+                NATIVE,
+                USDT,
+                100_000,
+                100_000,
+                100_000,
+                DEADLINE
+            ),
+            Error::<Test>::InsufficientLiquidity
+        );
+
+        // Offering more USDT than the current price asks for, only the 20 that
+        // match his NRSH are taken; he gets his exact share of the pool and
+        // withdraws all of it
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(CHARLIE),
+            NATIVE,
+            USDT,
+            10_010_010,
+            100_000,
+            10_010,
+            DEADLINE
+        ));
+        System::assert_last_event(Event::<Test>::LiquidityAdded(CHARLIE, NATIVE, USDT, 10_010_010, 20, 10_010).into());
+        assert_eq!(balance(USDT, CHARLIE), INITIAL_BALANCE - 20);
+        assert_eq!(LiquidityTokens::<Test>::get((NATIVE, USDT, CHARLIE)), 10_010);
+        assert_ok!(LiquidityPool::remove_liquidity(
+            RuntimeOrigin::signed(CHARLIE),
+            NATIVE,
+            USDT,
+            10_010,
+            10_010_010,
+            20,
+            DEADLINE
+        ));
+        assert_eq!(balance(NATIVE, CHARLIE), INITIAL_BALANCE);
+        assert_eq!(balance(USDT, CHARLIE), INITIAL_BALANCE);
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (1_001_001, 2));
+    });
+}
+
+fn seed_route() {
+    // NRSH -> USDT -> ELXR, ELXR being worth half a USDT
+    assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+    assert_ok!(LiquidityPool::add_liquidity(
+        RuntimeOrigin::signed(ALICE),
+        NATIVE,
+        USDT,
+        100_000,
+        200_000,
+        0,
+        DEADLINE
+    ));
+    assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), USDT, ELXR));
+    assert_ok!(LiquidityPool::add_liquidity(
+        RuntimeOrigin::signed(ALICE),
+        USDT,
+        ELXR,
+        150_000,
+        300_000,
+        0,
+        DEADLINE
+    ));
+}
+
+#[test]
+fn trades_past_their_deadline_are_rejected() {
+    new_test_ext().execute_with(|| {
+        seed_route();
+        System::set_block_number(DEADLINE + 1);
+
+        assert_noop!(
+            LiquidityPool::add_liquidity(RuntimeOrigin::signed(BOB), NATIVE, USDT, 1_000, 2_000, 0, DEADLINE),
+            Error::<Test>::DeadlineExceeded
+        );
+        assert_noop!(
+            LiquidityPool::remove_liquidity(RuntimeOrigin::signed(ALICE), NATIVE, USDT, 1_000, 0, 0, DEADLINE),
+            Error::<Test>::DeadlineExceeded
+        );
+        assert_noop!(
+            LiquidityPool::swap(RuntimeOrigin::signed(BOB), NATIVE, USDT, 1_000, 0, DEADLINE),
+            Error::<Test>::DeadlineExceeded
+        );
+        assert_noop!(
+            LiquidityPool::swap_exact_in_path(
+                RuntimeOrigin::signed(BOB),
+                bounded_path(&[NATIVE, USDT, ELXR]),
+                1_000,
+                0,
+                DEADLINE
+            ),
+            Error::<Test>::DeadlineExceeded
+        );
+        assert_noop!(
+            LiquidityPool::swap_exact_out_path(
+                RuntimeOrigin::signed(BOB),
+                bounded_path(&[NATIVE, USDT, ELXR]),
+                1_000,
+                u128::MAX,
+                DEADLINE
+            ),
+            Error::<Test>::DeadlineExceeded
+        );
+
+        // The deadline block itself is still in time
+        assert_ok!(LiquidityPool::swap(RuntimeOrigin::signed(BOB), NATIVE, USDT, 1_000, 0, DEADLINE + 1));
+    });
+}
+
+#[test]
+fn exact_in_paths_trade_through_every_pool() {
+    new_test_ext().execute_with(|| {
+        seed_route();
+        let path = bounded_path(&[NATIVE, USDT, ELXR]);
+        assert_eq!(LiquidityPool::quote_exact_in(path.to_vec(), 10_000), Some(32_232));
+
+        assert_noop!(
+            LiquidityPool::swap_exact_in_path(RuntimeOrigin::signed(BOB), path.clone(), 10_000, 32_233, DEADLINE),
+            Error::<Test>::SlippageExceeded
+        );
+        assert_ok!(LiquidityPool::swap_exact_in_path(RuntimeOrigin::signed(BOB), path, 10_000, 32_232, DEADLINE));
+
+        // Each hop is the single swap: 18_122 USDT, then sold for 32_232 ELXR
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (110_000, 181_878));
+        assert_eq!(LiquidityPools::<Test>::get((ELXR, USDT)), (267_768, 168_122));
+        System::assert_has_event(Event::<Test>::Swap(BOB, NATIVE, USDT, 10_000, 18_122, 36).into());
+        System::assert_has_event(Event::<Test>::Swap(BOB, USDT, ELXR, 18_122, 32_232, 66).into());
+
+        // Bob never held the USDT in between
+        assert_eq!(balance(NATIVE, BOB), INITIAL_BALANCE - 10_000);
+        assert_eq!(balance(USDT, BOB), INITIAL_BALANCE);
+        assert_eq!(balance(ELXR, BOB), INITIAL_BALANCE + 32_232);
+        assert_eq!(balance(USDT, pool_account()), 181_878 + 168_122);
+    });
+}
+
+#[test]
+fn exact_out_paths_buy_exactly_the_amount_asked() {
+    new_test_ext().execute_with(|| {
+        seed_route();
+        let path = bounded_path(&[NATIVE, USDT, ELXR]);
+        assert_eq!(LiquidityPool::quote_exact_out(path.to_vec(), 20_000), Some(5_706));
+        // The quoted input sells for at least the amount asked
+        assert!(LiquidityPool::quote_exact_in(path.to_vec(), 5_706).unwrap() >= 20_000);
+
+        assert_noop!(
+            LiquidityPool::swap_exact_out_path(RuntimeOrigin::signed(BOB), path.clone(), 20_000, 5_705, DEADLINE),
+            Error::<Test>::SlippageExceeded
+        );
+        assert_ok!(LiquidityPool::swap_exact_out_path(RuntimeOrigin::signed(BOB), path, 20_000, 5_706, DEADLINE));
+
+        assert_eq!(balance(NATIVE, BOB), INITIAL_BALANCE - 5_706);
+        assert_eq!(balance(USDT, BOB), INITIAL_BALANCE);
+        assert_eq!(balance(ELXR, BOB), INITIAL_BALANCE + 20_000);
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (105_706, 189_245));
+        assert_eq!(LiquidityPools::<Test>::get((ELXR, USDT)), (280_000, 160_755));
+    });
+}
+
+#[test]
+fn exact_out_swaps_cannot_empty_a_pool() {
+    new_test_ext().execute_with(|| {
+        seed_route();
+        assert_eq!(LiquidityPool::quote_exact_out(vec![NATIVE, USDT], 200_000), None);
+        assert_noop!(
+            LiquidityPool::swap_exact_out_path(
+                RuntimeOrigin::signed(BOB),
+                bounded_path(&[NATIVE, USDT]),
+                200_000,
+                u128::MAX,
+                DEADLINE
+            ),
+            Error::<Test>::InsufficientLiquidity
+        );
+    });
+}
+
+#[test]
+fn exact_in_swaps_cannot_empty_a_pool() {
+    new_test_ext().execute_with(|| {
+        // Only the locked minimum is left: 500 NRSH against 2_000 USDT
+        assert_ok!(LiquidityPool::create_pool(RuntimeOrigin::signed(ALICE), NATIVE, USDT));
+        assert_ok!(LiquidityPool::add_liquidity(
+            RuntimeOrigin::signed(ALICE),
+            NATIVE,
+            USDT,
+            100_000,
+            400_000,
+            0,
+            DEADLINE
+        ));
+        let shares = LiquidityTokens::<Test>::get((NATIVE, USDT, ALICE));
+        assert_ok!(LiquidityPool::remove_liquidity(RuntimeOrigin::signed(ALICE), NATIVE, USDT, shares, 0, 0, DEADLINE));
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (500, 2_000));
+
+        // Selling far more than the reserves buys all but the last unit
+        let quote = LiquidityPool::quote_exact_in(vec![NATIVE, USDT], 1_004_200).unwrap();
+        assert_eq!(quote, 1_999);
+        assert_ok!(LiquidityPool::swap(RuntimeOrigin::signed(CHARLIE), NATIVE, USDT, 1_004_200, 0, DEADLINE));
+        assert_eq!(balance(USDT, CHARLIE), INITIAL_BALANCE + 1_999);
+        assert_eq!(LiquidityPools::<Test>::get((NATIVE, USDT)), (1_004_700, 1));
+        // and no amount buys that one
+        assert_eq!(LiquidityPool::quote_exact_in(vec![NATIVE, USDT], u128::MAX / 2), Some(0));
+    });
+}
+
+#[test]
+fn paths_must_be_short_and_visit_each_asset_once() {
+    new_test_ext().execute_with(|| {
+        seed_route();
+        const OTHER: AssetId = 7;
+        for path in [vec![], vec![NATIVE], vec![NATIVE, USDT, NATIVE]] {
+            assert_eq!(LiquidityPool::quote_exact_in(path.clone(), 1_000), None);
+            assert_eq!(LiquidityPool::quote_exact_out(path.clone(), 1_000), None);
+            let path = bounded_path(&path);
+            assert_noop!(
+                LiquidityPool::swap_exact_in_path(RuntimeOrigin::signed(BOB), path.clone(), 1_000, 0, DEADLINE),
+                Error::<Test>::InvalidPath
+            );
+            assert_noop!(
+                LiquidityPool::swap_exact_out_path(RuntimeOrigin::signed(BOB), path, 1_000, u128::MAX, DEADLINE),
+                Error::<Test>::InvalidPath
+            );
+        }
+
+        // A path longer than `MaxPathLength` cannot even be passed to the extrinsics
+        let long = vec![NATIVE, USDT, ELXR, OTHER, NATIVE];
+        assert_eq!(LiquidityPool::quote_exact_in(long.clone(), 1_000), None);
+        assert_eq!(LiquidityPool::quote_exact_out(long.clone(), 1_000), None);
+        assert!(BoundedVec::<AssetId, <Test as Config>::MaxPathLength>::try_from(long).is_err());
+
+        // A hop without a pool
+        assert_eq!(LiquidityPool::quote_exact_in(vec![NATIVE, ELXR], 1_000), None);
+        assert_noop!(
+            LiquidityPool::swap_exact_in_path(
+                RuntimeOrigin::signed(BOB),
+                bounded_path(&[NATIVE, ELXR]),
+                1_000,
+                0,
+                DEADLINE
+            ),
+            Error::<Test>::PoolDoesNotExist
+        );
+    });
+}
@@ -6,8 +6,264 @@ use ink_storage::{
 };
 use pqc_kyber::*;
 use pqc_dilithium::*;
+use oracle_consumer::{OracleConsumer, PriceData};
 use scale::{Decode, Encode};
 
+/// Storage proof verification against a Substrate state root.
+/// Decodes the Merkle-Patricia trie nodes used by Substrate (`sp-trie` LayoutV0/V1)
+/// and walks them from the root to the storage key.
+mod trie {
+    use ink_prelude::vec::Vec;
+
+    pub type Hash = [u8; 32];
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum ProofError {
+        /// A node referenced on the path to the key is not in the proof
+        IncompleteProof,
+        /// A proof node could not be decoded
+        InvalidNode,
+    }
+
+    enum NodeValue<'a> {
+        Inline(&'a [u8]),
+        Hashed(Hash),
+    }
+
+    enum Child<'a> {
+        Inline(&'a [u8]),
+        Hashed(Hash),
+    }
+
+    enum Node<'a> {
+        Empty,
+        Leaf {
+            partial: Nibbles<'a>,
+            value: NodeValue<'a>,
+        },
+        Branch {
+            partial: Nibbles<'a>,
+            value: Option<NodeValue<'a>>,
+            children: [Option<Child<'a>>; 16],
+        },
+    }
+
+    #[derive(Clone, Copy)]
+    struct Nibbles<'a> {
+        data: &'a [u8],
+        // Number of leading padding nibbles (0 or 1)
+        offset: usize,
+        len: usize,
+    }
+
+    impl<'a> Nibbles<'a> {
+        fn at(&self, i: usize) -> u8 {
+            let n = self.offset + i;
+            if n % 2 == 0 {
+                self.data[n / 2] >> 4
+            } else {
+                self.data[n / 2] & 0x0f
+            }
+        }
+    }
+
+    struct Input<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl<'a> Input<'a> {
+        fn take(&mut self, n: usize) -> Result<&'a [u8], ProofError> {
+            let end = self.pos.checked_add(n).ok_or(ProofError::InvalidNode)?;
+            let out = self.data.get(self.pos..end).ok_or(ProofError::InvalidNode)?;
+            self.pos = end;
+            Ok(out)
+        }
+
+        fn byte(&mut self) -> Result<u8, ProofError> {
+            Ok(self.take(1)?[0])
+        }
+
+        fn hash(&mut self) -> Result<Hash, ProofError> {
+            let mut out = [0u8; 32];
+            out.copy_from_slice(self.take(32)?);
+            Ok(out)
+        }
+
+        // SCALE compact length prefix
+        fn compact_len(&mut self) -> Result<usize, ProofError> {
+            let first = self.byte()?;
+            let len = match first & 0b11 {
+                0b00 => (first >> 2) as usize,
+                0b01 => (u16::from_le_bytes([first, self.byte()?]) >> 2) as usize,
+                0b10 => {
+                    let rest = self.take(3)?;
+                    (u32::from_le_bytes([first, rest[0], rest[1], rest[2]]) >> 2) as usize
+                }
+                _ => return Err(ProofError::InvalidNode),
+            };
+            Ok(len)
+        }
+
+        fn bytes(&mut self) -> Result<&'a [u8], ProofError> {
+            let len = self.compact_len()?;
+            self.take(len)
+        }
+
+        // Nibble count packed in the low bits of the header byte, continued
+        // with 255-valued bytes when it does not fit
+        fn nibble_count(&mut self, header: u8, bits: u32) -> Result<usize, ProofError> {
+            let max = (1u8 << bits) - 1;
+            let mut count = (header & max) as usize;
+            if count < max as usize {
+                return Ok(count);
+            }
+            loop {
+                let next = self.byte()?;
+                count += next as usize;
+                if next < 255 {
+                    return Ok(count);
+                }
+            }
+        }
+
+        fn partial(&mut self, len: usize) -> Result<Nibbles<'a>, ProofError> {
+            let data = self.take((len + 1) / 2)?;
+            let offset = len % 2;
+            if offset == 1 && data[0] & 0xf0 != 0 {
+                return Err(ProofError::InvalidNode);
+            }
+            Ok(Nibbles { data, offset, len })
+        }
+    }
+
+    fn decode_node(data: &[u8]) -> Result<Node<'_>, ProofError> {
+        let mut input = Input { data, pos: 0 };
+        let header = input.byte()?;
+
+        let node = match header >> 6 {
+            0b01 => {
+                let len = input.nibble_count(header, 6)?;
+                Node::Leaf { partial: input.partial(len)?, value: NodeValue::Inline(input.bytes()?) }
+            }
+            0b10 | 0b11 => {
+                let len = input.nibble_count(header, 6)?;
+                let has_value = header >> 6 == 0b11;
+                decode_branch(&mut input, len, has_value, false)?
+            }
+            _ if header == 0 => Node::Empty,
+            _ if header >> 5 == 0b001 => {
+                let len = input.nibble_count(header, 5)?;
+                Node::Leaf { partial: input.partial(len)?, value: NodeValue::Hashed(input.hash()?) }
+            }
+            _ if header >> 4 == 0b0001 => {
+                let len = input.nibble_count(header, 4)?;
+                decode_branch(&mut input, len, true, true)?
+            }
+            _ => return Err(ProofError::InvalidNode),
+        };
+
+        if input.pos != data.len() {
+            return Err(ProofError::InvalidNode);
+        }
+        Ok(node)
+    }
+
+    fn decode_branch<'a>(
+        input: &mut Input<'a>,
+        len: usize,
+        has_value: bool,
+        hashed_value: bool,
+    ) -> Result<Node<'a>, ProofError> {
+        let partial = input.partial(len)?;
+        let bitmap = input.take(2)?;
+        let bitmap = u16::from_le_bytes([bitmap[0], bitmap[1]]);
+
+        let value = match (has_value, hashed_value) {
+            (false, _) => None,
+            (true, false) => Some(NodeValue::Inline(input.bytes()?)),
+            (true, true) => Some(NodeValue::Hashed(input.hash()?)),
+        };
+
+        let mut children: [Option<Child<'a>>; 16] = Default::default();
+        for (i, child) in children.iter_mut().enumerate() {
+            if bitmap & (1 << i) == 0 {
+                continue;
+            }
+            let encoded = input.bytes()?;
+            *child = Some(if encoded.len() == 32 {
+                let mut hash = [0u8; 32];
+                hash.copy_from_slice(encoded);
+                Child::Hashed(hash)
+            } else {
+                Child::Inline(encoded)
+            });
+        }
+
+        Ok(Node::Branch { partial, value, children })
+    }
+
+    /// Read `key` from the trie with root `root` using only the nodes in `proof`.
+    /// Returns `Ok(None)` when the proof shows the key is absent.
+    pub fn read_proof_value(
+        root: &Hash,
+        key: &[u8],
+        proof: &[Vec<u8>],
+        hash: fn(&[u8]) -> Hash,
+    ) -> Result<Option<Vec<u8>>, ProofError> {
+        let hashes: Vec<Hash> = proof.iter().map(|node| hash(node)).collect();
+        let lookup = |h: &Hash| -> Result<&[u8], ProofError> {
+            hashes
+                .iter()
+                .position(|candidate| candidate == h)
+                .map(|i| proof[i].as_slice())
+                .ok_or(ProofError::IncompleteProof)
+        };
+        let resolve = |value: NodeValue| -> Result<Vec<u8>, ProofError> {
+            match value {
+                NodeValue::Inline(bytes) => Ok(bytes.to_vec()),
+                NodeValue::Hashed(h) => lookup(&h).map(|bytes| bytes.to_vec()),
+            }
+        };
+
+        let key = Nibbles { data: key, offset: 0, len: key.len() * 2 };
+        let mut pos = 0;
+        let mut node_data = lookup(root)?;
+
+        loop {
+            let (partial, value, children) = match decode_node(node_data)? {
+                Node::Empty => return Ok(None),
+                Node::Leaf { partial, value } => (partial, Some(value), None),
+                Node::Branch { partial, value, children } => (partial, value, Some(children)),
+            };
+
+            if key.len - pos < partial.len || (0..partial.len).any(|i| partial.at(i) != key.at(pos + i)) {
+                return Ok(None);
+            }
+            pos += partial.len;
+
+            let mut children = match children {
+                // A leaf only holds the value if it consumes the whole key
+                None if pos == key.len => return value.map(resolve).transpose(),
+                None => return Ok(None),
+                Some(children) => children,
+            };
+
+            if pos == key.len {
+                return value.map(resolve).transpose();
+            }
+
+            let index = key.at(pos) as usize;
+            pos += 1;
+            node_data = match children[index].take() {
+                None => return Ok(None),
+                Some(Child::Inline(bytes)) => bytes,
+                Some(Child::Hashed(h)) => lookup(&h)?,
+            };
+        }
+    }
+}
+
 #[ink::contract]
 mod daemonless_oracle {
     #[ink(storage)]
@@ -19,7 +275,9 @@ mod daemonless_oracle {
         validator_stakes: Mapping<AccountId, Balance>,
         
         // Cross-chain verification
+        header_attesters: Mapping<AccountId, bool>,
         parachain_verifiers: Mapping<ParachainId, VerifierInfo>,
+        tracked_headers: Mapping<(ParachainId, BlockNumber), TrackedHeader>,
         state_proofs: Mapping<ProofId, StateProof>,
         
         // Security
@@ -31,8 +289,35 @@ mod daemonless_oracle {
         minimum_validators: u32,
         consensus_threshold: u32,
         reward_rate: Balance,
+        
+        // Derived commodity peg feeds
+        derived_feeds: Mapping<FeedId, DerivedFeed>,
+        derived_prices: Mapping<FeedId, DerivedPrice>,
+        derived_by_market: Mapping<FeedId, FeedId>,
+        metrics_reporters: Mapping<AccountId, bool>,
+        
+        // Price history ring buffers
+        observations: Mapping<(FeedId, u32), Observation>,
+        observation_state: Mapping<FeedId, ObservationState>,
+        history_depth: u32,
+        
+        // Governance
+        owner: AccountId,
     }
 
+    /// Fixed-point precision for reduction and adjustment factors (1.0 = 1_000_000)
+    pub const FACTOR_PRECISION: u32 = 1_000_000;
+
+    /// Largest quality, market or governance adjustment (2.0x)
+    pub const MAX_ADJUSTMENT_FACTOR: u32 = 2 * FACTOR_PRECISION;
+
+    /// Observations kept per feed unless governance changes it
+    pub const DEFAULT_HISTORY_DEPTH: u32 = 144;
+
+    /// Initial oracle values from docs/oracle-price-determination.md, in micro-USD per gallon
+    pub const SPIRULINA_BASE_VALUE: Balance = 333_000_000;
+    pub const KOMBUCHA_BASE_VALUE: Balance = 75_000_000;
+
     #[derive(Encode, Decode, Debug)]
     pub struct PriceFeed {
         asset_pair: (TokenId, TokenId),
@@ -60,13 +345,110 @@ mod daemonless_oracle {
         last_verification: BlockNumber,
     }
 
+    /// Storage proof for a single key of a tracked relay or parachain block.
+    /// `storage_value` of `None` proves the key is absent.
     #[derive(Encode, Decode, Debug)]
     pub struct StateProof {
         source_chain: ParachainId,
         block_number: BlockNumber,
         state_root: [u8; 32],
-        validator_signatures: Vec<DilithiumSignature>,
-        quantum_proof: Vec<u8>,
+        storage_key: Vec<u8>,
+        storage_value: Option<Vec<u8>>,
+        proof_nodes: Vec<Vec<u8>>,
+    }
+
+    /// Header accepted once `consensus_threshold` validators signed its hash
+    #[derive(Encode, Decode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct TrackedHeader {
+        header_hash: [u8; 32],
+        parent_hash: [u8; 32],
+        state_root: [u8; 32],
+        attested_by: Vec<AccountId>,
+    }
+
+    /// Content-addressed: blake2-256 of the SCALE-encoded proof
+    pub type ProofId = [u8; 32];
+
+    fn blake2_256(input: &[u8]) -> [u8; 32] {
+        let mut output = [0u8; 32];
+        ink_env::hash_bytes::<ink_env::hash::Blake2x256>(input, &mut output);
+        output
+    }
+
+    /// A consensus price and the time-weighted price accumulator at the moment it was set.
+    /// `price_cumulative` is the sum of `price * elapsed_ms` since the first observation
+    /// and wraps on overflow; only differences between two accumulators are meaningful.
+    #[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq, Default)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Observation {
+        timestamp: Timestamp,
+        price: Balance,
+        price_cumulative: u128,
+    }
+
+    /// Ring buffer bookkeeping for one feed. `index` is the slot of the newest observation.
+    #[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq, Default)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ObservationState {
+        index: u32,
+        count: u32,
+        capacity: u32,
+    }
+
+    /// One step of the price reduction trajectory. A stage is reached once the network
+    /// has both the facility count and the certified harvest volume it requires.
+    #[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct TrajectoryStage {
+        min_facilities: u32,
+        min_certified_volume: Balance,
+        reduction_factor: u32,
+    }
+
+    #[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq, Default)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct NetworkScaleMetrics {
+        registered_facilities: u32,
+        certified_harvest_volume: Balance,
+        reported_at: Timestamp,
+    }
+
+    /// Reference price definition for a commodity:
+    /// Price = BaseValue * NetworkScaleFactor * QualityFactor * MarketAdjustment * GovernanceFactor
+    #[derive(Encode, Decode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct DerivedFeed {
+        market_feed: FeedId,
+        base_value: Balance,
+        trajectory: Vec<TrajectoryStage>,
+        metrics: NetworkScaleMetrics,
+        quality_factor: u32,
+        market_adjustment: u32,
+        governance_factor: u32,
+    }
+
+    /// Published reference price with every input needed to recompute it
+    #[derive(Encode, Decode, Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct DerivedPrice {
+        reference_price: Balance,
+        market_price: Balance,
+        network_scale_factor: u32,
+        metrics: NetworkScaleMetrics,
+        timestamp: Timestamp,
+    }
+
+    /// The five-phase reduction curve (1.00x, 0.60x, 0.30x, 0.15x, 0.10x) with the
+    /// network scale each phase is expected at
+    pub fn default_trajectory() -> Vec<TrajectoryStage> {
+        vec![
+            TrajectoryStage { min_facilities: 0, min_certified_volume: 0, reduction_factor: 1_000_000 },
+            TrajectoryStage { min_facilities: 10, min_certified_volume: 1_000, reduction_factor: 600_000 },
+            TrajectoryStage { min_facilities: 100, min_certified_volume: 10_000, reduction_factor: 300_000 },
+            TrajectoryStage { min_facilities: 1_000, min_certified_volume: 100_000, reduction_factor: 150_000 },
+            TrajectoryStage { min_facilities: 10_000, min_certified_volume: 1_000_000, reduction_factor: 100_000 },
+        ]
     }
 
     impl DaemonlessOracle {
@@ -80,6 +462,8 @@ mod daemonless_oracle {
                 contract.minimum_validators = minimum_validators;
                 contract.consensus_threshold = consensus_threshold;
                 contract.reward_rate = reward_rate;
+                contract.owner = Self::env().caller();
+                contract.history_depth = DEFAULT_HISTORY_DEPTH;
                 
                 // Initialize quantum entropy
                 contract.quantum_entropy = contract.generate_quantum_entropy();
@@ -120,9 +504,15 @@ mod daemonless_oracle {
                 feed.quantum_proof = self.generate_quantum_proof(&feed);
                 
                 self.distribute_rewards(&feed)?;
+                self.record_observation(feed_id, price)?;
             }
             
             self.price_feeds.insert(feed_id, &feed);
+            
+            // Publish the peg alongside the market price it references
+            if let Some(derived_id) = self.derived_by_market.get(feed_id) {
+                self.refresh_derived_price(derived_id)?;
+            }
 
             self.env().emit_event(PriceUpdated {
                 feed_id,
@@ -138,30 +528,22 @@ mod daemonless_oracle {
         pub fn register_validator(
             &mut self,
             stake_amount: Balance,
+            quantum_key: KyberPublicKey,
+            signature_key: DilithiumPublicKey,
         ) -> Result<(), Error> {
             let caller = self.env().caller();
             
-            // Generate quantum-resistant keys
-            let (kyber_public, kyber_private) = kyber_keygen();
-            let (dilithium_public, dilithium_private) = dilithium_keygen();
-            
+            // Keys are generated off-chain; the contract only ever sees public keys
             let validator_info = ValidatorInfo {
                 stake: stake_amount,
                 reliability: 100,
                 last_update: self.env().block_timestamp(),
-                quantum_key: kyber_public,
-                signature_key: dilithium_public,
+                quantum_key,
+                signature_key,
             };
             
             self.validators.insert(caller, &validator_info);
             self.validator_stakes.insert(caller, &stake_amount);
-            
-            // Store private keys securely (implementation specific)
-            self.store_validator_keys(
-                caller,
-                kyber_private,
-                dilithium_private
-            )?;
 
             self.env().emit_event(ValidatorRegistered {
                 validator: caller,
@@ -171,6 +553,129 @@ mod daemonless_oracle {
             Ok(())
         }
 
+        #[ink(message)]
+        pub fn register_parachain(
+            &mut self,
+            parachain_id: ParachainId,
+            verifier_key: KyberPublicKey,
+            supported_assets: Vec<TokenId>,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            
+            let verifier = VerifierInfo {
+                parachain_id,
+                verifier_key,
+                supported_assets,
+                last_verification: 0,
+            };
+            self.parachain_verifiers.insert(parachain_id, &verifier);
+            
+            Ok(())
+        }
+
+        /// Allow a registered validator to attest headers. Registration alone costs
+        /// nothing, so only validators the owner vouches for count towards
+        /// `consensus_threshold` in `submit_header`.
+        #[ink(message)]
+        pub fn approve_attester(&mut self, validator: AccountId) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if self.validators.get(validator).is_none() {
+                return Err(Error::NotValidator);
+            }
+            self.header_attesters.insert(validator, &true);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn revoke_attester(&mut self, validator: AccountId) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.header_attesters.remove(validator);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn is_attester(&self, validator: AccountId) -> bool {
+            self.header_attesters.get(validator).unwrap_or(false)
+        }
+
+        /// Track a SCALE-encoded Substrate header of a registered chain. Validators sign
+        /// `(parachain_id, blake2_256(header))`; the header is accepted once
+        /// `consensus_threshold` distinct approved attesters have signed it.
+        #[ink(message)]
+        pub fn submit_header(
+            &mut self,
+            parachain_id: ParachainId,
+            header: Vec<u8>,
+            signatures: Vec<(AccountId, DilithiumSignature)>,
+        ) -> Result<[u8; 32], Error> {
+            let mut verifier = self.parachain_verifiers.get(parachain_id)
+                .ok_or(Error::ParachainNotRegistered)?;
+            
+            let (parent_hash, block_number, state_root) = Self::decode_header_prefix(&header)
+                .ok_or(Error::InvalidHeader)?;
+            let header_hash = blake2_256(&header);
+            let message = (parachain_id, header_hash).encode();
+            
+            let mut attested_by: Vec<AccountId> = Vec::new();
+            for (validator, signature) in signatures.iter() {
+                if !attested_by.contains(validator)
+                    && self.is_attester(*validator)
+                    && self.verify_validator_signature(validator, &message, signature)
+                {
+                    attested_by.push(*validator);
+                }
+            }
+            if attested_by.len() < self.consensus_threshold as usize {
+                return Err(Error::ConsensusNotReached);
+            }
+            
+            // Refuse forks of already tracked headers
+            if let Some(existing) = self.tracked_headers.get((parachain_id, block_number)) {
+                if existing.header_hash != header_hash {
+                    return Err(Error::HeaderMismatch);
+                }
+            }
+            if let Some(parent) = block_number.checked_sub(1)
+                .and_then(|parent_number| self.tracked_headers.get((parachain_id, parent_number)))
+            {
+                if parent.header_hash != parent_hash {
+                    return Err(Error::HeaderMismatch);
+                }
+            }
+            
+            self.tracked_headers.insert((parachain_id, block_number), &TrackedHeader {
+                header_hash,
+                parent_hash,
+                state_root,
+                attested_by,
+            });
+            
+            if block_number > verifier.last_verification {
+                verifier.last_verification = block_number;
+                self.parachain_verifiers.insert(parachain_id, &verifier);
+            }
+
+            self.env().emit_event(HeaderTracked {
+                parachain_id,
+                block_number,
+                header_hash,
+                state_root,
+            });
+
+            Ok(header_hash)
+        }
+
+        #[ink(message)]
+        pub fn get_tracked_header(
+            &self,
+            parachain_id: ParachainId,
+            block_number: BlockNumber,
+        ) -> Option<TrackedHeader> {
+            self.tracked_headers.get((parachain_id, block_number))
+        }
+
+        /// Verify a storage proof against the state root of a tracked header.
+        /// Returns `Ok(false)` when the proof does not hold.
         #[ink(message)]
         pub fn verify_state_proof(
             &mut self,
@@ -178,24 +683,31 @@ mod daemonless_oracle {
             proof: StateProof,
         ) -> Result<bool, Error> {
             // Verify parachain is registered
-            let verifier = self.parachain_verifiers.get(parachain_id)
-                .ok_or(Error::ParachainNotRegistered)?;
-            
-            // Verify quantum proof
-            if !self.verify_quantum_proof(&proof.quantum_proof) {
+            if self.parachain_verifiers.get(parachain_id).is_none() {
+                return Err(Error::ParachainNotRegistered);
+            }
+            if proof.source_chain != parachain_id {
                 return Ok(false);
             }
             
-            // Verify validator signatures
-            let valid_signatures = proof.validator_signatures
-                .iter()
-                .filter(|sig| self.verify_validator_signature(sig))
-                .count();
-                
-            if valid_signatures < self.consensus_threshold as usize {
+            // Proofs are only accepted against headers validators have attested
+            let header = self.tracked_headers.get((parachain_id, proof.block_number))
+                .ok_or(Error::UnknownHeader)?;
+            if header.state_root != proof.state_root {
                 return Ok(false);
             }
             
+            // Walk the Merkle-Patricia trie from the state root to the key
+            match trie::read_proof_value(
+                &proof.state_root,
+                &proof.storage_key,
+                &proof.proof_nodes,
+                blake2_256,
+            ) {
+                Ok(value) if value == proof.storage_value => {}
+                _ => return Ok(false),
+            }
+            
             // Store verified proof
             let proof_id = self.generate_proof_id(&proof);
             self.state_proofs.insert(proof_id, &proof);
@@ -209,7 +721,449 @@ mod daemonless_oracle {
             Ok(true)
         }
 
+        #[ink(message)]
+        pub fn configure_derived_feed(
+            &mut self,
+            feed_id: FeedId,
+            market_feed: FeedId,
+            base_value: Balance,
+            trajectory: Vec<TrajectoryStage>,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.validate_trajectory(&trajectory)?;
+            
+            // A market feed backs at most one derived feed
+            if let Some(other) = self.derived_by_market.get(market_feed) {
+                if other != feed_id {
+                    return Err(Error::MarketFeedInUse);
+                }
+            }
+            
+            // Keep metrics and adjustments when re-configuring an existing feed
+            let existing = self.derived_feeds.get(feed_id);
+            if let Some(previous) = &existing {
+                self.derived_by_market.remove(previous.market_feed);
+            }
+            
+            let derived = DerivedFeed {
+                market_feed,
+                base_value,
+                trajectory,
+                metrics: existing.as_ref().map(|f| f.metrics).unwrap_or_default(),
+                quality_factor: existing.as_ref().map(|f| f.quality_factor).unwrap_or(FACTOR_PRECISION),
+                market_adjustment: existing.as_ref().map(|f| f.market_adjustment).unwrap_or(FACTOR_PRECISION),
+                governance_factor: existing.as_ref().map(|f| f.governance_factor).unwrap_or(FACTOR_PRECISION),
+            };
+            
+            self.derived_feeds.insert(feed_id, &derived);
+            self.derived_by_market.insert(market_feed, &feed_id);
+
+            self.env().emit_event(DerivedFeedConfigured {
+                feed_id,
+                market_feed,
+                base_value,
+            });
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_adjustment_factors(
+            &mut self,
+            feed_id: FeedId,
+            quality_factor: u32,
+            market_adjustment: u32,
+            governance_factor: u32,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            
+            // A zero factor would zero the peg, a large one make it meaningless
+            for factor in [quality_factor, market_adjustment, governance_factor] {
+                if factor == 0 || factor > MAX_ADJUSTMENT_FACTOR {
+                    return Err(Error::InvalidFactor);
+                }
+            }
+            
+            let mut derived = self.derived_feeds.get(feed_id)
+                .ok_or(Error::FeedNotFound)?;
+            
+            derived.quality_factor = quality_factor;
+            derived.market_adjustment = market_adjustment;
+            derived.governance_factor = governance_factor;
+            self.derived_feeds.insert(feed_id, &derived);
+            
+            self.refresh_derived_price(feed_id)
+        }
+
+        /// Allow a registered validator to report network metrics. Reports only
+        /// ever raise the metrics, so like header attesters, reporters are vouched
+        /// for by the owner rather than taken from free registration.
+        #[ink(message)]
+        pub fn approve_metrics_reporter(&mut self, validator: AccountId) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if self.validators.get(validator).is_none() {
+                return Err(Error::NotValidator);
+            }
+            self.metrics_reporters.insert(validator, &true);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn revoke_metrics_reporter(&mut self, validator: AccountId) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.metrics_reporters.remove(validator);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn is_metrics_reporter(&self, validator: AccountId) -> bool {
+            self.metrics_reporters.get(validator).unwrap_or(false)
+        }
+
+        /// Approved validators report network scale from the facility registry and harvest certifications
+        #[ink(message)]
+        pub fn report_network_metrics(
+            &mut self,
+            feed_id: FeedId,
+            registered_facilities: u32,
+            certified_harvest_volume: Balance,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if self.validators.get(caller).is_none() {
+                return Err(Error::NotValidator);
+            }
+            if !self.is_metrics_reporter(caller) {
+                return Err(Error::NotAuthorized);
+            }
+            
+            let mut derived = self.derived_feeds.get(feed_id)
+                .ok_or(Error::FeedNotFound)?;
+            
+            // Network scale only grows; a lower report is a stale or faulty validator
+            if registered_facilities < derived.metrics.registered_facilities
+                || certified_harvest_volume < derived.metrics.certified_harvest_volume
+            {
+                return Err(Error::MetricsRegression);
+            }
+            
+            derived.metrics = NetworkScaleMetrics {
+                registered_facilities,
+                certified_harvest_volume,
+                reported_at: self.env().block_timestamp(),
+            };
+            self.derived_feeds.insert(feed_id, &derived);
+
+            self.env().emit_event(NetworkMetricsReported {
+                feed_id,
+                registered_facilities,
+                certified_harvest_volume,
+                validator: caller,
+            });
+            
+            self.refresh_derived_price(feed_id)
+        }
+
+        /// Correct the metrics of a feed, lower ones included, after a faulty report
+        #[ink(message)]
+        pub fn reset_network_metrics(
+            &mut self,
+            feed_id: FeedId,
+            registered_facilities: u32,
+            certified_harvest_volume: Balance,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            
+            let mut derived = self.derived_feeds.get(feed_id)
+                .ok_or(Error::FeedNotFound)?;
+            
+            derived.metrics = NetworkScaleMetrics {
+                registered_facilities,
+                certified_harvest_volume,
+                reported_at: self.env().block_timestamp(),
+            };
+            self.derived_feeds.insert(feed_id, &derived);
+
+            self.env().emit_event(NetworkMetricsReported {
+                feed_id,
+                registered_facilities,
+                certified_harvest_volume,
+                validator: self.env().caller(),
+            });
+            
+            self.refresh_derived_price(feed_id)
+        }
+
+        #[ink(message)]
+        pub fn get_derived_price(&self, feed_id: FeedId) -> Option<DerivedPrice> {
+            self.derived_prices.get(feed_id)
+        }
+
+        #[ink(message)]
+        pub fn get_derived_feed(&self, feed_id: FeedId) -> Option<DerivedFeed> {
+            self.derived_feeds.get(feed_id)
+        }
+
+        /// Number of observations kept for feeds. Existing rings grow to a larger depth
+        /// when they next wrap around and never shrink, so history is never reordered.
+        #[ink(message)]
+        pub fn set_history_depth(&mut self, depth: u32) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if depth < 2 {
+                return Err(Error::InvalidHistoryDepth);
+            }
+            self.history_depth = depth;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn history_depth(&self) -> u32 {
+            self.history_depth
+        }
+
+        /// Time-weighted average price over the last `window` milliseconds
+        #[ink(message)]
+        pub fn twap(&self, feed_id: FeedId, window: Timestamp) -> Result<Balance, Error> {
+            let state = self.observation_state.get(feed_id)
+                .ok_or(Error::FeedNotFound)?;
+            let now = self.env().block_timestamp();
+            
+            let latest = self.observation_at_slot(feed_id, state.index);
+            if window == 0 {
+                return Ok(latest.price);
+            }
+            
+            let start = now.checked_sub(window).ok_or(Error::InsufficientHistory)?;
+            let at_start = self.observation_before(feed_id, &state, start)
+                .ok_or(Error::InsufficientHistory)?;
+            
+            let cumulative_now = Self::cumulative_at(&latest, now);
+            let cumulative_start = Self::cumulative_at(&at_start, start);
+            
+            Ok(cumulative_now.wrapping_sub(cumulative_start) / window as u128)
+        }
+
+        /// Consensus price that was in effect at `timestamp`, if it is still in history
+        #[ink(message)]
+        pub fn price_at(&self, feed_id: FeedId, timestamp: Timestamp) -> Option<Balance> {
+            let state = self.observation_state.get(feed_id)?;
+            self.observation_before(feed_id, &state, timestamp)
+                .map(|observation| observation.price)
+        }
+
+        /// Observations from oldest to newest
+        #[ink(message)]
+        pub fn get_observations(&self, feed_id: FeedId) -> Vec<Observation> {
+            let state = match self.observation_state.get(feed_id) {
+                Some(state) => state,
+                None => return Vec::new(),
+            };
+            (0..state.count)
+                .map(|i| self.observation_at_slot(feed_id, Self::slot_of(&state, i)))
+                .collect()
+        }
+
         // Helper functions
+        fn record_observation(&mut self, feed_id: FeedId, price: Balance) -> Result<(), Error> {
+            let now = self.env().block_timestamp();
+            
+            let mut state = match self.observation_state.get(feed_id) {
+                Some(state) => state,
+                None => {
+                    let state = ObservationState { index: 0, count: 1, capacity: self.history_depth };
+                    self.observations.insert((feed_id, 0), &Observation {
+                        timestamp: now,
+                        price,
+                        price_cumulative: 0,
+                    });
+                    self.observation_state.insert(feed_id, &state);
+                    return Ok(());
+                }
+            };
+            
+            let latest = self.observation_at_slot(feed_id, state.index);
+            if now < latest.timestamp {
+                return Err(Error::InvalidTimestamp);
+            }
+            
+            let observation = Observation {
+                timestamp: now,
+                price,
+                price_cumulative: Self::cumulative_at(&latest, now),
+            };
+            
+            // Several updates in one block keep a single observation with the last price
+            if now == latest.timestamp {
+                self.observations.insert((feed_id, state.index), &observation);
+                return Ok(());
+            }
+            
+            // Grow into the new depth only at the end of the ring so slots stay ordered
+            if state.index + 1 == state.capacity && self.history_depth > state.capacity {
+                state.capacity = self.history_depth;
+            }
+            
+            state.index = (state.index + 1) % state.capacity;
+            state.count = (state.count + 1).min(state.capacity);
+            
+            self.observations.insert((feed_id, state.index), &observation);
+            self.observation_state.insert(feed_id, &state);
+            Ok(())
+        }
+
+        fn observation_at_slot(&self, feed_id: FeedId, slot: u32) -> Observation {
+            self.observations.get((feed_id, slot)).unwrap_or_default()
+        }
+
+        /// Slot of the `i`-th oldest observation
+        fn slot_of(state: &ObservationState, i: u32) -> u32 {
+            let oldest = if state.count < state.capacity {
+                0
+            } else {
+                (state.index + 1) % state.capacity
+            };
+            (oldest + i) % state.capacity
+        }
+
+        fn cumulative_at(observation: &Observation, timestamp: Timestamp) -> u128 {
+            let elapsed = timestamp.saturating_sub(observation.timestamp) as u128;
+            observation.price_cumulative
+                .wrapping_add(observation.price.wrapping_mul(elapsed))
+        }
+
+        /// Newest observation at or before `timestamp`, by binary search over the ring
+        fn observation_before(
+            &self,
+            feed_id: FeedId,
+            state: &ObservationState,
+            timestamp: Timestamp,
+        ) -> Option<Observation> {
+            let oldest = self.observation_at_slot(feed_id, Self::slot_of(state, 0));
+            if timestamp < oldest.timestamp {
+                return None;
+            }
+            
+            let (mut low, mut high) = (0, state.count - 1);
+            while low < high {
+                let mid = (low + high + 1) / 2;
+                let observation = self.observation_at_slot(feed_id, Self::slot_of(state, mid));
+                if observation.timestamp <= timestamp {
+                    low = mid;
+                } else {
+                    high = mid - 1;
+                }
+            }
+            Some(self.observation_at_slot(feed_id, Self::slot_of(state, low)))
+        }
+
+        fn ensure_owner(&self) -> Result<(), Error> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotAuthorized);
+            }
+            Ok(())
+        }
+
+        fn validate_trajectory(&self, trajectory: &[TrajectoryStage]) -> Result<(), Error> {
+            let first = trajectory.first().ok_or(Error::InvalidTrajectory)?;
+            if first.min_facilities != 0 || first.min_certified_volume != 0 {
+                return Err(Error::InvalidTrajectory);
+            }
+            
+            // Thresholds must increase and the factor must never rise
+            for pair in trajectory.windows(2) {
+                if pair[1].min_facilities <= pair[0].min_facilities
+                    || pair[1].min_certified_volume <= pair[0].min_certified_volume
+                    || pair[1].reduction_factor > pair[0].reduction_factor
+                {
+                    return Err(Error::InvalidTrajectory);
+                }
+            }
+            
+            if first.reduction_factor > FACTOR_PRECISION {
+                return Err(Error::InvalidTrajectory);
+            }
+            Ok(())
+        }
+
+        /// Reduction factor for the current network scale, interpolated linearly
+        /// between the reached stage and the next one by the slower of the two metrics
+        fn network_scale_factor(
+            &self,
+            trajectory: &[TrajectoryStage],
+            metrics: &NetworkScaleMetrics,
+        ) -> u32 {
+            let reached = trajectory
+                .iter()
+                .rposition(|stage| {
+                    metrics.registered_facilities >= stage.min_facilities
+                        && metrics.certified_harvest_volume >= stage.min_certified_volume
+                })
+                .unwrap_or(0);
+            
+            let current = trajectory[reached];
+            let next = match trajectory.get(reached + 1) {
+                Some(next) => next,
+                None => return current.reduction_factor,
+            };
+            
+            let facility_progress = (metrics.registered_facilities - current.min_facilities) as u128
+                * FACTOR_PRECISION as u128
+                / (next.min_facilities - current.min_facilities) as u128;
+            let volume_progress = (metrics.certified_harvest_volume - current.min_certified_volume)
+                * FACTOR_PRECISION as u128
+                / (next.min_certified_volume - current.min_certified_volume);
+            let progress = facility_progress.min(volume_progress).min(FACTOR_PRECISION as u128);
+            
+            let drop = (current.reduction_factor - next.reduction_factor) as u128 * progress
+                / FACTOR_PRECISION as u128;
+            current.reduction_factor - drop as u32
+        }
+
+        fn refresh_derived_price(&mut self, feed_id: FeedId) -> Result<(), Error> {
+            let derived = self.derived_feeds.get(feed_id)
+                .ok_or(Error::FeedNotFound)?;
+            
+            let network_scale_factor = self.network_scale_factor(
+                &derived.trajectory,
+                &derived.metrics,
+            );
+            
+            let reference_price = [
+                network_scale_factor,
+                derived.quality_factor,
+                derived.market_adjustment,
+                derived.governance_factor,
+            ]
+            .iter()
+            .try_fold(derived.base_value, |price, factor| {
+                price.checked_mul(*factor as Balance)
+                    .map(|scaled| scaled / FACTOR_PRECISION as Balance)
+            })
+            .ok_or(Error::ArithmeticOverflow)?;
+            
+            let market_price = self.price_feeds.get(derived.market_feed)
+                .map(|feed| feed.price)
+                .unwrap_or(0);
+            
+            let price = DerivedPrice {
+                reference_price,
+                market_price,
+                network_scale_factor,
+                metrics: derived.metrics,
+                timestamp: self.env().block_timestamp(),
+            };
+            self.derived_prices.insert(feed_id, &price);
+
+            self.env().emit_event(DerivedPriceUpdated {
+                feed_id,
+                market_feed: derived.market_feed,
+                reference_price,
+                market_price,
+                network_scale_factor,
+            });
+
+            Ok(())
+        }
+
         fn sign_price_update(
             &self,
             feed_id: FeedId,
@@ -229,20 +1183,26 @@ mod daemonless_oracle {
             Vec::new() // Placeholder
         }
 
-        fn verify_quantum_proof(
-            &self,
-            proof: &[u8],
-        ) -> bool {
-            // Implementation using Kyber
-            true // Placeholder
-        }
-
         fn verify_validator_signature(
             &self,
+            validator: &AccountId,
+            message: &[u8],
             signature: &DilithiumSignature,
         ) -> bool {
-            // Implementation using Dilithium
-            true // Placeholder
+            match self.validators.get(validator) {
+                Some(info) => verify(&signature[..], message, &info.signature_key[..]).is_ok(),
+                None => false,
+            }
+        }
+
+        /// Reads `parent_hash`, `number` and `state_root`, the leading fields of a
+        /// SCALE-encoded Substrate header
+        fn decode_header_prefix(header: &[u8]) -> Option<([u8; 32], BlockNumber, [u8; 32])> {
+            let mut input = header;
+            let parent_hash = <[u8; 32]>::decode(&mut input).ok()?;
+            let number = <scale::Compact<BlockNumber>>::decode(&mut input).ok()?.0;
+            let state_root = <[u8; 32]>::decode(&mut input).ok()?;
+            Some((parent_hash, number, state_root))
         }
 
         fn distribute_rewards(
@@ -258,19 +1218,38 @@ mod daemonless_oracle {
             [0u8; 32] // Placeholder
         }
 
-        fn store_validator_keys(
-            &mut self,
-            validator: AccountId,
-            kyber_private: KyberPrivateKey,
-            dilithium_private: DilithiumPrivateKey,
-        ) -> Result<(), Error> {
-            // Secure key storage implementation
-            Ok(()) // Placeholder
+        fn generate_proof_id(&self, proof: &StateProof) -> ProofId {
+            blake2_256(&proof.encode())
         }
+    }
 
-        fn generate_proof_id(&self, proof: &StateProof) -> ProofId {
-            // Implementation using quantum-resistant hash
-            ProofId::default() // Placeholder
+    impl OracleConsumer for DaemonlessOracle {
+        #[ink(message)]
+        fn latest_price(&self, feed_id: FeedId) -> Option<PriceData> {
+            // Feeds only get history once they reach consensus
+            self.observation_state.get(feed_id)?;
+            let feed = self.price_feeds.get(feed_id)?;
+            Some(PriceData {
+                price: feed.price,
+                confidence: feed.confidence,
+                timestamp: feed.timestamp,
+                staleness: self.env().block_timestamp().saturating_sub(feed.timestamp),
+            })
+        }
+
+        #[ink(message)]
+        fn price_confidence(&self, feed_id: FeedId) -> Option<u8> {
+            self.latest_price(feed_id).map(|data| data.confidence)
+        }
+
+        #[ink(message)]
+        fn price_staleness(&self, feed_id: FeedId) -> Option<Timestamp> {
+            self.latest_price(feed_id).map(|data| data.staleness)
+        }
+
+        #[ink(message)]
+        fn time_weighted_price(&self, feed_id: FeedId, window: Timestamp) -> Option<Balance> {
+            self.twap(feed_id, window).ok()
         }
     }
 
@@ -300,6 +1279,45 @@ mod daemonless_oracle {
         block_number: BlockNumber,
     }
 
+    #[ink(event)]
+    pub struct HeaderTracked {
+        #[ink(topic)]
+        parachain_id: ParachainId,
+        block_number: BlockNumber,
+        header_hash: [u8; 32],
+        state_root: [u8; 32],
+    }
+
+    #[ink(event)]
+    pub struct DerivedFeedConfigured {
+        #[ink(topic)]
+        feed_id: FeedId,
+        #[ink(topic)]
+        market_feed: FeedId,
+        base_value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct NetworkMetricsReported {
+        #[ink(topic)]
+        feed_id: FeedId,
+        registered_facilities: u32,
+        certified_harvest_volume: Balance,
+        #[ink(topic)]
+        validator: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct DerivedPriceUpdated {
+        #[ink(topic)]
+        feed_id: FeedId,
+        #[ink(topic)]
+        market_feed: FeedId,
+        reference_price: Balance,
+        market_price: Balance,
+        network_scale_factor: u32,
+    }
+
     // Error types
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
@@ -309,5 +1327,418 @@ mod daemonless_oracle {
         InvalidSignature,
         InsufficientStake,
         ConsensusNotReached,
+        NotAuthorized,
+        FeedNotFound,
+        InvalidTrajectory,
+        MetricsRegression,
+        ArithmeticOverflow,
+        InvalidHeader,
+        HeaderMismatch,
+        UnknownHeader,
+        InvalidHistoryDepth,
+        InsufficientHistory,
+        InvalidTimestamp,
+        MarketFeedInUse,
+        InvalidFactor,
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_lang as ink;
+
+        // Storage proof generated with sp-trie LayoutV1 (the state trie of Substrate
+        // chains) over six `Oracle::Prices` entries, proving the entry for key 4
+        const ROOT: &str = "df7dbcc9e3bf34929aa83426ea3ad362d0a069042fdf0e1d48bc5a7dbd258492";
+        const KEY: &str = "6ecdde33e5d791e9c15595f33fdaafd6b3c23035bf2835b2650cfc2d17cef06a1c8a02f456942930c9730654ca687a3a04000000";
+        const VALUE: &str = "00093d00000000000000000000000000";
+        const NODES: [&str; 3] = [
+            "668a02f456942930c9730654ca687a3a040000004000093d00000000000000000000000000",
+            "80021080f86742aa5eee76653da399d7cd29c0ee64f868603f5e1153fb5dea14cc37457080839ffedbaff3a0b390b91f3f48d1f16c1d98225409e02cde1b4e59acfd8846b6",
+            "bf016ecdde33e5d791e9c15595f33fdaafd6b3c23035bf2835b2650cfc2d17cef06a822280e638ce07628731c85ced90b6937da977d23aeb20029853d9b939bafc2d481229806ba5a7c3c02908ae37668b1301a63e7cc31a0147a4f9340c84fae790eb5a11b280690c06b2572a8b5ead1d7b2c9b2948d76c702c4ba6ddf1a3464de7fa91bd5dc3809c08f8006b3379b0ccb00346d29bc8f2795238ee12551037b8c32743e44f6ecc",
+        ];
+
+        fn hex(s: &str) -> Vec<u8> {
+            (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+        }
+
+        fn root() -> [u8; 32] {
+            let mut out = [0u8; 32];
+            out.copy_from_slice(&hex(ROOT));
+            out
+        }
+
+        fn fixture_header() -> Vec<u8> {
+            ([0u8; 32], scale::Compact(7u32), root(), [0u8; 32], Vec::<u8>::new()).encode()
+        }
+
+        fn fixture_proof() -> StateProof {
+            StateProof {
+                source_chain: 2000,
+                block_number: 7,
+                state_root: root(),
+                storage_key: hex(KEY),
+                storage_value: Some(hex(VALUE)),
+                proof_nodes: NODES.iter().map(|n| hex(n)).collect(),
+            }
+        }
+
+        #[ink::test]
+        fn trie_proof_reads_fixture_value() {
+            let nodes: Vec<Vec<u8>> = NODES.iter().map(|n| hex(n)).collect();
+            assert_eq!(
+                trie::read_proof_value(&root(), &hex(KEY), &nodes, blake2_256),
+                Ok(Some(hex(VALUE)))
+            );
+            // Without the leaf the proof is incomplete
+            assert_eq!(
+                trie::read_proof_value(&root(), &hex(KEY), &nodes[1..], blake2_256),
+                Err(trie::ProofError::IncompleteProof)
+            );
+        }
+
+        #[ink::test]
+        fn verifies_state_proof_against_attested_header() {
+            let mut oracle = DaemonlessOracle::new(1, 1, 0);
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            let keys = Keypair::generate();
+            let other = Keypair::generate();
+            oracle.register_validator(1_000, [0u8; KYBER_PUBLICKEYBYTES], keys.public).unwrap();
+            oracle.register_parachain(2000, [0u8; KYBER_PUBLICKEYBYTES], Vec::new()).unwrap();
+
+            let header = fixture_header();
+            let message = (2000u32, blake2_256(&header)).encode();
+
+            // Unknown headers are rejected before any trie work
+            assert_eq!(oracle.verify_state_proof(2000, fixture_proof()), Err(Error::UnknownHeader));
+
+            // Registering is not enough to attest
+            assert_eq!(
+                oracle.submit_header(2000, header.clone(), vec![(accounts.alice, keys.sign(&message))]),
+                Err(Error::ConsensusNotReached)
+            );
+            oracle.approve_attester(accounts.alice).unwrap();
+
+            // A key not registered for alice, or a non-validator, does not count
+            assert_eq!(
+                oracle.submit_header(2000, header.clone(), vec![(accounts.alice, other.sign(&message))]),
+                Err(Error::ConsensusNotReached)
+            );
+            assert_eq!(
+                oracle.submit_header(2000, header.clone(), vec![(accounts.bob, keys.sign(&message))]),
+                Err(Error::ConsensusNotReached)
+            );
+            assert!(oracle.submit_header(2000, header, vec![(accounts.alice, keys.sign(&message))]).is_ok());
+
+            let proof = fixture_proof();
+            let proof_id = blake2_256(&proof.encode());
+            assert_eq!(oracle.verify_state_proof(2000, proof), Ok(true));
+            assert!(oracle.state_proofs.get(proof_id).is_some());
+
+            let mut tampered = fixture_proof();
+            tampered.storage_value = Some(hex("00000000000000000000000000000000"));
+            assert_eq!(oracle.verify_state_proof(2000, tampered), Ok(false));
+        }
+
+        fn set_caller(account: AccountId) {
+            ink_env::test::set_caller::<ink_env::DefaultEnvironment>(account);
+        }
+
+        /// Oracle with alice as owner, validator and metrics reporter, and a derived
+        /// feed over the default trajectory with a base value of 1_000_000
+        fn derived_oracle() -> (DaemonlessOracle, FeedId, FeedId) {
+            let mut oracle = DaemonlessOracle::new(1, 1, 0);
+            let keys = Keypair::generate();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            oracle.register_validator(1_000, [0u8; KYBER_PUBLICKEYBYTES], keys.public).unwrap();
+            oracle.approve_metrics_reporter(accounts.alice).unwrap();
+            let (derived, market) = ([2u8; 32], [1u8; 32]);
+            oracle.configure_derived_feed(derived, market, 1_000_000, default_trajectory()).unwrap();
+            (oracle, derived, market)
+        }
+
+        fn factor(oracle: &DaemonlessOracle, feed: FeedId) -> u32 {
+            oracle.get_derived_price(feed).unwrap().network_scale_factor
+        }
+
+        #[ink::test]
+        fn derived_price_follows_the_trajectory() {
+            let (mut oracle, derived, market) = derived_oracle();
+            // Nothing is published until there is something to publish from
+            assert_eq!(oracle.get_derived_price(derived), None);
+
+            oracle.report_network_metrics(derived, 0, 0).unwrap();
+            assert_eq!(factor(&oracle, derived), 1_000_000);
+            assert_eq!(oracle.get_derived_price(derived).unwrap().reference_price, 1_000_000);
+
+            // Halfway to the second stage on both metrics: halfway from 1.0x to 0.6x
+            oracle.report_network_metrics(derived, 5, 500).unwrap();
+            assert_eq!(factor(&oracle, derived), 800_000);
+
+            // The slower metric sets the pace
+            oracle.report_network_metrics(derived, 10, 600).unwrap();
+            assert_eq!(factor(&oracle, derived), 760_000);
+
+            // Reaching a stage on both metrics lands exactly on its factor
+            oracle.report_network_metrics(derived, 100, 10_000).unwrap();
+            assert_eq!(factor(&oracle, derived), 300_000);
+            assert_eq!(oracle.get_derived_price(derived).unwrap().reference_price, 300_000);
+
+            // Past the last stage the factor stays put
+            oracle.report_network_metrics(derived, 50_000, 5_000_000).unwrap();
+            assert_eq!(factor(&oracle, derived), 100_000);
+
+            // A market update republishes the peg next to it
+            oracle.submit_price_update(market, 120_000, 100).unwrap();
+            let price = oracle.get_derived_price(derived).unwrap();
+            assert_eq!(price.market_price, 120_000);
+            assert_eq!(price.reference_price, 100_000);
+        }
+
+        #[ink::test]
+        fn trajectories_must_start_at_zero_and_only_fall() {
+            let (mut oracle, derived, market) = derived_oracle();
+            let stage = |min_facilities, min_certified_volume, reduction_factor| TrajectoryStage {
+                min_facilities,
+                min_certified_volume,
+                reduction_factor,
+            };
+            for trajectory in [
+                vec![],
+                // Does not start at zero network scale
+                vec![stage(1, 0, 1_000_000)],
+                // Starts above 1.0x
+                vec![stage(0, 0, 1_000_001)],
+                // Thresholds must rise on both metrics
+                vec![stage(0, 0, 1_000_000), stage(10, 0, 500_000)],
+                vec![stage(0, 0, 1_000_000), stage(10, 100, 500_000), stage(10, 200, 400_000)],
+                // The factor may never rise
+                vec![stage(0, 0, 500_000), stage(10, 100, 600_000)],
+            ] {
+                assert_eq!(
+                    oracle.configure_derived_feed(derived, market, 1_000_000, trajectory),
+                    Err(Error::InvalidTrajectory)
+                );
+            }
+            // A flat trajectory is fine
+            assert_eq!(
+                oracle.configure_derived_feed(derived, market, 1_000_000, vec![stage(0, 0, 1_000_000), stage(10, 100, 1_000_000)]),
+                Ok(())
+            );
+        }
+
+        #[ink::test]
+        fn adjustment_factors_scale_the_peg_within_bounds() {
+            let (mut oracle, derived, _) = derived_oracle();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+
+            for (quality, market, governance) in [(0, 1_000_000, 1_000_000), (1_000_000, 2_000_001, 1_000_000)] {
+                assert_eq!(
+                    oracle.set_adjustment_factors(derived, quality, market, governance),
+                    Err(Error::InvalidFactor)
+                );
+            }
+            assert_eq!(
+                oracle.set_adjustment_factors([9u8; 32], 1_000_000, 1_000_000, 1_000_000),
+                Err(Error::FeedNotFound)
+            );
+
+            // 0.9x quality and a 1.1x market adjustment
+            oracle.set_adjustment_factors(derived, 900_000, 1_100_000, 1_000_000).unwrap();
+            assert_eq!(oracle.get_derived_price(derived).unwrap().reference_price, 990_000);
+
+            // Factors survive reconfiguring the trajectory
+            oracle.configure_derived_feed(derived, [1u8; 32], 2_000_000, default_trajectory()).unwrap();
+            assert_eq!(oracle.get_derived_feed(derived).unwrap().quality_factor, 900_000);
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                oracle.set_adjustment_factors(derived, 1_000_000, 1_000_000, 1_000_000),
+                Err(Error::NotAuthorized)
+            );
+        }
+
+        #[ink::test]
+        fn network_metrics_only_grow_and_come_from_validators() {
+            let (mut oracle, derived, _) = derived_oracle();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+
+            oracle.report_network_metrics(derived, 10, 1_000).unwrap();
+            assert_eq!(oracle.report_network_metrics(derived, 9, 2_000), Err(Error::MetricsRegression));
+            assert_eq!(oracle.report_network_metrics(derived, 20, 999), Err(Error::MetricsRegression));
+            assert_eq!(oracle.report_network_metrics([9u8; 32], 20, 2_000), Err(Error::FeedNotFound));
+            // Equal reports are a validator catching up, not a regression
+            assert_eq!(oracle.report_network_metrics(derived, 10, 1_000), Ok(()));
+
+            set_caller(accounts.bob);
+            assert_eq!(oracle.report_network_metrics(derived, 20, 2_000), Err(Error::NotValidator));
+        }
+
+        #[ink::test]
+        fn only_approved_reporters_move_metrics_and_the_owner_can_reset_them() {
+            let (mut oracle, derived, _) = derived_oracle();
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            oracle.report_network_metrics(derived, 10, 1_000).unwrap();
+
+            // Registering costs nothing, so it does not make bob a reporter
+            set_caller(accounts.bob);
+            oracle.register_validator(1, [0u8; KYBER_PUBLICKEYBYTES], Keypair::generate().public).unwrap();
+            assert_eq!(oracle.report_network_metrics(derived, 50_000, 5_000_000), Err(Error::NotAuthorized));
+            assert_eq!(oracle.approve_metrics_reporter(accounts.bob), Err(Error::NotAuthorized));
+            assert_eq!(oracle.reset_network_metrics(derived, 0, 0), Err(Error::NotAuthorized));
+
+            // Once approved, bob's inflated report pins the peg at the floor
+            set_caller(accounts.alice);
+            oracle.approve_metrics_reporter(accounts.bob).unwrap();
+            set_caller(accounts.bob);
+            oracle.report_network_metrics(derived, 50_000, 5_000_000).unwrap();
+            assert_eq!(factor(&oracle, derived), 100_000);
+
+            // until the owner revokes bob and puts the metrics back
+            set_caller(accounts.alice);
+            oracle.revoke_metrics_reporter(accounts.bob).unwrap();
+            oracle.reset_network_metrics(derived, 10, 1_000).unwrap();
+            assert_eq!(oracle.get_derived_feed(derived).unwrap().metrics.registered_facilities, 10);
+            assert_eq!(factor(&oracle, derived), 600_000);
+            set_caller(accounts.bob);
+            assert_eq!(oracle.report_network_metrics(derived, 50_000, 5_000_000), Err(Error::NotAuthorized));
+            assert_eq!(oracle.approve_metrics_reporter(accounts.django), Err(Error::NotAuthorized));
+        }
+
+        #[ink::test]
+        fn market_feeds_back_a_single_derived_feed() {
+            let (mut oracle, derived, market) = derived_oracle();
+            let other = [3u8; 32];
+
+            assert_eq!(
+                oracle.configure_derived_feed(other, market, 500_000, default_trajectory()),
+                Err(Error::MarketFeedInUse)
+            );
+
+            // Moving the first feed to another market frees the old one
+            oracle.configure_derived_feed(derived, [4u8; 32], 1_000_000, default_trajectory()).unwrap();
+            assert_eq!(oracle.configure_derived_feed(other, market, 500_000, default_trajectory()), Ok(()));
+
+            oracle.submit_price_update(market, 70_000, 100).unwrap();
+            assert_eq!(oracle.get_derived_price(other).unwrap().market_price, 70_000);
+            assert_eq!(oracle.get_derived_price(derived), None);
+        }
+
+        /// Header of block `number` on top of `parent`, with `seed` standing in for its state
+        fn build_header(parent: [u8; 32], number: u32, seed: u8) -> Vec<u8> {
+            (parent, scale::Compact(number), [seed; 32], [0u8; 32], Vec::<u8>::new()).encode()
+        }
+
+        #[ink::test]
+        fn only_approved_attesters_track_headers() {
+            let mut oracle = DaemonlessOracle::new(1, 2, 0);
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            let (alice_keys, bob_keys) = (Keypair::generate(), Keypair::generate());
+            oracle.register_validator(1_000, [0u8; KYBER_PUBLICKEYBYTES], alice_keys.public).unwrap();
+            set_caller(accounts.bob);
+            oracle.register_validator(1_000, [0u8; KYBER_PUBLICKEYBYTES], bob_keys.public).unwrap();
+            assert_eq!(oracle.approve_attester(accounts.bob), Err(Error::NotAuthorized));
+            set_caller(accounts.alice);
+            oracle.register_parachain(2000, [0u8; KYBER_PUBLICKEYBYTES], Vec::new()).unwrap();
+            assert_eq!(oracle.approve_attester(accounts.charlie), Err(Error::NotValidator));
+
+            let header = build_header([0u8; 32], 1, 1);
+            let message = (2000u32, blake2_256(&header)).encode();
+            let signatures = vec![
+                (accounts.alice, alice_keys.sign(&message)),
+                (accounts.bob, bob_keys.sign(&message)),
+            ];
+
+            oracle.approve_attester(accounts.alice).unwrap();
+            assert_eq!(oracle.submit_header(2000, header.clone(), signatures.clone()), Err(Error::ConsensusNotReached));
+            oracle.approve_attester(accounts.bob).unwrap();
+            assert!(oracle.submit_header(2000, header.clone(), signatures.clone()).is_ok());
+            assert_eq!(oracle.get_tracked_header(2000, 1).unwrap().attested_by, vec![accounts.alice, accounts.bob]);
+
+            // A revoked attester no longer counts
+            oracle.revoke_attester(accounts.bob).unwrap();
+            assert!(!oracle.is_attester(accounts.bob));
+            let next = build_header(blake2_256(&header), 2, 2);
+            let message = (2000u32, blake2_256(&next)).encode();
+            assert_eq!(
+                oracle.submit_header(
+                    2000,
+                    next,
+                    vec![(accounts.alice, alice_keys.sign(&message)), (accounts.bob, bob_keys.sign(&message))]
+                ),
+                Err(Error::ConsensusNotReached)
+            );
+        }
+
+        #[ink::test]
+        fn conflicting_headers_are_refused() {
+            let mut oracle = DaemonlessOracle::new(1, 1, 0);
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            let keys = Keypair::generate();
+            oracle.register_validator(1_000, [0u8; KYBER_PUBLICKEYBYTES], keys.public).unwrap();
+            oracle.approve_attester(accounts.alice).unwrap();
+            oracle.register_parachain(2000, [0u8; KYBER_PUBLICKEYBYTES], Vec::new()).unwrap();
+            let submit = |oracle: &mut DaemonlessOracle, header: &Vec<u8>| {
+                let message = (2000u32, blake2_256(header)).encode();
+                oracle.submit_header(2000, header.clone(), vec![(accounts.alice, keys.sign(&message))])
+            };
+
+            let block_7 = build_header([0u8; 32], 7, 1);
+            assert_eq!(submit(&mut oracle, &block_7), Ok(blake2_256(&block_7)));
+            // Resubmitting the same header is harmless
+            assert_eq!(submit(&mut oracle, &block_7), Ok(blake2_256(&block_7)));
+
+            // A fork at the same height, even fully attested, does not replace it
+            let fork_7 = build_header([0u8; 32], 7, 2);
+            assert_eq!(submit(&mut oracle, &fork_7), Err(Error::HeaderMismatch));
+            assert_eq!(oracle.get_tracked_header(2000, 7).unwrap().state_root, [1u8; 32]);
+
+            // Nor can a child of the fork be tracked on top of the real block
+            let child_of_fork = build_header(blake2_256(&fork_7), 8, 3);
+            assert_eq!(submit(&mut oracle, &child_of_fork), Err(Error::HeaderMismatch));
+            let child = build_header(blake2_256(&block_7), 8, 3);
+            assert_eq!(submit(&mut oracle, &child), Ok(blake2_256(&child)));
+
+            // Headers that are not SCALE headers at all
+            assert_eq!(submit(&mut oracle, &vec![0u8; 16]), Err(Error::InvalidHeader));
+        }
+
+        #[ink::test]
+        fn twap_and_price_history() {
+            let mut oracle = DaemonlessOracle::new(1, 1, 0);
+            let keys = Keypair::generate();
+            oracle.register_validator(1_000, [0u8; KYBER_PUBLICKEYBYTES], keys.public).unwrap();
+            oracle.set_history_depth(3).unwrap();
+            let feed = [1u8; 32];
+
+            let submit_at = |oracle: &mut DaemonlessOracle, timestamp: Timestamp, price: Balance| {
+                ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(timestamp);
+                oracle.submit_price_update(feed, price, 100).unwrap();
+            };
+
+            submit_at(&mut oracle, 1_000, 100);
+            submit_at(&mut oracle, 2_000, 200);
+            // Same block: the observation is replaced, not appended
+            submit_at(&mut oracle, 2_000, 300);
+            submit_at(&mut oracle, 4_000, 500);
+            assert_eq!(oracle.get_observations(feed).len(), 3);
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(6_000);
+            // 300 for 2s then 500 for 2s
+            assert_eq!(oracle.twap(feed, 4_000), Ok(400));
+            // 100 for 1s, 300 for 2s, 500 for 2s
+            assert_eq!(oracle.twap(feed, 5_000), Ok(340));
+            assert_eq!(oracle.twap(feed, 0), Ok(500));
+            assert_eq!(oracle.price_staleness(feed), Some(2_000));
+            assert_eq!(oracle.time_weighted_price(feed, 4_000), Some(400));
+            assert_eq!(oracle.price_at(feed, 3_999), Some(300));
+            assert_eq!(oracle.price_at(feed, 999), None);
+
+            // The ring wraps and the oldest observation falls out of history
+            submit_at(&mut oracle, 7_000, 600);
+            assert_eq!(oracle.price_at(feed, 1_500), None);
+            assert_eq!(oracle.twap(feed, 6_500), Err(Error::InsufficientHistory));
+            assert_eq!(oracle.price_at(feed, 7_000), Some(600));
+        }
     }
 }
@@ -0,0 +1,152 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+use ink_env::{
+    call::{build_call, Call, ExecutionInput, Selector},
+    AccountId, DefaultEnvironment,
+};
+use ink_lang as ink;
+use scale::{Decode, Encode};
+
+// Stable consumer interface of DaemonlessOracle
+// Contracts that read prices depend on this trait and its selectors only,
+// never on the oracle's storage layout or its other messages
+
+pub type FeedId = [u8; 32];
+pub type Balance = u128;
+pub type Timestamp = u64;
+
+/// Prices are fixed-point with 6 decimals (micro-USD for USD feeds)
+pub const PRICE_PRECISION: Balance = 1_000_000;
+
+/// Latest consensus price of a feed
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct PriceData {
+    pub price: Balance,
+    pub confidence: u8,
+    /// Block timestamp the price reached consensus
+    pub timestamp: Timestamp,
+    /// Milliseconds since `timestamp` at the time of the query
+    pub staleness: Timestamp,
+}
+
+#[ink::trait_definition]
+pub trait OracleConsumer {
+    /// Latest consensus price, `None` if the feed has never reached consensus
+    #[ink(message)]
+    fn latest_price(&self, feed_id: FeedId) -> Option<PriceData>;
+
+    #[ink(message)]
+    fn price_confidence(&self, feed_id: FeedId) -> Option<u8>;
+
+    /// Milliseconds since the feed last reached consensus
+    #[ink(message)]
+    fn price_staleness(&self, feed_id: FeedId) -> Option<Timestamp>;
+
+    /// Time-weighted average over the last `window` milliseconds
+    #[ink(message)]
+    fn time_weighted_price(&self, feed_id: FeedId, window: Timestamp) -> Option<Balance>;
+}
+
+/// Cross-contract handle for `OracleConsumer` used by the DEX and the liquidity pools
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct OracleRef {
+    pub account: AccountId,
+}
+
+impl OracleRef {
+    pub fn new(account: AccountId) -> Self {
+        Self { account }
+    }
+
+    pub fn latest_price(&self, feed_id: FeedId) -> Option<PriceData> {
+        build_call::<DefaultEnvironment>()
+            .call_type(Call::new().callee(self.account))
+            .exec_input(
+                ExecutionInput::new(Selector::new(ink::selector_bytes!("OracleConsumer::latest_price")))
+                    .push_arg(feed_id),
+            )
+            .returns::<Option<PriceData>>()
+            .fire()
+            .ok()
+            .flatten()
+    }
+
+    pub fn time_weighted_price(&self, feed_id: FeedId, window: Timestamp) -> Option<Balance> {
+        build_call::<DefaultEnvironment>()
+            .call_type(Call::new().callee(self.account))
+            .exec_input(
+                ExecutionInput::new(Selector::new(ink::selector_bytes!("OracleConsumer::time_weighted_price")))
+                    .push_arg(feed_id)
+                    .push_arg(window),
+            )
+            .returns::<Option<Balance>>()
+            .fire()
+            .ok()
+            .flatten()
+    }
+
+    /// Latest price if it is at most `max_staleness` old and at least `min_confidence`
+    pub fn fresh_price(
+        &self,
+        feed_id: FeedId,
+        max_staleness: Timestamp,
+        min_confidence: u8,
+    ) -> Option<PriceData> {
+        self.latest_price(feed_id)
+            .filter(|data| data.staleness <= max_staleness && data.confidence >= min_confidence)
+    }
+}
+
+/// PSP22 balances for the off-chain tests of the contracts trading tokens.
+/// Those tests cannot call token contracts, so the contracts' `#[cfg(test)]`
+/// transfers move balances here instead.
+#[cfg(feature = "std")]
+pub mod test_ledger {
+    use super::Balance;
+    use ink_env::AccountId;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    thread_local! {
+        // Balances by (token contract, holder)
+        static LEDGER: RefCell<BTreeMap<(AccountId, AccountId), Balance>> = RefCell::new(BTreeMap::new());
+    }
+
+    /// `from` holds less than the amount moved
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct InsufficientBalance;
+
+    pub fn transfer(
+        token: AccountId,
+        from: AccountId,
+        to: AccountId,
+        amount: Balance,
+    ) -> Result<(), InsufficientBalance> {
+        LEDGER.with(|ledger| {
+            let mut ledger = ledger.borrow_mut();
+            let from_balance = ledger.get(&(token, from)).copied().unwrap_or(0);
+            if from_balance < amount {
+                return Err(InsufficientBalance);
+            }
+            ledger.insert((token, from), from_balance - amount);
+            *ledger.entry((token, to)).or_insert(0) += amount;
+            Ok(())
+        })
+    }
+
+    pub fn mint(token: AccountId, to: AccountId, amount: Balance) {
+        LEDGER.with(|ledger| *ledger.borrow_mut().entry((token, to)).or_insert(0) += amount);
+    }
+
+    pub fn balance(token: AccountId, of: AccountId) -> Balance {
+        LEDGER.with(|ledger| ledger.borrow().get(&(token, of)).copied().unwrap_or(0))
+    }
+
+    /// Total held across all accounts
+    pub fn supply(token: AccountId) -> Balance {
+        LEDGER.with(|ledger| {
+            ledger.borrow().iter().filter(|((t, _), _)| *t == token).map(|(_, b)| *b).sum()
+        })
+    }
+}
@@ -0,0 +1,49 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+use ink_env::{
+    call::{build_call, Call, ExecutionInput, Selector},
+    AccountId, DefaultEnvironment,
+};
+use ink_lang as ink;
+use scale::{Decode, Encode};
+
+// Stable interface of proof-of-personhood registrars
+// Any contract recording verified identities (Humanity Protocol handprints,
+// KYC registrars, council-run allow-lists) can back the pools' sybil checks
+// by implementing this trait
+
+pub type Timestamp = u64;
+
+#[ink::trait_definition]
+pub trait PersonhoodRegistry {
+    /// Block timestamp until which `account` is verified as a unique human,
+    /// `None` if it never was or the verification was revoked
+    #[ink(message)]
+    fn verified_until(&self, account: AccountId) -> Option<Timestamp>;
+}
+
+/// Cross-contract handle for `PersonhoodRegistry`
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct PersonhoodRef {
+    pub account: AccountId,
+}
+
+impl PersonhoodRef {
+    pub fn new(account: AccountId) -> Self {
+        Self { account }
+    }
+
+    /// A registrar that cannot be called verifies no one
+    pub fn verified_until(&self, account: AccountId) -> Option<Timestamp> {
+        build_call::<DefaultEnvironment>()
+            .call_type(Call::new().callee(self.account))
+            .exec_input(
+                ExecutionInput::new(Selector::new(ink::selector_bytes!("PersonhoodRegistry::verified_until")))
+                    .push_arg(account),
+            )
+            .returns::<Option<Timestamp>>()
+            .fire()
+            .ok()
+            .flatten()
+    }
+}
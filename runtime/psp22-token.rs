@@ -0,0 +1,42 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+// Fungible PSP22 token used for NRSH, ELXR and IMRT on the contracts chain
+// ZeroSpreadDEX and UnifiedLiquidityPool hold and move these through their
+// allowances; the whole supply is minted to the deployer
+
+#[openbrush::contract]
+pub mod psp22_token {
+    use ink_storage::traits::SpreadAllocate;
+    use openbrush::{
+        contracts::psp22::extensions::metadata::*,
+        traits::{Storage, String},
+    };
+
+    #[ink(storage)]
+    #[derive(Default, SpreadAllocate, Storage)]
+    pub struct Psp22Token {
+        #[storage_field]
+        psp22: psp22::Data,
+        #[storage_field]
+        metadata: metadata::Data,
+    }
+
+    impl PSP22 for Psp22Token {}
+
+    impl PSP22Metadata for Psp22Token {}
+
+    impl Psp22Token {
+        #[ink(constructor)]
+        pub fn new(total_supply: Balance, name: Option<String>, symbol: Option<String>, decimals: u8) -> Self {
+            ink_lang::utils::initialize_contract(|instance: &mut Self| {
+                instance.metadata.name = name;
+                instance.metadata.symbol = symbol;
+                instance.metadata.decimals = decimals;
+                instance
+                    ._mint_to(instance.env().caller(), total_supply)
+                    .expect("minting the initial supply cannot overflow");
+            })
+        }
+    }
+}
@@ -6,6 +6,11 @@ use ink_storage::{
 };
 use pqc_kyber::*;
 use pqc_dilithium::*;
+use openbrush::contracts::traits::psp22::PSP22Ref;
+use oracle_consumer::{FeedId, OracleRef, PRICE_PRECISION};
+#[cfg(test)]
+use oracle_consumer::test_ledger;
+use personhood_registry::PersonhoodRef;
 use scale::{Decode, Encode};
 
 #[ink::contract]
@@ -15,20 +20,107 @@ mod unified_liquidity_pool {
     pub struct UnifiedLiquidityPool {
         // Token reserves for NRSH, ELXR, IMRT
         reserves: Mapping<TokenId, Balance>,
+        // PSP22 contract of each token
+        token_contracts: Mapping<TokenId, AccountId>,
         // Liquidity provider shares
         shares: Mapping<(AccountId, TokenId), Balance>,
-        // Post-quantum encrypted provider data
+        // Shares outstanding per token, the sum over all providers
+        total_shares: Mapping<TokenId, Balance>,
+        // Provider profiles, encrypted client-side to the vault key
         provider_data: Mapping<AccountId, EncryptedData>,
         // Treasury reserves
         treasury: Mapping<TokenId, Balance>,
+        // Bought back by the treasury and retired; held by the pool, never spent
+        burned: Mapping<TokenId, Balance>,
+        // Shares minted for swap inputs and owed to the providers of the tokens
+        // sold for them; counted in total_shares but held by nobody yet
+        proceeds: Mapping<TokenId, Balance>,
+        // Proceeds in shares of the second token per held share of the first
+        // since the start, scaled by REWARD_PRECISION
+        proceeds_per_share: Mapping<(TokenId, TokenId), Balance>,
+        // `shares * proceeds_per_share` of each position when last settled
+        proceeds_debt: Mapping<(AccountId, TokenId, TokenId), Balance>,
+        // Settled but not yet claimed, by the token they are paid in
+        settled_proceeds: Mapping<(AccountId, TokenId), Balance>,
+        // Treasury spending proposals, voted on with LP shares
+        proposals: Mapping<ProposalId, Proposal>,
+        next_proposal_id: ProposalId,
+        // (approve, weight) of each vote cast
+        votes: Mapping<(ProposalId, AccountId), (bool, Balance)>,
+        // (next_proposal_id, shares) whenever a provider's shares of a token
+        // change, at most one per proposal id and in order, for voting weights
+        share_checkpoints: Mapping<(AccountId, TokenId, u32), (ProposalId, Balance)>,
+        checkpoint_count: Mapping<(AccountId, TokenId), u32>,
+        // Shares voted with cannot be withdrawn before this time
+        vote_locks: Mapping<(AccountId, TokenId), Timestamp>,
+        voting_period: Timestamp,
+        timelock: Timestamp,
+        quorum: Balance,
+        // Liquidity mining: pool token paid as rewards and what is left to pay
+        reward_token: Option<TokenId>,
+        reward_funds: Balance,
+        reward_schedules: Mapping<TokenId, RewardSchedule>,
+        // Rewards per unit of weight since the start, scaled by REWARD_PRECISION
+        reward_per_weight: Mapping<TokenId, Balance>,
+        last_reward_time: Mapping<TokenId, Timestamp>,
+        // Sum of the providers' weights per token
+        total_weight: Mapping<TokenId, Balance>,
+        reward_positions: Mapping<(AccountId, TokenId), RewardPosition>,
+        // (duration, multiplier per mille) providers can lock their shares for
+        lock_tiers: Vec<(Timestamp, u32)>,
         // Protocol parameters
         fee_rate: Balance,
         treasury_rate: Balance,
-        // Quantum-resistant keys
+        // Quantum-resistant keys; the Kyber key is the operators' vault key
         kyber_public_key: KyberPublicKey,
+        // 0 until operators set a vault key they hold the secret of
+        kyber_key_version: u32,
         dilithium_signature: DilithiumSignature,
+        // Oracle USD prices for valuing NRSH, ELXR and IMRT against each other
+        oracle: Option<OracleRef>,
+        token_feeds: Mapping<TokenId, FeedId>,
+        max_oracle_staleness: Timestamp,
+        // Proof-of-personhood required to provide liquidity, None accepts everyone
+        personhood: Option<PersonhoodProvider>,
+        // Bumped when the provider changes, invalidating earlier verifications
+        personhood_epoch: u32,
+        // (epoch, expiry) of each account verified as a unique human
+        verified_humans: Mapping<AccountId, (u32, Timestamp)>,
+        personhood_cache_ttl: Timestamp,
+        // Governance
+        owner: AccountId,
     }
 
+    /// Oracle prices older than this are not used for valuation (10 minutes)
+    pub const DEFAULT_MAX_ORACLE_STALENESS: Timestamp = 600_000;
+
+    /// `fee_rate` and `treasury_rate` are parts per million of a swap's input
+    pub const FEE_DENOMINATOR: Balance = 1_000_000;
+
+    /// Registrar verifications are trusted this long before asking again (1 day)
+    pub const DEFAULT_PERSONHOOD_CACHE_TTL: Timestamp = 86_400_000;
+
+    /// Scale of the reward-per-weight accumulator
+    pub const REWARD_PRECISION: Balance = 1_000_000_000_000;
+    /// Lock-up multipliers are per mille; unlocked shares weigh 1x
+    pub const BASE_MULTIPLIER: u32 = 1_000;
+    pub const MAX_MULTIPLIER: u32 = 3_000;
+    pub const MAX_LOCK_TIERS: usize = 8;
+    const DAY: Timestamp = 86_400_000;
+    /// 30 days at 1.25x, 90 at 1.5x, 180 at 2x
+    pub const DEFAULT_LOCK_TIERS: [(Timestamp, u32); 3] =
+        [(30 * DAY, 1_250), (90 * DAY, 1_500), (180 * DAY, 2_000)];
+
+    pub const DEFAULT_VOTING_PERIOD: Timestamp = 3 * 86_400_000;
+    /// Time between the end of a vote and the earliest execution, for
+    /// providers who disagree to withdraw first
+    pub const DEFAULT_TIMELOCK: Timestamp = 2 * 86_400_000;
+    /// Votes in favour a proposal needs, per million of the shares outstanding when it was made
+    pub const DEFAULT_QUORUM: Balance = 100_000; // 10%
+
+    /// All tokens held by the unified pool
+    pub const POOL_TOKENS: [TokenId; 3] = [TokenId::NRSH, TokenId::ELXR, TokenId::IMRT];
+
     #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub enum TokenId {
@@ -37,10 +129,110 @@ mod unified_liquidity_pool {
         IMRT,
     }
 
-    #[derive(Encode, Decode)]
+    /// Where the pool learns that an account belongs to a unique human
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum PersonhoodProvider {
+        /// Accounts the owner verified with `set_verified_human`
+        AllowList,
+        /// A registrar contract implementing `PersonhoodRegistry`, asked again
+        /// once its answer is `personhood_cache_ttl` old
+        Registrar(PersonhoodRef),
+        /// Attestations the account submits with `submit_attestation`, signed
+        /// by the issuer with this compressed ECDSA key
+        Attestation([u8; 33]),
+    }
+
+    /// Emissions to the providers of one token: `rate` reward tokens per
+    /// second from `start` until `end`, shared by weight
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone, Default)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct RewardSchedule {
+        pub rate: Balance,
+        pub start: Timestamp,
+        pub end: Timestamp,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone, Default)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct RewardPosition {
+        /// Shares times the lock-up multiplier
+        pub weight: Balance,
+        /// `weight * reward_per_weight` when last settled
+        pub reward_debt: Balance,
+        /// Settled but not yet claimed
+        pub pending: Balance,
+        /// Shares cannot be withdrawn before this time
+        pub lock_until: Timestamp,
+        /// Per mille, applies until `lock_until`
+        pub multiplier: u32,
+    }
+
+    impl RewardPosition {
+        fn multiplier_at(&self, now: Timestamp) -> u32 {
+            if self.lock_until > now { self.multiplier } else { BASE_MULTIPLIER }
+        }
+    }
+
+    pub type ProposalId = u64;
+
+    /// What a treasury spending proposal does with its funds
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Spend {
+        /// Pay for cultivation or processing facilities
+        Facility(AccountId),
+        ResearchGrant(AccountId),
+        /// Buy `token` from the pool and retire it. Fails if the pool pays
+        /// less than `min_amount_out` when the proposal is executed.
+        BuybackAndBurn { token: TokenId, min_amount_out: Balance },
+    }
+
+    /// Spending of `amount` of `token_id` from the treasury. Providers of
+    /// `token_id` vote with their shares until `voting_ends`; a passed
+    /// proposal can be executed by anyone from `executable_at`.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Proposal {
+        pub proposer: AccountId,
+        pub token_id: TokenId,
+        pub amount: Balance,
+        pub spend: Spend,
+        /// Hash of the description published off-chain
+        pub description: Hash,
+        pub votes_for: Balance,
+        pub votes_against: Balance,
+        /// Shares of `token_id` held by providers when proposed, the quorum base
+        pub total_weight: Balance,
+        pub voting_ends: Timestamp,
+        pub executable_at: Timestamp,
+        pub status: ProposalStatus,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum ProposalStatus {
+        Active,
+        Executed,
+        Cancelled,
+    }
+
+    /// ML-KEM-768 ciphertext bytes, the encapsulated key of `EncryptedData`
+    pub const KEM_CIPHERTEXT_BYTES: usize = 1_088;
+    /// Largest provider profile, before the 16-byte authentication tag
+    pub const MAX_PROVIDER_DATA: usize = 4_096;
+    pub const AEAD_TAG_BYTES: usize = 16;
+
+    /// Provider data sealed to version `key_version` of the vault key: an
+    /// ML-KEM-768 encapsulation and the XChaCha20-Poly1305 ciphertext under
+    /// the shared key. The contract only checks sizes, it cannot decrypt.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub struct EncryptedData {
-        ciphertext: Vec<u8>,
-        nonce: [u8; 24],
+        pub key_version: u32,
+        pub kem_ciphertext: Vec<u8>,
+        pub ciphertext: Vec<u8>,
+        pub nonce: [u8; 24],
     }
 
     impl UnifiedLiquidityPool {
@@ -49,6 +241,13 @@ mod unified_liquidity_pool {
             ink_lang::utils::initialize_contract(|contract: &mut Self| {
                 contract.fee_rate = fee_rate;
                 contract.treasury_rate = treasury_rate;
+                contract.owner = Self::env().caller();
+                contract.max_oracle_staleness = DEFAULT_MAX_ORACLE_STALENESS;
+                contract.personhood_cache_ttl = DEFAULT_PERSONHOOD_CACHE_TTL;
+                contract.voting_period = DEFAULT_VOTING_PERIOD;
+                contract.timelock = DEFAULT_TIMELOCK;
+                contract.quorum = DEFAULT_QUORUM;
+                contract.lock_tiers = DEFAULT_LOCK_TIERS.to_vec();
                 
                 // Initialize post-quantum keys
                 let (public_key, _private_key) = kyber_keygen();
@@ -62,27 +261,48 @@ mod unified_liquidity_pool {
             })
         }
 
+        /// Deposit `amount` of `token_id` for at least `min_shares` of its shares
         #[ink(message)]
         pub fn add_liquidity(
             &mut self,
             token_id: TokenId,
             amount: Balance,
+            min_shares: Balance,
         ) -> Result<Balance, Error> {
             let caller = self.env().caller();
             
-            // Verify humanity protocol handprint
-            if !self.verify_human_handprint(&caller) {
+            if !self.verify_personhood(caller) {
                 return Err(Error::NotHuman);
             }
 
             // Calculate shares with post-quantum secure math
             let shares = self.calculate_shares(token_id, amount)?;
+            if shares == 0 {
+                return Err(Error::ZeroShares);
+            }
+            if shares < min_shares {
+                return Err(Error::SlippageExceeded);
+            }
+            let total_shares = self.total_shares(token_id)
+                .checked_add(shares)
+                .ok_or(Error::ArithmeticError)?;
+            let provider_shares = self.shares.get((caller, token_id))
+                .unwrap_or(0)
+                .checked_add(shares)
+                .ok_or(Error::ArithmeticError)?;
+            
+            // Take the tokens before crediting them
+            self.pull(token_id, caller, amount)?;
             
             // Update reserves with quantum-resistant encryption
             self.update_reserves(token_id, amount, true)?;
             
             // Update provider shares
-            self.shares.insert((caller, token_id), &shares);
+            self.settle_proceeds(caller, token_id, provider_shares)?;
+            self.settle_rewards(caller, token_id, provider_shares)?;
+            self.shares.insert((caller, token_id), &provider_shares);
+            self.total_shares.insert(token_id, &total_shares);
+            self.checkpoint_shares(caller, token_id, provider_shares);
             
             // Emit encrypted event
             self.env().emit_event(LiquidityAdded {
@@ -107,21 +327,35 @@ mod unified_liquidity_pool {
             let provider_shares = self.shares.get((caller, token_id))
                 .ok_or(Error::InsufficientShares)?;
                 
-            if provider_shares < shares {
+            if provider_shares < shares || shares == 0 {
                 return Err(Error::InsufficientShares);
             }
+            let now = self.env().block_timestamp();
+            let lock_until = self.reward_positions.get((caller, token_id))
+                .map(|position| position.lock_until)
+                .unwrap_or(0);
+            if self.vote_locks.get((caller, token_id)).unwrap_or(0) > now || lock_until > now {
+                return Err(Error::SharesLocked);
+            }
 
             // Calculate amount with post-quantum secure math
             let amount = self.calculate_withdrawal_amount(token_id, shares)?;
+            let total_shares = self.total_shares(token_id) - shares;
             
             // Update reserves
             self.update_reserves(token_id, amount, false)?;
             
             // Update shares
+            self.settle_proceeds(caller, token_id, provider_shares - shares)?;
+            self.settle_rewards(caller, token_id, provider_shares - shares)?;
             self.shares.insert(
                 (caller, token_id),
                 &(provider_shares - shares)
             );
+            self.total_shares.insert(token_id, &total_shares);
+            self.checkpoint_shares(caller, token_id, provider_shares - shares);
+            
+            self.push(token_id, caller, amount)?;
 
             // Emit encrypted event
             self.env().emit_event(LiquidityRemoved {
@@ -134,68 +368,2085 @@ mod unified_liquidity_pool {
             Ok(amount)
         }
 
-        // Helper functions
-        fn verify_human_handprint(&self, account: &AccountId) -> bool {
-            // Integrate with Humanity Protocol for verification
-            true // Simplified for example
+        /// Lock all of the caller's `token_id` shares for the duration of
+        /// `lock_tiers[tier]`, weighting them by its multiplier until then.
+        /// A lock can be extended or raised, never shortened.
+        #[ink(message)]
+        pub fn lock_shares(&mut self, token_id: TokenId, tier: u32) -> Result<Timestamp, Error> {
+            let caller = self.env().caller();
+            let (duration, multiplier) = *self.lock_tiers.get(tier as usize)
+                .ok_or(Error::InvalidParameter)?;
+            let shares = self.shares_of(caller, token_id);
+            if shares == 0 {
+                return Err(Error::InsufficientShares);
+            }
+            let now = self.env().block_timestamp();
+            let lock_until = now.checked_add(duration).ok_or(Error::ArithmeticError)?;
+            let position = self.reward_positions.get((caller, token_id)).unwrap_or_default();
+            if position.lock_until > lock_until || position.multiplier_at(now) > multiplier {
+                return Err(Error::SharesLocked);
+            }
+
+            self.settle_rewards(caller, token_id, shares)?;
+            let mut position = self.reward_positions.get((caller, token_id)).unwrap_or_default();
+            position.lock_until = lock_until;
+            position.multiplier = multiplier;
+            self.reward_positions.insert((caller, token_id), &position);
+            // Reweigh with the new multiplier
+            self.settle_rewards(caller, token_id, shares)?;
+
+            self.env().emit_event(LiquidityLocked {
+                provider: caller,
+                token_id,
+                until: lock_until,
+                multiplier,
+            });
+
+            Ok(lock_until)
         }
 
-        fn calculate_shares(
-            &self,
+        /// Pay out the caller's rewards from all three tokens' emissions
+        #[ink(message)]
+        pub fn claim_rewards(&mut self) -> Result<Balance, Error> {
+            let caller = self.env().caller();
+            let reward_token = self.reward_token.ok_or(Error::RewardsNotConfigured)?;
+            if !self.verify_personhood(caller) {
+                return Err(Error::NotHuman);
+            }
+            let claimable = POOL_TOKENS.iter().try_fold(0, |total: Balance, token_id| {
+                total.checked_add(self.pending_rewards(caller, *token_id)?)
+                    .ok_or(Error::ArithmeticError)
+            })?;
+            if claimable > self.reward_funds {
+                return Err(Error::InsufficientRewardFunds);
+            }
+
+            for token_id in POOL_TOKENS {
+                let shares = self.shares_of(caller, token_id);
+                self.settle_rewards(caller, token_id, shares)?;
+                if let Some(mut position) = self.reward_positions.get((caller, token_id)) {
+                    position.pending = 0;
+                    self.reward_positions.insert((caller, token_id), &position);
+                }
+            }
+            self.reward_funds -= claimable;
+            self.push(reward_token, caller, claimable)?;
+
+            self.env().emit_event(RewardsClaimed {
+                provider: caller,
+                amount: claimable,
+            });
+
+            Ok(claimable)
+        }
+
+        /// Rewards `provider` has earned on `token_id` and not claimed
+        #[ink(message)]
+        pub fn pending_rewards(&self, provider: AccountId, token_id: TokenId) -> Result<Balance, Error> {
+            let position = self.reward_positions.get((provider, token_id)).unwrap_or_default();
+            let accrued = position.weight
+                .checked_mul(self.current_reward_per_weight(token_id)?)
+                .ok_or(Error::ArithmeticError)?
+                / REWARD_PRECISION;
+            Ok(position.pending + accrued.saturating_sub(position.reward_debt))
+        }
+
+        #[ink(message)]
+        pub fn reward_position(&self, provider: AccountId, token_id: TokenId) -> RewardPosition {
+            self.reward_positions.get((provider, token_id)).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn reward_schedule(&self, token_id: TokenId) -> RewardSchedule {
+            self.reward_schedules.get(token_id).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn lock_tiers(&self) -> Vec<(Timestamp, u32)> {
+            self.lock_tiers.clone()
+        }
+
+        /// Reward tokens funded and not yet paid out
+        #[ink(message)]
+        pub fn reward_funds(&self) -> Balance {
+            self.reward_funds
+        }
+
+        /// Drop the boost of an expired lock, so it stops diluting the
+        /// other providers; anyone may call it
+        #[ink(message)]
+        pub fn refresh_position(&mut self, provider: AccountId, token_id: TokenId) -> Result<(), Error> {
+            let shares = self.shares_of(provider, token_id);
+            self.settle_rewards(provider, token_id, shares)
+        }
+
+        /// Add reward tokens for emissions to pay out of; anyone may fund
+        #[ink(message)]
+        pub fn fund_rewards(&mut self, amount: Balance) -> Result<(), Error> {
+            let reward_token = self.reward_token.ok_or(Error::RewardsNotConfigured)?;
+            let funds = self.reward_funds.checked_add(amount).ok_or(Error::ArithmeticError)?;
+            self.pull(reward_token, self.env().caller(), amount)?;
+            self.reward_funds = funds;
+            Ok(())
+        }
+
+        /// Pay rewards in `token_id`; only while no funds are held in another token
+        #[ink(message)]
+        pub fn set_reward_token(&mut self, token_id: TokenId) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if self.reward_funds > 0 {
+                return Err(Error::ReservesNotEmpty);
+            }
+            self.reward_token = Some(token_id);
+            Ok(())
+        }
+
+        /// Emissions already accrued are kept at the old schedule
+        #[ink(message)]
+        pub fn set_reward_schedule(
+            &mut self,
             token_id: TokenId,
-            amount: Balance,
+            schedule: RewardSchedule,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if schedule.end < schedule.start {
+                return Err(Error::InvalidParameter);
+            }
+            self.update_rewards(token_id)?;
+            self.reward_schedules.insert(token_id, &schedule);
+            Ok(())
+        }
+
+        /// Applies to locks made from now on
+        #[ink(message)]
+        pub fn set_lock_tiers(&mut self, tiers: Vec<(Timestamp, u32)>) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if tiers.len() > MAX_LOCK_TIERS
+                || tiers.iter().any(|(_, multiplier)| *multiplier < BASE_MULTIPLIER || *multiplier > MAX_MULTIPLIER)
+            {
+                return Err(Error::InvalidParameter);
+            }
+            self.lock_tiers = tiers;
+            Ok(())
+        }
+
+        /// Swap `amount_in` of `token_in` for at least `min_amount_out` of
+        /// `token_out` against the pool's reserves. `fee_rate` of the input is
+        /// charged; the `treasury_rate` part of it goes to the treasury and the
+        /// rest, with the input, joins the `token_in` reserve. The providers of
+        /// `token_out`, whose reserve the output is taken from, are paid the
+        /// `token_in` shares minted for it.
+        #[ink(message)]
+        pub fn swap(
+            &mut self,
+            token_in: TokenId,
+            token_out: TokenId,
+            amount_in: Balance,
+            min_amount_out: Balance,
+        ) -> Result<Balance, Error> {
+            let caller = self.env().caller();
+            let (amount_out, fee, treasury_fee) = self.calculate_swap(token_in, token_out, amount_in)?;
+            if amount_out < min_amount_out {
+                return Err(Error::SlippageExceeded);
+            }
+            let treasury = self.treasury_balance(token_in)
+                .checked_add(treasury_fee)
+                .ok_or(Error::ArithmeticError)?;
+            let credited = amount_in - treasury_fee;
+            let (per_share, minted) = self.share_proceeds(token_out, token_in, credited)?;
+
+            self.pull(token_in, caller, amount_in)?;
+            self.treasury.insert(token_in, &treasury);
+            self.credit_proceeds(token_out, token_in, per_share, minted)?;
+            self.update_reserves(token_in, credited, true)?;
+            self.update_reserves(token_out, amount_out, false)?;
+            self.push(token_out, caller, amount_out)?;
+
+            self.env().emit_event(Swapped {
+                trader: caller,
+                token_in,
+                token_out,
+                amount_in,
+                amount_out,
+                fee,
+                treasury_fee,
+            });
+
+            Ok(amount_out)
+        }
+
+        /// Output of swapping `amount_in` of `token_in` for `token_out` at the current reserves
+        #[ink(message)]
+        pub fn quote_swap(
+            &self,
+            token_in: TokenId,
+            token_out: TokenId,
+            amount_in: Balance,
         ) -> Result<Balance, Error> {
-            // Post-quantum secure calculation
-            // Implementation details...
-            Ok(amount) // Simplified for example
+            self.calculate_swap(token_in, token_out, amount_in)
+                .map(|(amount_out, _, _)| amount_out)
         }
 
-        fn update_reserves(
+        /// Swap fees of `token_id` set aside for the treasury; held by the
+        /// pool but not part of the reserve
+        #[ink(message)]
+        pub fn treasury_balance(&self, token_id: TokenId) -> Balance {
+            self.treasury.get(token_id).unwrap_or(0)
+        }
+
+        /// Shares of `token_id` minted for swap inputs and not claimed yet by
+        /// the providers of the tokens sold for them
+        #[ink(message)]
+        pub fn proceeds(&self, token_id: TokenId) -> Balance {
+            self.proceeds.get(token_id).unwrap_or(0)
+        }
+
+        /// Shares of `token_id` that `provider` earned by selling the other
+        /// tokens they provide and has not claimed
+        #[ink(message)]
+        pub fn pending_proceeds(&self, provider: AccountId, token_id: TokenId) -> Result<Balance, Error> {
+            POOL_TOKENS.iter()
+                .filter(|sold| **sold != token_id)
+                .try_fold(self.settled_proceeds.get((provider, token_id)).unwrap_or(0), |total, sold| {
+                    total.checked_add(self.unsettled_proceeds(provider, *sold, token_id)?)
+                        .ok_or(Error::ArithmeticError)
+                })
+        }
+
+        /// Add the caller's proceeds in `token_id` to their shares of it. The
+        /// shares earn proceeds and rewards of their own only once claimed.
+        #[ink(message)]
+        pub fn claim_proceeds(&mut self, token_id: TokenId) -> Result<Balance, Error> {
+            let caller = self.env().caller();
+            let claimable = self.pending_proceeds(caller, token_id)?;
+            let provider_shares = self.shares_of(caller, token_id)
+                .checked_add(claimable)
+                .ok_or(Error::ArithmeticError)?;
+
+            for sold in POOL_TOKENS {
+                if sold != token_id {
+                    let shares = self.shares_of(caller, sold);
+                    self.settle_proceeds(caller, sold, shares)?;
+                }
+            }
+            self.settled_proceeds.remove((caller, token_id));
+            self.proceeds.insert(token_id, &(self.proceeds(token_id) - claimable));
+            self.settle_proceeds(caller, token_id, provider_shares)?;
+            self.settle_rewards(caller, token_id, provider_shares)?;
+            self.shares.insert((caller, token_id), &provider_shares);
+            self.checkpoint_shares(caller, token_id, provider_shares);
+
+            self.env().emit_event(ProceedsClaimed {
+                provider: caller,
+                token_id,
+                shares: claimable,
+            });
+
+            Ok(claimable)
+        }
+
+        /// Version and ML-KEM-768 encapsulation key providers encrypt their data to
+        #[ink(message)]
+        pub fn vault_key(&self) -> (u32, KyberPublicKey) {
+            (self.kyber_key_version, self.kyber_public_key)
+        }
+
+        /// Publish a new vault key. Data sealed to older keys stays readable by
+        /// operators holding them until providers rotate it.
+        #[ink(message)]
+        pub fn set_vault_key(&mut self, public_key: KyberPublicKey) -> Result<u32, Error> {
+            self.ensure_owner()?;
+            self.kyber_public_key = public_key;
+            self.kyber_key_version += 1;
+            Ok(self.kyber_key_version)
+        }
+
+        /// Store the caller's profile, sealed to the current vault key
+        #[ink(message)]
+        pub fn store_provider_data(&mut self, data: EncryptedData) -> Result<(), Error> {
+            let caller = self.env().caller();
+            self.check_provider_data(&data)?;
+            self.provider_data.insert(caller, &data);
+            self.env().emit_event(ProviderDataStored {
+                provider: caller,
+                key_version: data.key_version,
+            });
+            Ok(())
+        }
+
+        /// Replace the caller's stored profile, re-sealed after a vault key
+        /// rotation or with updated contents
+        #[ink(message)]
+        pub fn rotate_provider_data(&mut self, data: EncryptedData) -> Result<(), Error> {
+            if !self.provider_data.contains(self.env().caller()) {
+                return Err(Error::NoProviderData);
+            }
+            self.store_provider_data(data)
+        }
+
+        #[ink(message)]
+        pub fn remove_provider_data(&mut self) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if !self.provider_data.contains(caller) {
+                return Err(Error::NoProviderData);
+            }
+            self.provider_data.remove(caller);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn provider_data(&self, provider: AccountId) -> Option<EncryptedData> {
+            self.provider_data.get(provider)
+        }
+
+        /// Tokens bought back by the treasury and retired
+        #[ink(message)]
+        pub fn burned(&self, token_id: TokenId) -> Balance {
+            self.burned.get(token_id).unwrap_or(0)
+        }
+
+        /// Propose spending `amount` of the `token_id` treasury. Only
+        /// providers of `token_id` may propose.
+        #[ink(message)]
+        pub fn propose(
             &mut self,
             token_id: TokenId,
             amount: Balance,
-            is_addition: bool,
-        ) -> Result<(), Error> {
-            let current = self.reserves.get(token_id)
-                .unwrap_or(0);
-            
-            let new_amount = if is_addition {
-                current.checked_add(amount)
-            } else {
-                current.checked_sub(amount)
-            }.ok_or(Error::ArithmeticError)?;
-            
-            self.reserves.insert(token_id, &new_amount);
+            spend: Spend,
+            description: Hash,
+        ) -> Result<ProposalId, Error> {
+            let caller = self.env().caller();
+            if self.shares_of(caller, token_id) == 0 {
+                return Err(Error::NoVotingPower);
+            }
+            if amount == 0 || amount > self.treasury_balance(token_id) {
+                return Err(Error::InsufficientTreasury);
+            }
+            if let Spend::BuybackAndBurn { token, .. } = spend {
+                if token == token_id {
+                    return Err(Error::IdenticalTokens);
+                }
+            }
+
+            let now = self.env().block_timestamp();
+            let voting_ends = now.checked_add(self.voting_period).ok_or(Error::ArithmeticError)?;
+            let executable_at = voting_ends.checked_add(self.timelock).ok_or(Error::ArithmeticError)?;
+            let proposal_id = self.next_proposal_id;
+            self.next_proposal_id += 1;
+            self.proposals.insert(proposal_id, &Proposal {
+                proposer: caller,
+                token_id,
+                amount,
+                spend,
+                description,
+                votes_for: 0,
+                votes_against: 0,
+                total_weight: self.held_shares(token_id),
+                voting_ends,
+                executable_at,
+                status: ProposalStatus::Active,
+            });
+
+            self.env().emit_event(ProposalCreated {
+                proposal_id,
+                proposer: caller,
+                token_id,
+                amount,
+                spend,
+            });
+
+            Ok(proposal_id)
+        }
+
+        /// Vote with the shares of the proposal's token held both when it was
+        /// proposed and now, so shares deposited for the vote do not count.
+        /// They stay locked in the pool until voting ends.
+        #[ink(message)]
+        pub fn vote(&mut self, proposal_id: ProposalId, approve: bool) -> Result<Balance, Error> {
+            let caller = self.env().caller();
+            let mut proposal = self.proposals.get(proposal_id).ok_or(Error::ProposalNotFound)?;
+            if proposal.status != ProposalStatus::Active || self.env().block_timestamp() >= proposal.voting_ends {
+                return Err(Error::VotingClosed);
+            }
+            if self.votes.contains((proposal_id, caller)) {
+                return Err(Error::AlreadyVoted);
+            }
+            let weight = self.shares_at(caller, proposal.token_id, proposal_id)
+                .min(self.shares_of(caller, proposal.token_id));
+            if weight == 0 {
+                return Err(Error::NoVotingPower);
+            }
+
+            let tally = if approve { &mut proposal.votes_for } else { &mut proposal.votes_against };
+            *tally = tally.checked_add(weight).ok_or(Error::ArithmeticError)?;
+            let lock = self.vote_locks.get((caller, proposal.token_id)).unwrap_or(0);
+            self.vote_locks.insert((caller, proposal.token_id), &lock.max(proposal.voting_ends));
+            self.votes.insert((proposal_id, caller), &(approve, weight));
+            self.proposals.insert(proposal_id, &proposal);
+
+            self.env().emit_event(Voted {
+                proposal_id,
+                voter: caller,
+                approve,
+                weight,
+            });
+
+            Ok(weight)
+        }
+
+        /// Carry out a passed proposal once its timelock has run out. Returns
+        /// the amount retired for a buyback, 0 otherwise.
+        #[ink(message)]
+        pub fn execute_proposal(&mut self, proposal_id: ProposalId) -> Result<Balance, Error> {
+            let mut proposal = self.proposals.get(proposal_id).ok_or(Error::ProposalNotFound)?;
+            if proposal.status != ProposalStatus::Active {
+                return Err(Error::ProposalNotActive);
+            }
+            if self.env().block_timestamp() < proposal.executable_at {
+                return Err(Error::TimelockActive);
+            }
+            if !self.passed(&proposal) {
+                return Err(Error::ProposalRejected);
+            }
+            let treasury = self.treasury_balance(proposal.token_id);
+            if proposal.amount > treasury {
+                return Err(Error::InsufficientTreasury);
+            }
+
+            let (recipient, burned) = match proposal.spend {
+                Spend::Facility(recipient) | Spend::ResearchGrant(recipient) => (Some(recipient), 0),
+                Spend::BuybackAndBurn { token, min_amount_out } => {
+                    let (amount_out, _, _) = self.calculate_swap(proposal.token_id, token, proposal.amount)?;
+                    if amount_out < min_amount_out {
+                        return Err(Error::SlippageExceeded);
+                    }
+                    let burned = self.burned(token).checked_add(amount_out).ok_or(Error::ArithmeticError)?;
+                    // The treasury pays the full fee to the providers of `token`
+                    let (per_share, minted) = self.share_proceeds(token, proposal.token_id, proposal.amount)?;
+                    self.credit_proceeds(token, proposal.token_id, per_share, minted)?;
+                    self.update_reserves(proposal.token_id, proposal.amount, true)?;
+                    self.update_reserves(token, amount_out, false)?;
+                    self.burned.insert(token, &burned);
+                    (None, amount_out)
+                }
+            };
+            self.treasury.insert(proposal.token_id, &(treasury - proposal.amount));
+            proposal.status = ProposalStatus::Executed;
+            self.proposals.insert(proposal_id, &proposal);
+            if let Some(recipient) = recipient {
+                self.push(proposal.token_id, recipient, proposal.amount)?;
+            }
+
+            self.env().emit_event(ProposalExecuted {
+                proposal_id,
+                token_id: proposal.token_id,
+                amount: proposal.amount,
+                spend: proposal.spend,
+                burned,
+            });
+
+            Ok(burned)
+        }
+
+        /// Withdraw a proposal before it is executed; the proposer or the owner may
+        #[ink(message)]
+        pub fn cancel_proposal(&mut self, proposal_id: ProposalId) -> Result<(), Error> {
+            let mut proposal = self.proposals.get(proposal_id).ok_or(Error::ProposalNotFound)?;
+            let caller = self.env().caller();
+            if caller != proposal.proposer && caller != self.owner {
+                return Err(Error::NotAuthorized);
+            }
+            if proposal.status != ProposalStatus::Active {
+                return Err(Error::ProposalNotActive);
+            }
+            proposal.status = ProposalStatus::Cancelled;
+            self.proposals.insert(proposal_id, &proposal);
             Ok(())
         }
-    }
 
-    // Events
-    #[ink(event)]
-    pub struct LiquidityAdded {
-        #[ink(topic)]
-        provider: AccountId,
-        token_id: TokenId,
-        amount: Balance,
-        shares: Balance,
-    }
+        #[ink(message)]
+        pub fn get_proposal(&self, proposal_id: ProposalId) -> Option<Proposal> {
+            self.proposals.get(proposal_id)
+        }
 
-    #[ink(event)]
-    pub struct LiquidityRemoved {
-        #[ink(topic)]
-        provider: AccountId,
-        token_id: TokenId,
-        amount: Balance,
-        shares: Balance,
-    }
+        /// (approve, weight) of `voter`'s vote on a proposal
+        #[ink(message)]
+        pub fn vote_of(&self, proposal_id: ProposalId, voter: AccountId) -> Option<(bool, Balance)> {
+            self.votes.get((proposal_id, voter))
+        }
 
-    // Custom errors
-    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
-    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
-    pub enum Error {
-        InsufficientShares,
-        ArithmeticError,
-        NotHuman,
-        // Add more error types as needed
+        /// Whether a proposal has passed, or would with the votes cast so far
+        #[ink(message)]
+        pub fn proposal_passed(&self, proposal_id: ProposalId) -> bool {
+            self.proposals.get(proposal_id)
+                .map(|proposal| self.passed(&proposal))
+                .unwrap_or(false)
+        }
+
+        #[ink(message)]
+        pub fn set_governance_params(
+            &mut self,
+            voting_period: Timestamp,
+            timelock: Timestamp,
+            quorum: Balance,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if voting_period == 0 || quorum > FEE_DENOMINATOR {
+                return Err(Error::InvalidParameter);
+            }
+            self.voting_period = voting_period;
+            self.timelock = timelock;
+            self.quorum = quorum;
+            Ok(())
+        }
+
+        /// Reserve of `token_id` held by the pool
+        #[ink(message)]
+        pub fn reserve(&self, token_id: TokenId) -> Balance {
+            self.reserves.get(token_id).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn shares_of(&self, provider: AccountId, token_id: TokenId) -> Balance {
+            self.shares.get((provider, token_id)).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn total_shares(&self, token_id: TokenId) -> Balance {
+            self.total_shares.get(token_id).unwrap_or(0)
+        }
+
+        /// Shares minted for depositing `amount` of `token_id` at the current reserve
+        #[ink(message)]
+        pub fn quote_shares(&self, token_id: TokenId, amount: Balance) -> Result<Balance, Error> {
+            self.calculate_shares(token_id, amount)
+        }
+
+        /// Amount of `token_id` paid out for redeeming `shares` at the current reserve
+        #[ink(message)]
+        pub fn quote_withdrawal(&self, token_id: TokenId, shares: Balance) -> Result<Balance, Error> {
+            self.calculate_withdrawal_amount(token_id, shares)
+        }
+
+        #[ink(message)]
+        pub fn token_contract(&self, token_id: TokenId) -> Option<AccountId> {
+            self.token_contracts.get(token_id)
+        }
+
+        /// Point `token_id` at its PSP22 contract. Only possible while the pool
+        /// holds no reserve of it, so reserves always match the contract's balances.
+        #[ink(message)]
+        pub fn set_token_contract(
+            &mut self,
+            token_id: TokenId,
+            contract: AccountId,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if self.reserve(token_id) > 0 {
+                return Err(Error::ReservesNotEmpty);
+            }
+            self.token_contracts.insert(token_id, &contract);
+            Ok(())
+        }
+
+        /// Switch the proof-of-personhood check; `None` accepts everyone. Every
+        /// account has to be verified again with the new provider.
+        #[ink(message)]
+        pub fn set_personhood_provider(
+            &mut self,
+            provider: Option<PersonhoodProvider>,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.personhood = provider;
+            self.personhood_epoch = self.personhood_epoch.wrapping_add(1);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_personhood_cache_ttl(&mut self, ttl: Timestamp) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.personhood_cache_ttl = ttl;
+            Ok(())
+        }
+
+        /// Verify `account` until `expires_at` on the allow-list
+        #[ink(message)]
+        pub fn set_verified_human(
+            &mut self,
+            account: AccountId,
+            expires_at: Timestamp,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if self.personhood != Some(PersonhoodProvider::AllowList) {
+                return Err(Error::InvalidParameter);
+            }
+            self.record_verification(account, expires_at);
+            Ok(())
+        }
+
+        /// Drop a verification before it expires, under any provider. A
+        /// registrar that still vouches for the account verifies it again.
+        #[ink(message)]
+        pub fn revoke_verification(&mut self, account: AccountId) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.verified_humans.remove(account);
+            Ok(())
+        }
+
+        /// Verify the caller with the issuer's signature over
+        /// `blake2x256((pool, caller, expires_at).encode())`
+        #[ink(message)]
+        pub fn submit_attestation(
+            &mut self,
+            expires_at: Timestamp,
+            signature: [u8; 65],
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let issuer = match self.personhood {
+                Some(PersonhoodProvider::Attestation(issuer)) => issuer,
+                _ => return Err(Error::InvalidAttestation),
+            };
+            if expires_at <= self.env().block_timestamp() {
+                return Err(Error::InvalidAttestation);
+            }
+            let mut message_hash = [0u8; 32];
+            ink_env::hash_bytes::<ink_env::hash::Blake2x256>(
+                &(self.env().account_id(), caller, expires_at).encode(),
+                &mut message_hash,
+            );
+            let mut signer = [0u8; 33];
+            if ink_env::ecdsa_recover(&signature, &message_hash, &mut signer).is_err() || signer != issuer {
+                return Err(Error::InvalidAttestation);
+            }
+            self.record_verification(caller, expires_at);
+            Ok(())
+        }
+
+        /// Whether `account` may provide liquidity now. Registrar answers not
+        /// yet cached are looked up, but only cached by `add_liquidity`.
+        #[ink(message)]
+        pub fn is_verified_human(&self, account: AccountId) -> bool {
+            self.personhood_expiry(account).is_some()
+        }
+
+        #[ink(message)]
+        pub fn set_oracle(
+            &mut self,
+            oracle: AccountId,
+            max_staleness: Timestamp,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.oracle = Some(OracleRef::new(oracle));
+            self.max_oracle_staleness = max_staleness;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_token_feed(
+            &mut self,
+            token_id: TokenId,
+            feed_id: FeedId,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.token_feeds.insert(token_id, &feed_id);
+            Ok(())
+        }
+
+        /// USD value of `amount` of `token_id`, scaled by `PRICE_PRECISION`
+        #[ink(message)]
+        pub fn token_value(
+            &self,
+            token_id: TokenId,
+            amount: Balance,
+        ) -> Result<Balance, Error> {
+            let price = self.fresh_token_price(token_id)?;
+            amount.checked_mul(price)
+                .map(|value| value / PRICE_PRECISION)
+                .ok_or(Error::ArithmeticError)
+        }
+
+        /// USD value of all NRSH, ELXR and IMRT reserves
+        #[ink(message)]
+        pub fn pool_value(&self) -> Result<Balance, Error> {
+            POOL_TOKENS.iter().try_fold(0, |total: Balance, token_id| {
+                let reserve = self.reserves.get(token_id).unwrap_or(0);
+                if reserve == 0 {
+                    return Ok(total);
+                }
+                total.checked_add(self.token_value(*token_id, reserve)?)
+                    .ok_or(Error::ArithmeticError)
+            })
+        }
+
+        /// USD value of everything `provider` can withdraw or claim across the three tokens
+        #[ink(message)]
+        pub fn provider_value(&self, provider: AccountId) -> Result<Balance, Error> {
+            POOL_TOKENS.iter().try_fold(0, |total: Balance, token_id| {
+                let shares = self.shares.get((provider, *token_id))
+                    .unwrap_or(0)
+                    .checked_add(self.pending_proceeds(provider, *token_id)?)
+                    .ok_or(Error::ArithmeticError)?;
+                let amount = self.calculate_withdrawal_amount(*token_id, shares)?;
+                if amount == 0 {
+                    return Ok(total);
+                }
+                total.checked_add(self.token_value(*token_id, amount)?)
+                    .ok_or(Error::ArithmeticError)
+            })
+        }
+
+        // Helper functions
+        fn ensure_owner(&self) -> Result<(), Error> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotAuthorized);
+            }
+            Ok(())
+        }
+
+        fn check_provider_data(&self, data: &EncryptedData) -> Result<(), Error> {
+            if self.kyber_key_version == 0 || data.key_version != self.kyber_key_version {
+                return Err(Error::StaleVaultKey);
+            }
+            if data.kem_ciphertext.len() != KEM_CIPHERTEXT_BYTES
+                || data.ciphertext.len() < AEAD_TAG_BYTES
+                || data.ciphertext.len() > MAX_PROVIDER_DATA + AEAD_TAG_BYTES
+            {
+                return Err(Error::InvalidProviderData);
+            }
+            Ok(())
+        }
+
+        /// Accumulator including emissions since it was last stored; `rate`
+        /// is per second and timestamps are in milliseconds
+        fn current_reward_per_weight(&self, token_id: TokenId) -> Result<Balance, Error> {
+            let stored = self.reward_per_weight.get(token_id).unwrap_or(0);
+            let total_weight = self.total_weight.get(token_id).unwrap_or(0);
+            let schedule = self.reward_schedule(token_id);
+            let from = self.last_reward_time.get(token_id).unwrap_or(0).max(schedule.start);
+            let to = self.env().block_timestamp().min(schedule.end);
+            // Nothing is emitted while nobody provides the token
+            if total_weight == 0 || to <= from {
+                return Ok(stored);
+            }
+            let emitted = schedule.rate
+                .checked_mul((to - from) as Balance)
+                .and_then(|product| product.checked_mul(REWARD_PRECISION / 1_000))
+                .ok_or(Error::ArithmeticError)?;
+            stored.checked_add(emitted / total_weight).ok_or(Error::ArithmeticError)
+        }
+
+        fn update_rewards(&mut self, token_id: TokenId) -> Result<Balance, Error> {
+            let reward_per_weight = self.current_reward_per_weight(token_id)?;
+            self.reward_per_weight.insert(token_id, &reward_per_weight);
+            self.last_reward_time.insert(token_id, &self.env().block_timestamp());
+            Ok(reward_per_weight)
+        }
+
+        /// Settle what `provider` earned on `token_id` so far, then weigh the
+        /// position by `shares`, the share count it will have afterwards
+        fn settle_rewards(&mut self, provider: AccountId, token_id: TokenId, shares: Balance) -> Result<(), Error> {
+            let reward_per_weight = self.update_rewards(token_id)?;
+            let mut position = self.reward_positions.get((provider, token_id)).unwrap_or_default();
+            let accrued = position.weight
+                .checked_mul(reward_per_weight)
+                .ok_or(Error::ArithmeticError)?
+                / REWARD_PRECISION;
+            position.pending = position.pending
+                .checked_add(accrued.saturating_sub(position.reward_debt))
+                .ok_or(Error::ArithmeticError)?;
+
+            let multiplier = position.multiplier_at(self.env().block_timestamp());
+            let weight = shares
+                .checked_mul(multiplier as Balance)
+                .ok_or(Error::ArithmeticError)?
+                / BASE_MULTIPLIER as Balance;
+            let total_weight = self.total_weight.get(token_id).unwrap_or(0) - position.weight + weight;
+            position.weight = weight;
+            position.reward_debt = weight
+                .checked_mul(reward_per_weight)
+                .ok_or(Error::ArithmeticError)?
+                / REWARD_PRECISION;
+            self.total_weight.insert(token_id, &total_weight);
+            self.reward_positions.insert((provider, token_id), &position);
+            Ok(())
+        }
+
+        /// Shares of `token_id` held by providers, leaving out unclaimed proceeds
+        fn held_shares(&self, token_id: TokenId) -> Balance {
+            self.total_shares(token_id) - self.proceeds(token_id)
+        }
+
+        /// Shares of `token` minted for `amount` joining its reserve, at the
+        /// reserve before it does, and the accumulator for proceeds in `token`
+        /// per held share of `sold` once they are paid to the providers of `sold`
+        fn share_proceeds(&self, sold: TokenId, token: TokenId, amount: Balance) -> Result<(Balance, Balance), Error> {
+            let held_shares = self.held_shares(sold);
+            if held_shares == 0 {
+                return Err(Error::InsufficientLiquidity);
+            }
+            let minted = self.calculate_shares(token, amount)?;
+            let per_share = minted.checked_mul(REWARD_PRECISION)
+                .map(|scaled| scaled / held_shares)
+                .and_then(|added| self.proceeds_per_share.get((sold, token)).unwrap_or(0).checked_add(added))
+                .ok_or(Error::ArithmeticError)?;
+            Ok((per_share, minted))
+        }
+
+        /// Mint `minted` shares of `token` as proceeds for the providers of
+        /// `sold`, with `per_share` from `share_proceeds`
+        fn credit_proceeds(
+            &mut self,
+            sold: TokenId,
+            token: TokenId,
+            per_share: Balance,
+            minted: Balance,
+        ) -> Result<(), Error> {
+            let total_shares = self.total_shares(token).checked_add(minted).ok_or(Error::ArithmeticError)?;
+            let proceeds = self.proceeds(token).checked_add(minted).ok_or(Error::ArithmeticError)?;
+            self.proceeds_per_share.insert((sold, token), &per_share);
+            self.total_shares.insert(token, &total_shares);
+            self.proceeds.insert(token, &proceeds);
+            Ok(())
+        }
+
+        /// Proceeds in `token` earned by `provider`'s `sold` shares since they were last settled
+        fn unsettled_proceeds(&self, provider: AccountId, sold: TokenId, token: TokenId) -> Result<Balance, Error> {
+            let per_share = self.proceeds_per_share.get((sold, token)).unwrap_or(0);
+            let accrued = self.shares_of(provider, sold)
+                .checked_mul(per_share)
+                .ok_or(Error::ArithmeticError)?
+                / REWARD_PRECISION;
+            Ok(accrued.saturating_sub(self.proceeds_debt.get((provider, sold, token)).unwrap_or(0)))
+        }
+
+        /// Settle the proceeds `provider`'s `sold` shares earned so far, before
+        /// their count changes to `shares`
+        fn settle_proceeds(&mut self, provider: AccountId, sold: TokenId, shares: Balance) -> Result<(), Error> {
+            for token in POOL_TOKENS {
+                if token == sold {
+                    continue;
+                }
+                let settled = self.settled_proceeds.get((provider, token))
+                    .unwrap_or(0)
+                    .checked_add(self.unsettled_proceeds(provider, sold, token)?)
+                    .ok_or(Error::ArithmeticError)?;
+                let debt = shares
+                    .checked_mul(self.proceeds_per_share.get((sold, token)).unwrap_or(0))
+                    .ok_or(Error::ArithmeticError)?
+                    / REWARD_PRECISION;
+                self.settled_proceeds.insert((provider, token), &settled);
+                self.proceeds_debt.insert((provider, sold, token), &debt);
+            }
+            Ok(())
+        }
+
+        /// Record `shares` as `provider`'s share count from the next proposal on
+        fn checkpoint_shares(&mut self, provider: AccountId, token_id: TokenId, shares: Balance) {
+            let epoch = self.next_proposal_id;
+            let count = self.checkpoint_count.get((provider, token_id)).unwrap_or(0);
+            let last = count.checked_sub(1)
+                .and_then(|index| self.share_checkpoints.get((provider, token_id, index)).map(|(at, _)| (index, at)));
+            match last {
+                Some((index, at)) if at == epoch => {
+                    self.share_checkpoints.insert((provider, token_id, index), &(epoch, shares));
+                }
+                _ => {
+                    self.share_checkpoints.insert((provider, token_id, count), &(epoch, shares));
+                    self.checkpoint_count.insert((provider, token_id), &(count + 1));
+                }
+            }
+        }
+
+        /// Shares `provider` held of `token_id` when `proposal_id` was proposed
+        fn shares_at(&self, provider: AccountId, token_id: TokenId, proposal_id: ProposalId) -> Balance {
+            // First checkpoint made after the proposal, by binary search
+            let (mut low, mut high) = (0, self.checkpoint_count.get((provider, token_id)).unwrap_or(0));
+            while low < high {
+                let middle = low + (high - low) / 2;
+                let (at, _) = self.share_checkpoints.get((provider, token_id, middle)).unwrap_or_default();
+                if at <= proposal_id {
+                    low = middle + 1;
+                } else {
+                    high = middle;
+                }
+            }
+            low.checked_sub(1)
+                .and_then(|index| self.share_checkpoints.get((provider, token_id, index)))
+                .map(|(_, shares)| shares)
+                .unwrap_or(0)
+        }
+
+        /// More shares for than against, and enough for the quorum
+        fn passed(&self, proposal: &Proposal) -> bool {
+            let quorum = proposal.total_weight
+                .saturating_mul(self.quorum)
+                / FEE_DENOMINATOR;
+            proposal.votes_for > proposal.votes_against && proposal.votes_for >= quorum.max(1)
+        }
+
+        fn fresh_token_price(&self, token_id: TokenId) -> Result<Balance, Error> {
+            let oracle = self.oracle.ok_or(Error::OracleUnavailable)?;
+            let feed_id = self.token_feeds.get(token_id)
+                .ok_or(Error::OracleUnavailable)?;
+            let data = oracle.latest_price(feed_id)
+                .ok_or(Error::OracleUnavailable)?;
+            
+            if data.staleness > self.max_oracle_staleness {
+                return Err(Error::StalePrice);
+            }
+            Ok(data.price)
+        }
+
+        /// Verification still valid for `account`: cached, or from the
+        /// registrar capped at the cache TTL. `Some(Timestamp::MAX)` without a
+        /// provider.
+        fn personhood_expiry(&self, account: AccountId) -> Option<Timestamp> {
+            let provider = match self.personhood {
+                Some(provider) => provider,
+                None => return Some(Timestamp::MAX),
+            };
+            let now = self.env().block_timestamp();
+            if let Some((epoch, expiry)) = self.verified_humans.get(account) {
+                if epoch == self.personhood_epoch && expiry > now {
+                    return Some(expiry);
+                }
+            }
+            match provider {
+                PersonhoodProvider::Registrar(registry) => self.registrar_lookup(registry, account)
+                    .filter(|until| *until > now)
+                    .map(|until| until.min(now.saturating_add(self.personhood_cache_ttl))),
+                PersonhoodProvider::AllowList | PersonhoodProvider::Attestation(_) => None,
+            }
+        }
+
+        /// Check `account` and cache a fresh registrar answer
+        fn verify_personhood(&mut self, account: AccountId) -> bool {
+            let expiry = match self.personhood_expiry(account) {
+                Some(expiry) => expiry,
+                None => return false,
+            };
+            let cached = self.verified_humans.get(account)
+                .map(|(epoch, cached)| epoch == self.personhood_epoch && cached == expiry)
+                .unwrap_or(false);
+            if self.personhood.is_some() && !cached {
+                self.record_verification(account, expiry);
+            }
+            true
+        }
+
+        fn record_verification(&mut self, account: AccountId, expires_at: Timestamp) {
+            self.verified_humans.insert(account, &(self.personhood_epoch, expires_at));
+            self.env().emit_event(PersonhoodVerified {
+                account,
+                expires_at,
+            });
+        }
+
+        #[cfg(not(test))]
+        fn registrar_lookup(&self, registry: PersonhoodRef, account: AccountId) -> Option<Timestamp> {
+            registry.verified_until(account)
+        }
+
+        // Off-chain tests cannot call the registrar contract either
+        #[cfg(test)]
+        fn registrar_lookup(&self, registry: PersonhoodRef, account: AccountId) -> Option<Timestamp> {
+            tests::registry_verified_until(registry.account, account)
+        }
+
+        /// Shares for a deposit or a swap input, in proportion to the reserve
+        /// each share is backed by; the first deposit of a token mints one
+        /// share per unit. Rounds down, so nothing mints more than it is
+        /// worth; depositors bound what the rounding costs them with `min_shares`.
+        fn calculate_shares(
+            &self,
+            token_id: TokenId,
+            amount: Balance,
+        ) -> Result<Balance, Error> {
+            let total_shares = self.total_shares(token_id);
+            let reserve = self.reserve(token_id);
+            if total_shares == 0 || reserve == 0 {
+                return Ok(amount);
+            }
+            amount.checked_mul(total_shares)
+                .map(|product| product / reserve)
+                .ok_or(Error::ArithmeticError)
+        }
+
+        /// Reserve backing `shares`, rounded down so redeeming never takes more
+        /// than the shares are worth
+        fn calculate_withdrawal_amount(
+            &self,
+            token_id: TokenId,
+            shares: Balance,
+        ) -> Result<Balance, Error> {
+            let total_shares = self.total_shares(token_id);
+            if shares > total_shares {
+                return Err(Error::InsufficientShares);
+            }
+            if total_shares == 0 {
+                return Ok(0);
+            }
+            shares.checked_mul(self.reserve(token_id))
+                .map(|product| product / total_shares)
+                .ok_or(Error::ArithmeticError)
+        }
+
+        /// Output, total fee and treasury part of the fee for a swap.
+        ///
+        /// Priced on the weighted product invariant of the pair with equal
+        /// weights, `reserve_in * reserve_out`: with `net` the input after
+        /// fees, `out = reserve_out * net / (reserve_in + net)`, rounded down.
+        /// The fee is rounded up, both in favour of the pool. All of the input
+        /// but the treasury fee joins `reserve_in`, so the price moves back as
+        /// the pair is traded the other way and splitting a swap never pays
+        /// more than making it at once.
+        fn calculate_swap(
+            &self,
+            token_in: TokenId,
+            token_out: TokenId,
+            amount_in: Balance,
+        ) -> Result<(Balance, Balance, Balance), Error> {
+            if token_in == token_out {
+                return Err(Error::IdenticalTokens);
+            }
+            let reserve_in = self.reserve(token_in);
+            let reserve_out = self.reserve(token_out);
+            // A reserve left without held shares has nobody to pay the input to
+            if reserve_in == 0 || reserve_out == 0 || self.held_shares(token_out) == 0 {
+                return Err(Error::InsufficientLiquidity);
+            }
+
+            let net = amount_in.checked_mul(FEE_DENOMINATOR.saturating_sub(self.fee_rate))
+                .map(|product| product / FEE_DENOMINATOR)
+                .ok_or(Error::ArithmeticError)?;
+            let fee = amount_in - net;
+            let treasury_fee = amount_in.checked_mul(self.treasury_rate)
+                .map(|product| product / FEE_DENOMINATOR)
+                .ok_or(Error::ArithmeticError)?
+                .min(fee);
+
+            let amount_out = reserve_out.checked_mul(net)
+                .and_then(|product| {
+                    reserve_in.checked_add(net).map(|denominator| product / denominator)
+                })
+                .ok_or(Error::ArithmeticError)?;
+            if amount_out == 0 {
+                return Err(Error::InsufficientLiquidity);
+            }
+            Ok((amount_out, fee, treasury_fee))
+        }
+
+        fn update_reserves(
+            &mut self,
+            token_id: TokenId,
+            amount: Balance,
+            is_addition: bool,
+        ) -> Result<(), Error> {
+            let current = self.reserves.get(token_id)
+                .unwrap_or(0);
+            
+            let new_amount = if is_addition {
+                current.checked_add(amount)
+            } else {
+                current.checked_sub(amount)
+            }.ok_or(Error::ArithmeticError)?;
+            
+            self.reserves.insert(token_id, &new_amount);
+            Ok(())
+        }
+
+        fn token_address(&self, token_id: TokenId) -> Result<AccountId, Error> {
+            self.token_contracts.get(token_id).ok_or(Error::TokenNotConfigured)
+        }
+
+        // Token movements
+        #[cfg(not(test))]
+        fn pull(&self, token_id: TokenId, from: AccountId, amount: Balance) -> Result<(), Error> {
+            let token = self.token_address(token_id)?;
+            if amount == 0 {
+                return Ok(());
+            }
+            PSP22Ref::transfer_from(&token, from, self.env().account_id(), amount, Vec::new())
+                .map_err(|_| Error::TransferFailed)
+        }
+
+        #[cfg(not(test))]
+        fn push(&self, token_id: TokenId, to: AccountId, amount: Balance) -> Result<(), Error> {
+            let token = self.token_address(token_id)?;
+            if amount == 0 {
+                return Ok(());
+            }
+            PSP22Ref::transfer(&token, to, amount, Vec::new())
+                .map_err(|_| Error::TransferFailed)
+        }
+
+        #[cfg(test)]
+        fn pull(&self, token_id: TokenId, from: AccountId, amount: Balance) -> Result<(), Error> {
+            test_ledger::transfer(self.token_address(token_id)?, from, self.env().account_id(), amount)
+                .map_err(|_| Error::TransferFailed)
+        }
+
+        #[cfg(test)]
+        fn push(&self, token_id: TokenId, to: AccountId, amount: Balance) -> Result<(), Error> {
+            test_ledger::transfer(self.token_address(token_id)?, self.env().account_id(), to, amount)
+                .map_err(|_| Error::TransferFailed)
+        }
+    }
+
+    // Events
+    #[ink(event)]
+    pub struct LiquidityAdded {
+        #[ink(topic)]
+        provider: AccountId,
+        token_id: TokenId,
+        amount: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct LiquidityRemoved {
+        #[ink(topic)]
+        provider: AccountId,
+        token_id: TokenId,
+        amount: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Swapped {
+        #[ink(topic)]
+        trader: AccountId,
+        token_in: TokenId,
+        token_out: TokenId,
+        amount_in: Balance,
+        amount_out: Balance,
+        fee: Balance,
+        treasury_fee: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ProceedsClaimed {
+        #[ink(topic)]
+        provider: AccountId,
+        token_id: TokenId,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct LiquidityLocked {
+        #[ink(topic)]
+        provider: AccountId,
+        token_id: TokenId,
+        until: Timestamp,
+        multiplier: u32,
+    }
+
+    #[ink(event)]
+    pub struct RewardsClaimed {
+        #[ink(topic)]
+        provider: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ProviderDataStored {
+        #[ink(topic)]
+        provider: AccountId,
+        key_version: u32,
+    }
+
+    #[ink(event)]
+    pub struct PersonhoodVerified {
+        #[ink(topic)]
+        account: AccountId,
+        expires_at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct ProposalCreated {
+        #[ink(topic)]
+        proposal_id: ProposalId,
+        #[ink(topic)]
+        proposer: AccountId,
+        token_id: TokenId,
+        amount: Balance,
+        spend: Spend,
+    }
+
+    #[ink(event)]
+    pub struct Voted {
+        #[ink(topic)]
+        proposal_id: ProposalId,
+        #[ink(topic)]
+        voter: AccountId,
+        approve: bool,
+        weight: Balance,
+    }
+
+    #[ink(event)]
+    pub struct ProposalExecuted {
+        #[ink(topic)]
+        proposal_id: ProposalId,
+        token_id: TokenId,
+        amount: Balance,
+        spend: Spend,
+        burned: Balance,
+    }
+
+    // Custom errors
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        InsufficientShares,
+        ArithmeticError,
+        NotHuman,
+        NotAuthorized,
+        OracleUnavailable,
+        StalePrice,
+        TokenNotConfigured,
+        TransferFailed,
+        ReservesNotEmpty,
+        ZeroShares,
+        IdenticalTokens,
+        InsufficientLiquidity,
+        SlippageExceeded,
+        SharesLocked,
+        NoVotingPower,
+        InsufficientTreasury,
+        ProposalNotFound,
+        ProposalNotActive,
+        VotingClosed,
+        AlreadyVoted,
+        TimelockActive,
+        ProposalRejected,
+        InvalidParameter,
+        InvalidAttestation,
+        StaleVaultKey,
+        InvalidProviderData,
+        NoProviderData,
+        RewardsNotConfigured,
+        InsufficientRewardFunds,
+        // Add more error types as needed
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_lang as ink;
+        use oracle_consumer::test_ledger::{balance, mint};
+        use std::cell::RefCell;
+        use std::collections::BTreeMap;
+
+        thread_local! {
+            // Expiry of each (registrar, account) verification, and the lookups made
+            static REGISTRY: RefCell<BTreeMap<(AccountId, AccountId), Timestamp>> = RefCell::new(BTreeMap::new());
+            static LOOKUPS: RefCell<u32> = RefCell::new(0);
+        }
+
+        pub(super) fn registry_verified_until(registry: AccountId, account: AccountId) -> Option<Timestamp> {
+            LOOKUPS.with(|lookups| *lookups.borrow_mut() += 1);
+            REGISTRY.with(|entries| entries.borrow().get(&(registry, account)).copied())
+        }
+
+        fn register(registry: AccountId, account: AccountId, until: Option<Timestamp>) {
+            REGISTRY.with(|entries| match until {
+                Some(until) => entries.borrow_mut().insert((registry, account), until),
+                None => entries.borrow_mut().remove(&(registry, account)),
+            });
+        }
+
+        fn lookups() -> u32 {
+            LOOKUPS.with(|lookups| *lookups.borrow())
+        }
+
+        const POOL: [u8; 32] = [0xee; 32];
+
+        fn token_address(token_id: TokenId) -> AccountId {
+            AccountId::from([0x10 * (token_id as u8 + 1); 32])
+        }
+
+        fn accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
+            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+        }
+
+        fn set_caller(account: AccountId) {
+            ink_env::test::set_caller::<ink_env::DefaultEnvironment>(account);
+        }
+
+        /// Pool with all three tokens configured and bob and charlie funded
+        fn setup() -> UnifiedLiquidityPool {
+            ink_env::test::set_callee::<ink_env::DefaultEnvironment>(AccountId::from(POOL));
+            set_caller(accounts().alice);
+            let mut pool = UnifiedLiquidityPool::new(3_690, 1_000);
+            for token_id in POOL_TOKENS {
+                pool.set_token_contract(token_id, token_address(token_id)).unwrap();
+                for account in [accounts().bob, accounts().charlie] {
+                    mint(token_address(token_id), account, 1_000_000);
+                }
+            }
+            pool
+        }
+
+        #[ink::test]
+        fn reserves_match_token_holdings() {
+            let mut pool = setup();
+            let acc = accounts();
+            let nrsh = token_address(TokenId::NRSH);
+
+            set_caller(acc.bob);
+            pool.add_liquidity(TokenId::NRSH, 400_000, 0).unwrap();
+            pool.add_liquidity(TokenId::NRSH, 100_000, 0).unwrap();
+            set_caller(acc.charlie);
+            pool.add_liquidity(TokenId::NRSH, 250_000, 0).unwrap();
+
+            assert_eq!(pool.reserve(TokenId::NRSH), 750_000);
+            assert_eq!(balance(nrsh, AccountId::from(POOL)), 750_000);
+            assert_eq!(balance(nrsh, acc.bob), 500_000);
+            assert_eq!(balance(nrsh, acc.charlie), 750_000);
+
+            // Other tokens are untouched
+            assert_eq!(pool.reserve(TokenId::ELXR), 0);
+            assert_eq!(balance(token_address(TokenId::ELXR), AccountId::from(POOL)), 0);
+        }
+
+        #[ink::test]
+        fn deposits_need_a_configured_token_and_funds() {
+            let mut pool = setup();
+            let acc = accounts();
+
+            set_caller(acc.bob);
+            assert_eq!(pool.add_liquidity(TokenId::ELXR, 2_000_000, 0), Err(Error::TransferFailed));
+            assert_eq!(pool.reserve(TokenId::ELXR), 0);
+            assert_eq!(pool.shares_of(acc.bob, TokenId::ELXR), 0);
+
+            pool.add_liquidity(TokenId::ELXR, 10, 0).unwrap();
+            set_caller(acc.alice);
+            let elsewhere = AccountId::from([0x99; 32]);
+            assert_eq!(pool.set_token_contract(TokenId::ELXR, elsewhere), Err(Error::ReservesNotEmpty));
+
+            let mut fresh = UnifiedLiquidityPool::new(3_690, 1_000);
+            set_caller(acc.bob);
+            assert_eq!(fresh.add_liquidity(TokenId::IMRT, 1, 0), Err(Error::TokenNotConfigured));
+        }
+
+        // Raises the share price: reserve and holdings grow without new shares
+        fn accrue(pool: &mut UnifiedLiquidityPool, token_id: TokenId, amount: Balance) {
+            mint(token_address(token_id), AccountId::from(POOL), amount);
+            pool.update_reserves(token_id, amount, true).unwrap();
+        }
+
+        #[ink::test]
+        fn shares_are_proportional_to_the_reserve() {
+            let mut pool = setup();
+            let acc = accounts();
+
+            set_caller(acc.bob);
+            assert_eq!(pool.add_liquidity(TokenId::ELXR, 1_000, 0), Ok(1_000));
+            // A second deposit adds to the first
+            assert_eq!(pool.add_liquidity(TokenId::ELXR, 500, 0), Ok(500));
+            assert_eq!(pool.shares_of(acc.bob, TokenId::ELXR), 1_500);
+
+            // Fees double the reserve, each share is now backed by 2 ELXR
+            accrue(&mut pool, TokenId::ELXR, 1_500);
+            set_caller(acc.charlie);
+            assert_eq!(pool.quote_shares(TokenId::ELXR, 999), Ok(499));
+            assert_eq!(pool.add_liquidity(TokenId::ELXR, 1_000, 501), Err(Error::SlippageExceeded));
+            assert_eq!(pool.add_liquidity(TokenId::ELXR, 1_000, 500), Ok(500));
+            assert_eq!(pool.total_shares(TokenId::ELXR), 2_000);
+
+            // Bob takes the fees earned before charlie joined
+            set_caller(acc.bob);
+            assert_eq!(pool.remove_liquidity(TokenId::ELXR, 1_500), Ok(3_000));
+            set_caller(acc.charlie);
+            assert_eq!(pool.remove_liquidity(TokenId::ELXR, 500), Ok(1_000));
+            assert_eq!(pool.reserve(TokenId::ELXR), 0);
+            assert_eq!(pool.total_shares(TokenId::ELXR), 0);
+
+            // Too small to be worth a share
+            set_caller(acc.bob);
+            pool.add_liquidity(TokenId::IMRT, 10, 0).unwrap();
+            accrue(&mut pool, TokenId::IMRT, 100);
+            assert_eq!(pool.add_liquidity(TokenId::IMRT, 10, 0), Err(Error::ZeroShares));
+            assert_eq!(pool.remove_liquidity(TokenId::IMRT, 11), Err(Error::InsufficientShares));
+        }
+
+        #[ink::test]
+        fn deposits_and_withdrawals_never_create_value() {
+            let mut pool = setup();
+            let acc = accounts();
+            let providers = [acc.bob, acc.charlie, acc.django];
+            for token_id in POOL_TOKENS {
+                mint(token_address(token_id), acc.django, 1_000_000);
+            }
+
+            // xorshift64, fixed seed so failures reproduce
+            let mut state = 0x9e37_79b9_7f4a_7c15u64;
+            let mut next = move |bound: u64| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state % bound
+            };
+
+            // Per (provider, token): deposited and withdrawn; per token: fees accrued
+            let mut deposited = BTreeMap::new();
+            let mut withdrawn = BTreeMap::new();
+            let mut fees = BTreeMap::new();
+            for _ in 0..500 {
+                let provider = providers[next(3) as usize];
+                let token_id = POOL_TOKENS[next(3) as usize];
+                set_caller(provider);
+                match next(5) {
+                    0 | 1 => {
+                        let amount = 1 + next(10_000) as Balance;
+                        if pool.add_liquidity(token_id, amount, 0).is_ok() {
+                            *deposited.entry((provider, token_id as u8)).or_insert(0) += amount;
+                        }
+                    }
+                    2 | 3 => {
+                        let held = pool.shares_of(provider, token_id);
+                        if held > 0 {
+                            let shares = 1 + next(held as u64) as Balance;
+                            let amount = pool.remove_liquidity(token_id, shares).unwrap();
+                            *withdrawn.entry((provider, token_id as u8)).or_insert(0) += amount;
+                        }
+                    }
+                    _ => {
+                        // Fees only accrue to a pool with providers in it
+                        if pool.total_shares(token_id) > 0 {
+                            let amount = next(1_000) as Balance;
+                            accrue(&mut pool, token_id, amount);
+                            *fees.entry(token_id as u8).or_insert(0) += amount;
+                        }
+                    }
+                }
+
+                for token_id in POOL_TOKENS {
+                    let held: Balance = providers.iter().map(|p| pool.shares_of(*p, token_id)).sum();
+                    assert_eq!(held, pool.total_shares(token_id));
+                    assert_eq!(pool.reserve(token_id), balance(token_address(token_id), AccountId::from(POOL)));
+                }
+            }
+
+            // Everyone leaves; the pool keeps only rounding dust
+            for token_id in POOL_TOKENS {
+                for provider in providers {
+                    set_caller(provider);
+                    let held = pool.shares_of(provider, token_id);
+                    if held > 0 {
+                        let amount = pool.remove_liquidity(token_id, held).unwrap();
+                        *withdrawn.entry((provider, token_id as u8)).or_insert(0) += amount;
+                    }
+                }
+                let key = token_id as u8;
+                let total_in: Balance = providers.iter().map(|p| deposited.get(&(*p, key)).copied().unwrap_or(0)).sum();
+                let total_out: Balance = providers.iter().map(|p| withdrawn.get(&(*p, key)).copied().unwrap_or(0)).sum();
+                assert_eq!(pool.total_shares(token_id), 0);
+                assert_eq!(total_in + fees.get(&key).copied().unwrap_or(0), total_out + pool.reserve(token_id));
+            }
+
+            // Without fees nobody could have taken out more than they put in
+            let mut pool = setup();
+            for _ in 0..300 {
+                let provider = providers[next(3) as usize];
+                set_caller(provider);
+                let before = balance(token_address(TokenId::NRSH), provider);
+                let amount = 1 + next(10_000) as Balance;
+                let shares = pool.add_liquidity(TokenId::NRSH, amount, 0).unwrap();
+                let out = pool.remove_liquidity(TokenId::NRSH, shares).unwrap();
+                assert!(out <= amount);
+                assert!(balance(token_address(TokenId::NRSH), provider) <= before);
+                if next(2) == 0 {
+                    // Leave something behind so the share price is not always 1
+                    pool.add_liquidity(TokenId::NRSH, 1 + next(100) as Balance, 0).unwrap();
+                }
+            }
+        }
+
+        #[ink::test]
+        fn swaps_pay_the_sellers_and_split_the_fee() {
+            let mut pool = setup();
+            let acc = accounts();
+            let (nrsh, elxr) = (token_address(TokenId::NRSH), token_address(TokenId::ELXR));
+
+            set_caller(acc.bob);
+            pool.add_liquidity(TokenId::NRSH, 500_000, 0).unwrap();
+            pool.add_liquidity(TokenId::ELXR, 500_000, 0).unwrap();
+
+            // 0.369% fee on 10_000 is 37 after rounding up, 10 of it for the treasury;
+            // 500_000 * 9_963 / 509_963 ELXR out
+            set_caller(acc.charlie);
+            assert_eq!(pool.quote_swap(TokenId::NRSH, TokenId::ELXR, 10_000), Ok(9_768));
+            assert_eq!(pool.swap(TokenId::NRSH, TokenId::ELXR, 10_000, 9_768), Ok(9_768));
+
+            // All but the treasury fee joins the NRSH reserve, for 9_990 new shares
+            assert_eq!(pool.reserve(TokenId::NRSH), 509_990);
+            assert_eq!(pool.treasury_balance(TokenId::NRSH), 10);
+            assert_eq!(pool.total_shares(TokenId::NRSH), 509_990);
+            assert_eq!(pool.proceeds(TokenId::NRSH), 9_990);
+            assert_eq!(pool.reserve(TokenId::ELXR), 490_232);
+            assert_eq!(pool.treasury_balance(TokenId::ELXR), 0);
+            assert_eq!(balance(nrsh, AccountId::from(POOL)), 510_000);
+            assert_eq!(balance(elxr, AccountId::from(POOL)), 490_232);
+            assert_eq!(balance(nrsh, acc.charlie), 990_000);
+            assert_eq!(balance(elxr, acc.charlie), 1_009_768);
+
+            // Those shares, the input and the providers' part of the fee, pay for the ELXR sold
+            assert_eq!(pool.pending_proceeds(acc.bob, TokenId::NRSH), Ok(9_990));
+            assert_eq!(pool.pending_proceeds(acc.bob, TokenId::ELXR), Ok(0));
+            set_caller(acc.bob);
+            assert_eq!(pool.claim_proceeds(TokenId::NRSH), Ok(9_990));
+            assert_eq!(pool.claim_proceeds(TokenId::NRSH), Ok(0));
+            assert_eq!(pool.proceeds(TokenId::NRSH), 0);
+            assert_eq!(pool.shares_of(acc.bob, TokenId::NRSH), 509_990);
+            assert_eq!(pool.remove_liquidity(TokenId::NRSH, 509_990), Ok(509_990));
+            assert_eq!(balance(nrsh, AccountId::from(POOL)), 10);
+        }
+
+        #[ink::test]
+        fn providers_cannot_swap_value_out_of_another_token() {
+            let mut pool = setup();
+            let acc = accounts();
+            let (nrsh, elxr) = (token_address(TokenId::NRSH), token_address(TokenId::ELXR));
+            mint(nrsh, acc.eve, 1_000_000);
+
+            set_caller(acc.bob);
+            pool.add_liquidity(TokenId::ELXR, 500_000, 0).unwrap();
+
+            // Eve provides NRSH and buys bob's ELXR with it
+            set_caller(acc.eve);
+            pool.add_liquidity(TokenId::NRSH, 500_000, 0).unwrap();
+            assert_eq!(pool.swap(TokenId::NRSH, TokenId::ELXR, 100_000, 0), Ok(83_076));
+            // Her NRSH shares are worth what she deposited, not her input as well:
+            // that is backed by new shares, paid to bob
+            assert_eq!(pool.quote_withdrawal(TokenId::NRSH, 500_000), Ok(500_000));
+            assert_eq!(pool.pending_proceeds(acc.eve, TokenId::NRSH), Ok(0));
+            assert_eq!(pool.remove_liquidity(TokenId::NRSH, 500_000), Ok(500_000));
+            assert_eq!(balance(nrsh, acc.eve), 900_000);
+            assert_eq!(balance(elxr, acc.eve), 83_076);
+
+            // Bob sold 83_076 ELXR for the 99_900 NRSH left after the treasury fee
+            set_caller(acc.bob);
+            assert_eq!(pool.remove_liquidity(TokenId::ELXR, 500_000), Ok(416_924));
+            assert_eq!(pool.claim_proceeds(TokenId::NRSH), Ok(99_900));
+            assert_eq!(pool.remove_liquidity(TokenId::NRSH, 99_900), Ok(99_900));
+            assert_eq!(balance(nrsh, acc.bob), 1_099_900);
+            assert_eq!(balance(elxr, acc.bob), 916_924);
+            assert_eq!(balance(nrsh, AccountId::from(POOL)), pool.treasury_balance(TokenId::NRSH));
+            assert_eq!(balance(elxr, AccountId::from(POOL)), 0);
+        }
+
+        #[ink::test]
+        fn swaps_are_rejected_without_changing_anything() {
+            let mut pool = setup();
+            let acc = accounts();
+
+            set_caller(acc.bob);
+            pool.add_liquidity(TokenId::NRSH, 500_000, 0).unwrap();
+            pool.add_liquidity(TokenId::ELXR, 500_000, 0).unwrap();
+
+            set_caller(acc.charlie);
+            let quoted = pool.quote_swap(TokenId::ELXR, TokenId::NRSH, 50_000).unwrap();
+            assert_eq!(pool.swap(TokenId::ELXR, TokenId::NRSH, 50_000, quoted + 1), Err(Error::SlippageExceeded));
+            assert_eq!(pool.swap(TokenId::ELXR, TokenId::ELXR, 50_000, 0), Err(Error::IdenticalTokens));
+            assert_eq!(pool.swap(TokenId::ELXR, TokenId::IMRT, 50_000, 0), Err(Error::InsufficientLiquidity));
+            // Too small to buy anything after the fee
+            assert_eq!(pool.swap(TokenId::ELXR, TokenId::NRSH, 1, 0), Err(Error::InsufficientLiquidity));
+            assert_eq!(pool.swap(TokenId::ELXR, TokenId::NRSH, 2_000_000, 0), Err(Error::TransferFailed));
+
+            assert_eq!(pool.reserve(TokenId::NRSH), 500_000);
+            assert_eq!(pool.reserve(TokenId::ELXR), 500_000);
+            assert_eq!(pool.treasury_balance(TokenId::ELXR), 0);
+            assert_eq!(pool.proceeds(TokenId::ELXR), 0);
+            assert_eq!(balance(token_address(TokenId::ELXR), acc.charlie), 1_000_000);
+        }
+
+        #[ink::test]
+        fn swaps_never_beat_the_pool_price() {
+            let mut pool = setup();
+            let acc = accounts();
+            for token_id in POOL_TOKENS {
+                mint(token_address(token_id), acc.django, 100_000);
+                set_caller(acc.bob);
+                pool.add_liquidity(token_id, 200_000 + 100_000 * token_id as Balance, 0).unwrap();
+                set_caller(acc.django);
+                pool.add_liquidity(token_id, 100_000, 0).unwrap();
+            }
+
+            let mut state = 0x2545_f491_4f6c_dd1du64;
+            let mut next = move |bound: u64| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state % bound
+            };
+
+            set_caller(acc.charlie);
+            for _ in 0..300 {
+                let token_in = POOL_TOKENS[next(3) as usize];
+                let token_out = POOL_TOKENS[(token_in as usize + 1 + next(2) as usize) % 3];
+                let amount_in = 1 + next(2_000) as Balance;
+                let (reserve_in, reserve_out) = (pool.reserve(token_in), pool.reserve(token_out));
+                let treasury = pool.treasury_balance(token_in);
+
+                // Never more than the marginal price gives; the input less the
+                // treasury fee joins the reserve it was priced against
+                if let Ok(amount_out) = pool.swap(token_in, token_out, amount_in, 0) {
+                    assert!(amount_out * reserve_in <= amount_in * reserve_out);
+                    let treasury_fee = pool.treasury_balance(token_in) - treasury;
+                    assert_eq!(pool.reserve(token_in), reserve_in + amount_in - treasury_fee);
+                    assert_eq!(pool.reserve(token_out), reserve_out - amount_out);
+                }
+                for token_id in POOL_TOKENS {
+                    assert_eq!(
+                        pool.reserve(token_id) + pool.treasury_balance(token_id),
+                        balance(token_address(token_id), AccountId::from(POOL))
+                    );
+                    let held = pool.shares_of(acc.bob, token_id) + pool.shares_of(acc.django, token_id);
+                    assert_eq!(held + pool.proceeds(token_id), pool.total_shares(token_id));
+                }
+            }
+
+            // Once the providers claim and leave, only the treasury and the
+            // reserve behind unclaimable rounding dust remain
+            for provider in [acc.bob, acc.django] {
+                set_caller(provider);
+                for token_id in POOL_TOKENS {
+                    pool.claim_proceeds(token_id).unwrap();
+                    pool.remove_liquidity(token_id, pool.shares_of(provider, token_id)).unwrap();
+                }
+            }
+            for token_id in POOL_TOKENS {
+                // Below one share per provider and token sold
+                assert!(pool.proceeds(token_id) <= 4);
+                assert_eq!(pool.total_shares(token_id), pool.proceeds(token_id));
+                assert_eq!(pool.quote_withdrawal(token_id, pool.proceeds(token_id)), Ok(pool.reserve(token_id)));
+                assert_eq!(
+                    balance(token_address(token_id), AccountId::from(POOL)),
+                    pool.reserve(token_id) + pool.treasury_balance(token_id)
+                );
+            }
+        }
+
+        #[ink::test]
+        fn split_swaps_never_beat_one_swap() {
+            let mut pool = setup();
+            let acc = accounts();
+
+            set_caller(acc.bob);
+            pool.add_liquidity(TokenId::NRSH, 1_000, 0).unwrap();
+            pool.add_liquidity(TokenId::ELXR, 1_000, 0).unwrap();
+
+            // Two halves buy no more than the whole, and trading back restores the price
+            set_caller(acc.charlie);
+            assert_eq!(pool.quote_swap(TokenId::NRSH, TokenId::ELXR, 1_000), Ok(498));
+            assert_eq!(pool.swap(TokenId::NRSH, TokenId::ELXR, 500, 0), Ok(332));
+            assert_eq!(pool.swap(TokenId::NRSH, TokenId::ELXR, 500, 0), Ok(166));
+            assert_eq!(pool.swap(TokenId::ELXR, TokenId::NRSH, 498, 0), Ok(993));
+            assert_eq!(pool.reserve(TokenId::NRSH), 1_007);
+            assert_eq!(pool.reserve(TokenId::ELXR), 1_000);
+
+            let mut state = 0x853c_49e6_748f_ea9bu64;
+            let mut next = move |bound: u64| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state % bound
+            };
+
+            set_caller(acc.bob);
+            pool.add_liquidity(TokenId::NRSH, 500_000, 0).unwrap();
+            pool.add_liquidity(TokenId::ELXR, 500_000, 0).unwrap();
+            set_caller(acc.charlie);
+            for trial in 0..50 {
+                let (token_in, token_out) = if trial % 2 == 0 {
+                    (TokenId::NRSH, TokenId::ELXR)
+                } else {
+                    (TokenId::ELXR, TokenId::NRSH)
+                };
+                let total = 1_000 + next(100_000) as Balance;
+                let single = pool.quote_swap(token_in, token_out, total).unwrap();
+
+                let parts = 2 + next(5);
+                let (mut left, mut received) = (total, 0);
+                for part in 0..parts {
+                    let amount = if part + 1 == parts { left } else { next(left as u64 + 1) as Balance };
+                    left -= amount;
+                    if let Ok(amount_out) = pool.swap(token_in, token_out, amount, 0) {
+                        received += amount_out;
+                    }
+                }
+                assert!(received <= single);
+            }
+        }
+
+        fn set_timestamp(timestamp: Timestamp) {
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(timestamp);
+        }
+
+        #[ink::test]
+        fn treasury_grants_pay_out_after_vote_and_timelock() {
+            let mut pool = setup();
+            let acc = accounts();
+            let nrsh = token_address(TokenId::NRSH);
+
+            set_caller(acc.bob);
+            pool.add_liquidity(TokenId::NRSH, 500_000, 0).unwrap();
+            pool.add_liquidity(TokenId::ELXR, 500_000, 0).unwrap();
+            set_caller(acc.charlie);
+            pool.add_liquidity(TokenId::NRSH, 100_000, 0).unwrap();
+            // 0.1% of the input to the treasury
+            pool.swap(TokenId::NRSH, TokenId::ELXR, 200_000, 0).unwrap();
+            assert_eq!(pool.treasury_balance(TokenId::NRSH), 200);
+
+            let grant = Spend::ResearchGrant(acc.django);
+            let description = Hash::from([1; 32]);
+            set_caller(acc.eve);
+            assert_eq!(pool.propose(TokenId::NRSH, 150, grant, description), Err(Error::NoVotingPower));
+            set_caller(acc.bob);
+            assert_eq!(pool.propose(TokenId::NRSH, 201, grant, description), Err(Error::InsufficientTreasury));
+            let id = pool.propose(TokenId::NRSH, 150, grant, description).unwrap();
+
+            assert_eq!(pool.vote(id, true), Ok(500_000));
+            set_caller(acc.charlie);
+            assert_eq!(pool.vote(id, false), Ok(100_000));
+            assert_eq!(pool.vote(id, true), Err(Error::AlreadyVoted));
+            assert_eq!(pool.vote_of(id, acc.charlie), Some((false, 100_000)));
+            assert!(pool.proposal_passed(id));
+
+            // Voting locks the shares, the timelock lets dissenters leave
+            assert_eq!(pool.remove_liquidity(TokenId::NRSH, 100_000), Err(Error::SharesLocked));
+            assert_eq!(pool.execute_proposal(id), Err(Error::TimelockActive));
+            set_timestamp(DEFAULT_VOTING_PERIOD);
+            set_caller(acc.eve);
+            assert_eq!(pool.vote(id, false), Err(Error::VotingClosed));
+            assert_eq!(pool.execute_proposal(id), Err(Error::TimelockActive));
+            set_caller(acc.charlie);
+            pool.remove_liquidity(TokenId::NRSH, 100_000).unwrap();
+
+            set_timestamp(DEFAULT_VOTING_PERIOD + DEFAULT_TIMELOCK);
+            set_caller(acc.eve);
+            assert_eq!(pool.execute_proposal(id), Ok(0));
+            assert_eq!(pool.execute_proposal(id), Err(Error::ProposalNotActive));
+            assert_eq!(pool.get_proposal(id).unwrap().status, ProposalStatus::Executed);
+            assert_eq!(balance(nrsh, acc.django), 150);
+            assert_eq!(pool.treasury_balance(TokenId::NRSH), 50);
+            assert_eq!(
+                balance(nrsh, AccountId::from(POOL)),
+                pool.reserve(TokenId::NRSH) + pool.treasury_balance(TokenId::NRSH)
+            );
+        }
+
+        #[ink::test]
+        fn votes_count_the_shares_held_when_proposed() {
+            let mut pool = setup();
+            let acc = accounts();
+            mint(token_address(TokenId::NRSH), acc.eve, 1_000_000);
+
+            set_caller(acc.bob);
+            pool.add_liquidity(TokenId::NRSH, 500_000, 0).unwrap();
+            pool.add_liquidity(TokenId::ELXR, 500_000, 0).unwrap();
+            set_caller(acc.charlie);
+            pool.add_liquidity(TokenId::NRSH, 100_000, 0).unwrap();
+            pool.swap(TokenId::NRSH, TokenId::ELXR, 200_000, 0).unwrap();
+
+            set_caller(acc.bob);
+            let grant = Spend::ResearchGrant(acc.django);
+            let id = pool.propose(TokenId::NRSH, 100, grant, Hash::from([3; 32])).unwrap();
+
+            // Shares bought or borrowed for the vote carry no weight
+            set_caller(acc.eve);
+            pool.add_liquidity(TokenId::NRSH, 900_000, 0).unwrap();
+            assert_eq!(pool.vote(id, false), Err(Error::NoVotingPower));
+            set_caller(acc.charlie);
+            pool.add_liquidity(TokenId::NRSH, 400_000, 0).unwrap();
+            assert_eq!(pool.vote(id, false), Ok(100_000));
+            set_caller(acc.bob);
+            assert_eq!(pool.vote(id, true), Ok(500_000));
+            assert!(pool.proposal_passed(id));
+
+            // Withdrawn shares do not vote either
+            let later = pool.propose(TokenId::NRSH, 50, grant, Hash::from([4; 32])).unwrap();
+            set_caller(acc.eve);
+            pool.remove_liquidity(TokenId::NRSH, 400_000).unwrap();
+            assert_eq!(pool.vote(later, false), Ok(500_000));
+            set_caller(acc.charlie);
+            assert_eq!(pool.vote(later, false), Ok(500_000));
+            assert!(!pool.proposal_passed(later));
+        }
+
+        #[ink::test]
+        fn only_passed_proposals_spend_and_buybacks_retire_tokens() {
+            let mut pool = setup();
+            let acc = accounts();
+
+            set_caller(acc.bob);
+            pool.add_liquidity(TokenId::NRSH, 500_000, 0).unwrap();
+            pool.add_liquidity(TokenId::ELXR, 50_000, 0).unwrap();
+            set_caller(acc.charlie);
+            pool.add_liquidity(TokenId::ELXR, 500_000, 0).unwrap();
+            pool.swap(TokenId::ELXR, TokenId::NRSH, 100_000, 0).unwrap();
+            assert_eq!(pool.treasury_balance(TokenId::ELXR), 100);
+
+            // Bob alone holds less than the 10% quorum of ELXR shares
+            set_caller(acc.bob);
+            let description = Hash::from([2; 32]);
+            let short = pool.propose(TokenId::ELXR, 60, Spend::Facility(acc.django), description).unwrap();
+            pool.vote(short, true).unwrap();
+            assert!(!pool.proposal_passed(short));
+            let outvoted = pool.propose(TokenId::ELXR, 10, Spend::ResearchGrant(acc.django), description).unwrap();
+            pool.vote(outvoted, true).unwrap();
+
+            set_caller(acc.charlie);
+            let buyback = Spend::BuybackAndBurn { token: TokenId::NRSH, min_amount_out: 1 };
+            let burn = pool.propose(TokenId::ELXR, 40, buyback, description).unwrap();
+            pool.vote(burn, true).unwrap();
+            pool.vote(outvoted, false).unwrap();
+            assert_eq!(pool.cancel_proposal(short), Err(Error::NotAuthorized));
+            set_caller(acc.bob);
+            pool.cancel_proposal(short).unwrap();
+            assert_eq!(pool.set_governance_params(1, 0, 0), Err(Error::NotAuthorized));
+            set_caller(acc.alice);
+            assert_eq!(pool.set_governance_params(1, 0, 2_000_000), Err(Error::InvalidParameter));
+
+            set_timestamp(DEFAULT_VOTING_PERIOD + DEFAULT_TIMELOCK);
+            assert_eq!(pool.execute_proposal(short), Err(Error::ProposalNotActive));
+            assert_eq!(pool.execute_proposal(outvoted), Err(Error::ProposalRejected));
+            assert_eq!(pool.execute_proposal(99), Err(Error::ProposalNotFound));
+
+            // The treasury buys NRSH at the pool price, fee included, and keeps it out of circulation.
+            // What it pays joins the ELXR reserve; the shares minted for it go to the NRSH providers.
+            let expected = pool.quote_swap(TokenId::ELXR, TokenId::NRSH, 40).unwrap();
+            let reserve = pool.reserve(TokenId::ELXR);
+            let earned = pool.pending_proceeds(acc.bob, TokenId::ELXR).unwrap();
+            assert_eq!(pool.execute_proposal(burn), Ok(expected));
+            assert_eq!(pool.burned(TokenId::NRSH), expected);
+            assert_eq!(pool.treasury_balance(TokenId::ELXR), 60);
+            assert_eq!(pool.reserve(TokenId::ELXR), reserve + 40);
+            assert_eq!(pool.pending_proceeds(acc.bob, TokenId::ELXR), Ok(earned + 40));
+            for token_id in [TokenId::NRSH, TokenId::ELXR] {
+                assert_eq!(
+                    balance(token_address(token_id), AccountId::from(POOL)),
+                    pool.reserve(token_id) + pool.treasury_balance(token_id) + pool.burned(token_id)
+                );
+            }
+        }
+
+        #[ink::test]
+        fn registrar_verifications_are_cached_until_they_expire() {
+            let mut pool = setup();
+            let acc = accounts();
+            let registry = AccountId::from([0x77; 32]);
+            pool.set_personhood_provider(Some(PersonhoodProvider::Registrar(PersonhoodRef::new(registry))))
+                .unwrap();
+            pool.set_personhood_cache_ttl(1_000).unwrap();
+
+            set_caller(acc.bob);
+            assert_eq!(pool.add_liquidity(TokenId::NRSH, 100, 0), Err(Error::NotHuman));
+            assert_eq!(pool.reserve(TokenId::NRSH), 0);
+
+            register(registry, acc.bob, Some(10_000));
+            register(registry, acc.charlie, Some(500));
+            assert!(pool.is_verified_human(acc.bob));
+            let before = lookups();
+            pool.add_liquidity(TokenId::NRSH, 100, 0).unwrap();
+            pool.add_liquidity(TokenId::NRSH, 100, 0).unwrap();
+            assert_eq!(lookups(), before + 1);
+
+            // Revoked at the registrar, bob stays verified until the cache TTL runs out
+            register(registry, acc.bob, None);
+            set_timestamp(999);
+            pool.add_liquidity(TokenId::NRSH, 100, 0).unwrap();
+            set_timestamp(1_000);
+            assert_eq!(pool.add_liquidity(TokenId::NRSH, 100, 0), Err(Error::NotHuman));
+
+            // Cached no longer than the registrar vouches
+            set_timestamp(0);
+            set_caller(acc.charlie);
+            pool.add_liquidity(TokenId::ELXR, 100, 0).unwrap();
+            set_timestamp(500);
+            assert_eq!(pool.add_liquidity(TokenId::ELXR, 100, 0), Err(Error::NotHuman));
+            assert!(!pool.is_verified_human(acc.charlie));
+
+            // Without a provider everyone may provide liquidity
+            set_caller(acc.alice);
+            pool.set_personhood_provider(None).unwrap();
+            set_caller(acc.charlie);
+            pool.add_liquidity(TokenId::ELXR, 100, 0).unwrap();
+        }
+
+        #[ink::test]
+        fn allow_lists_and_attestations_gate_deposits() {
+            let mut pool = setup();
+            let acc = accounts();
+
+            pool.set_personhood_provider(Some(PersonhoodProvider::AllowList)).unwrap();
+            pool.set_verified_human(acc.bob, 1_000).unwrap();
+            set_caller(acc.bob);
+            assert_eq!(pool.set_verified_human(acc.charlie, 1_000), Err(Error::NotAuthorized));
+            pool.add_liquidity(TokenId::NRSH, 100, 0).unwrap();
+            set_caller(acc.charlie);
+            assert_eq!(pool.add_liquidity(TokenId::NRSH, 100, 0), Err(Error::NotHuman));
+            set_timestamp(1_000);
+            set_caller(acc.bob);
+            assert_eq!(pool.add_liquidity(TokenId::NRSH, 100, 0), Err(Error::NotHuman));
+
+            set_caller(acc.alice);
+            pool.set_verified_human(acc.bob, 5_000).unwrap();
+            pool.revoke_verification(acc.bob).unwrap();
+            assert!(!pool.is_verified_human(acc.bob));
+            pool.set_verified_human(acc.bob, 5_000).unwrap();
+
+            // A new provider starts from scratch
+            pool.set_personhood_provider(Some(PersonhoodProvider::Attestation([2; 33]))).unwrap();
+            assert!(!pool.is_verified_human(acc.bob));
+            assert_eq!(pool.set_verified_human(acc.bob, 5_000), Err(Error::InvalidParameter));
+            set_caller(acc.bob);
+            assert_eq!(pool.submit_attestation(5_000, [1; 65]), Err(Error::InvalidAttestation));
+            assert_eq!(pool.submit_attestation(1_000, [1; 65]), Err(Error::InvalidAttestation));
+            assert_eq!(pool.add_liquidity(TokenId::NRSH, 100, 0), Err(Error::NotHuman));
+        }
+
+        fn sealed(key_version: u32, plaintext_len: usize) -> EncryptedData {
+            EncryptedData {
+                key_version,
+                kem_ciphertext: vec![7; KEM_CIPHERTEXT_BYTES],
+                ciphertext: vec![9; plaintext_len + AEAD_TAG_BYTES],
+                nonce: [3; 24],
+            }
+        }
+
+        #[ink::test]
+        fn provider_data_follows_the_vault_key() {
+            let mut pool = setup();
+            let acc = accounts();
+
+            // Nobody holds the secret of the key generated at deployment
+            set_caller(acc.bob);
+            assert_eq!(pool.vault_key().0, 0);
+            assert_eq!(pool.store_provider_data(sealed(0, 100)), Err(Error::StaleVaultKey));
+            assert_eq!(pool.set_vault_key([1; KYBER_PUBLICKEYBYTES]), Err(Error::NotAuthorized));
+            set_caller(acc.alice);
+            assert_eq!(pool.set_vault_key([1; KYBER_PUBLICKEYBYTES]), Ok(1));
+            assert_eq!(pool.vault_key(), (1, [1; KYBER_PUBLICKEYBYTES]));
+
+            set_caller(acc.bob);
+            assert_eq!(pool.rotate_provider_data(sealed(1, 100)), Err(Error::NoProviderData));
+            pool.store_provider_data(sealed(1, 100)).unwrap();
+            assert_eq!(pool.provider_data(acc.bob), Some(sealed(1, 100)));
+
+            let mut truncated = sealed(1, 100);
+            truncated.kem_ciphertext.pop();
+            assert_eq!(pool.store_provider_data(truncated), Err(Error::InvalidProviderData));
+            assert_eq!(pool.store_provider_data(sealed(1, MAX_PROVIDER_DATA + 1)), Err(Error::InvalidProviderData));
+            pool.store_provider_data(sealed(1, MAX_PROVIDER_DATA)).unwrap();
+
+            // After a key rotation only data sealed to the new key is accepted
+            set_caller(acc.alice);
+            assert_eq!(pool.set_vault_key([2; KYBER_PUBLICKEYBYTES]), Ok(2));
+            set_caller(acc.bob);
+            assert_eq!(pool.rotate_provider_data(sealed(1, 50)), Err(Error::StaleVaultKey));
+            pool.rotate_provider_data(sealed(2, 50)).unwrap();
+            assert_eq!(pool.provider_data(acc.bob).unwrap().key_version, 2);
+
+            pool.remove_provider_data().unwrap();
+            assert_eq!(pool.provider_data(acc.bob), None);
+            assert_eq!(pool.remove_provider_data(), Err(Error::NoProviderData));
+        }
+
+        /// Rewards paid in NRSH, `funds` of them from alice, and ELXR
+        /// providers earning 10 per second from 0 to `end`
+        fn start_emissions(pool: &mut UnifiedLiquidityPool, funds: Balance, end: Timestamp) {
+            set_caller(accounts().alice);
+            pool.set_reward_token(TokenId::NRSH).unwrap();
+            mint(token_address(TokenId::NRSH), accounts().alice, funds);
+            pool.fund_rewards(funds).unwrap();
+            pool.set_reward_schedule(TokenId::ELXR, RewardSchedule { rate: 10, start: 0, end }).unwrap();
+        }
+
+        #[ink::test]
+        fn emissions_are_shared_by_shares_over_time() {
+            let mut pool = setup();
+            let acc = accounts();
+            start_emissions(&mut pool, 100_000, 10_000);
+
+            set_caller(acc.bob);
+            pool.add_liquidity(TokenId::ELXR, 1_000, 0).unwrap();
+            set_timestamp(4_000);
+            assert_eq!(pool.pending_rewards(acc.bob, TokenId::ELXR), Ok(40));
+            set_caller(acc.charlie);
+            pool.add_liquidity(TokenId::ELXR, 3_000, 0).unwrap();
+
+            // The last 60 are split 1:3, and nothing is emitted after the end
+            set_timestamp(20_000);
+            assert_eq!(pool.pending_rewards(acc.bob, TokenId::ELXR), Ok(55));
+            assert_eq!(pool.pending_rewards(acc.charlie, TokenId::ELXR), Ok(45));
+            assert_eq!(pool.pending_rewards(acc.charlie, TokenId::NRSH), Ok(0));
+
+            set_caller(acc.bob);
+            assert_eq!(pool.claim_rewards(), Ok(55));
+            assert_eq!(pool.claim_rewards(), Ok(0));
+            assert_eq!(balance(token_address(TokenId::NRSH), acc.bob), 1_000_055);
+            assert_eq!(pool.reward_funds(), 100_000 - 55);
+            // Withdrawing settles what was earned instead of forfeiting it
+            set_caller(acc.charlie);
+            pool.remove_liquidity(TokenId::ELXR, 3_000).unwrap();
+            assert_eq!(pool.pending_rewards(acc.charlie, TokenId::ELXR), Ok(45));
+            assert_eq!(pool.claim_rewards(), Ok(45));
+            assert_eq!(
+                balance(token_address(TokenId::NRSH), AccountId::from(POOL)),
+                pool.reserve(TokenId::NRSH) + pool.reward_funds()
+            );
+        }
+
+        #[ink::test]
+        fn lockups_boost_rewards_until_they_expire() {
+            let mut pool = setup();
+            let acc = accounts();
+            set_caller(acc.bob);
+            assert_eq!(pool.claim_rewards(), Err(Error::RewardsNotConfigured));
+            assert_eq!(pool.set_lock_tiers(vec![(5_000, 2_000)]), Err(Error::NotAuthorized));
+            set_caller(acc.alice);
+            assert_eq!(pool.set_lock_tiers(vec![(5_000, 500)]), Err(Error::InvalidParameter));
+            pool.set_lock_tiers(vec![(1_000, 1_500), (5_000, 2_000)]).unwrap();
+            start_emissions(&mut pool, 40, 100_000);
+
+            for provider in [acc.bob, acc.charlie] {
+                set_caller(provider);
+                pool.add_liquidity(TokenId::ELXR, 1_000, 0).unwrap();
+            }
+            set_caller(acc.eve);
+            assert_eq!(pool.lock_shares(TokenId::ELXR, 1), Err(Error::InsufficientShares));
+            set_caller(acc.bob);
+            assert_eq!(pool.lock_shares(TokenId::ELXR, 2), Err(Error::InvalidParameter));
+            assert_eq!(pool.lock_shares(TokenId::ELXR, 1), Ok(5_000));
+            assert_eq!(pool.reward_position(acc.bob, TokenId::ELXR).weight, 2_000);
+            // Neither shortened nor weakened while it runs
+            assert_eq!(pool.lock_shares(TokenId::ELXR, 0), Err(Error::SharesLocked));
+
+            set_timestamp(3_000);
+            assert_eq!(pool.pending_rewards(acc.bob, TokenId::ELXR), Ok(20));
+            assert_eq!(pool.pending_rewards(acc.charlie, TokenId::ELXR), Ok(10));
+            assert_eq!(pool.remove_liquidity(TokenId::ELXR, 1), Err(Error::SharesLocked));
+
+            // The boost lasts until someone settles the expired lock
+            set_timestamp(5_000);
+            set_caller(acc.charlie);
+            pool.refresh_position(acc.bob, TokenId::ELXR).unwrap();
+            assert_eq!(pool.reward_position(acc.bob, TokenId::ELXR).weight, 1_000);
+            set_timestamp(9_000);
+            assert_eq!(pool.pending_rewards(acc.bob, TokenId::ELXR), Ok(53));
+            assert_eq!(pool.pending_rewards(acc.charlie, TokenId::ELXR), Ok(36));
+
+            // Pending rewards wait for funds rather than being paid in part
+            set_caller(acc.bob);
+            assert_eq!(pool.claim_rewards(), Err(Error::InsufficientRewardFunds));
+            set_caller(acc.alice);
+            mint(token_address(TokenId::NRSH), acc.alice, 100);
+            pool.fund_rewards(100).unwrap();
+            set_caller(acc.bob);
+            assert_eq!(pool.claim_rewards(), Ok(53));
+            pool.remove_liquidity(TokenId::ELXR, 1_000).unwrap();
+        }
     }
 }
@@ -6,6 +6,10 @@ use ink_storage::{
 };
 use pqc_kyber::*;
 use pqc_dilithium::*;
+use openbrush::contracts::traits::psp22::PSP22Ref;
+use oracle_consumer::{FeedId, OracleRef, PriceData, PRICE_PRECISION};
+#[cfg(test)]
+use oracle_consumer::test_ledger;
 use scale::{Decode, Encode};
 
 #[ink::contract]
@@ -17,23 +21,103 @@ mod zero_spread_dex {
         orders: Mapping<OrderId, Order>,
         user_orders: Mapping<AccountId, Vec<OrderId>>,
         trades: Mapping<TradeId, Trade>,
+        next_order_id: OrderId,
+        next_trade_id: TradeId,
         
-        // MEV protection
-        order_queue: Vec<OrderId>,
-        block_commitment: [u8; 32],
+        // Deposited PSP22 tokens not escrowed in an order, by (owner, token)
+        balances: Mapping<(AccountId, TokenId), Balance>,
+        
+        // Resting orders selling `.0` for `.1`, best price first, then oldest first
+        books: Mapping<(TokenId, TokenId), Vec<OrderId>>,
+        
+        // MEV protection: batch auctions
+        // Revealed orders per (batch, token_a, token_b) with their commitments
+        order_queue: Mapping<(BatchId, TokenId, TokenId), Vec<(OrderId, Hash)>>,
+        // Running hash over every commitment made in a batch
+        block_commitment: Mapping<BatchId, Hash>,
+        // Unrevealed commitments and the batch they were made in
+        commitments: Mapping<Hash, (AccountId, BatchId)>,
+        batch_length: BlockNumber,
         
         // Fees and protocol parameters
         fee_rate: Balance, // Set to 0.0369%
+        // Part of fee_rate charged to makers, per million; takers pay the rest
+        maker_fee_share: Balance,
         fee_collector: AccountId,
+        // Fees held for the collector, by token
+        collected_fees: Mapping<TokenId, Balance>,
+        // All-time fees per pair keyed like batches, in (token_a, token_b)
+        pair_fee_revenue: Mapping<(TokenId, TokenId), (Balance, Balance)>,
         
         // Bridge data
         external_bridges: Mapping<ChainId, BridgeInfo>,
+        // Hashed-timelock escrows of bridged orders
+        bridge_escrows: Mapping<OrderId, BridgeEscrow>,
+        // Most of a token that may be delivered over a bridge per day
+        bridge_daily_caps: Mapping<(ChainId, TokenId), Balance>,
+        // (day, volume) delivered so far on that day
+        bridge_daily_volume: Mapping<(ChainId, TokenId), (u64, Balance)>,
+        // May pause bridges, but only the owner can resume them
+        bridge_guardian: Option<AccountId>,
         
         // Post-quantum security
         kyber_keys: Mapping<AccountId, KyberPublicKey>,
         dilithium_signatures: Mapping<OrderId, DilithiumSignature>,
+        
+        // Oracle prices for order triggering
+        oracle: Option<OracleRef>,
+        token_feeds: Mapping<TokenId, FeedId>,
+        max_oracle_staleness: Timestamp,
+        keeper_bounty_rate: Balance,
+        
+        // Governance
+        owner: AccountId,
     }
 
+    /// Tokens are PSP22 contracts, identified by their address
+    pub type TokenId = AccountId;
+    pub type OrderId = u64;
+    pub type TradeId = u64;
+    /// Batch auctions run in windows of `batch_length` blocks
+    pub type BatchId = BlockNumber;
+    pub type ChainId = u32;
+
+    /// Oracle prices older than this are not used to trigger orders (10 minutes)
+    pub const DEFAULT_MAX_ORACLE_STALENESS: Timestamp = 600_000;
+
+    /// Resting orders per side of a pair, bounds the gas of matching and insertion
+    pub const MAX_BOOK_DEPTH: usize = 128;
+
+    /// Order lifetime in blocks when the creator does not choose one
+    pub const DEFAULT_ORDER_LIFETIME: BlockNumber = 100;
+    /// Longest order lifetime, one week of 6 second blocks
+    pub const MAX_ORDER_LIFETIME: BlockNumber = 100_800;
+
+    /// Bounds on how long a bridged order stays locked, in blocks. The remote
+    /// side must lock for less, so the counterparty can claim here in time.
+    pub const MIN_BRIDGE_TIMEOUT: BlockNumber = 20;
+    pub const MAX_BRIDGE_TIMEOUT: BlockNumber = 14_400;
+
+    /// Bridge volume caps reset every day
+    pub const BRIDGE_CAP_PERIOD: Timestamp = 86_400_000;
+
+    /// Highest fee governance can set, per million
+    pub const MAX_FEE_RATE: Balance = 10_000; // 1%
+    /// Makers pay none of the fee unless governance changes the split
+    pub const DEFAULT_MAKER_FEE_SHARE: Balance = 0;
+
+    /// Blocks per batch auction window
+    pub const DEFAULT_BATCH_LENGTH: BlockNumber = 5;
+    pub const MAX_BATCH_LENGTH: BlockNumber = 600;
+    /// Revealed orders per pair and batch, bounds the gas of clearing
+    pub const MAX_BATCH_ORDERS: usize = 64;
+
+    /// Share of a conditional order paid to the keeper that triggers it, per million
+    pub const DEFAULT_KEEPER_BOUNTY_RATE: Balance = 1_000; // 0.1%
+    pub const MAX_KEEPER_BOUNTY_RATE: Balance = 10_000; // 1%
+
+    /// An order sells `amount_in` of `token_in` for at least `min_amount_out` of
+    /// `token_out`; partial fills must respect the same ratio.
     #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub struct Order {
@@ -44,6 +128,38 @@ mod zero_spread_dex {
         min_amount_out: Balance,
         expiration: BlockNumber,
         order_type: OrderType,
+        status: OrderStatus,
+        // Oracle price of token_in in token_out that activates a conditional order
+        trigger_price: Balance,
+        // Escrowed on top of amount_in for the keeper, refunded if never triggered
+        bounty: Balance,
+        // token_in sold and token_out received so far
+        filled_in: Balance,
+        filled_out: Balance,
+    }
+
+    impl Order {
+        pub fn remaining(&self) -> Balance {
+            self.amount_in - self.filled_in
+        }
+
+        /// Open orders hold `remaining()` of `token_in` in escrow
+        pub fn is_open(&self) -> bool {
+            matches!(self.status, OrderStatus::Open | OrderStatus::PartiallyFilled)
+        }
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum OrderStatus {
+        Open,
+        PartiallyFilled,
+        Filled,
+        /// Withdrawn by the creator, or the unfilled part of a market order
+        Cancelled,
+        Expired,
+        /// Escrow locked in a cross-chain swap until completed or refunded
+        Bridged,
     }
 
     #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
@@ -51,26 +167,82 @@ mod zero_spread_dex {
     pub enum OrderType {
         Market,
         Limit,
+        /// Market sell once the oracle price falls to the trigger price
         StopLoss,
+        /// Market sell once the oracle price rises to the trigger price
+        TakeProfit,
+        /// Limit order placed once the oracle price falls to the trigger price
+        StopLimit,
         CrossChain,
+        /// Revealed into a batch auction, filled at its uniform clearing price
+        Batch,
+    }
+
+    impl OrderType {
+        /// Waits for `trigger_order` before it can trade
+        pub fn is_conditional(&self) -> bool {
+            matches!(self, OrderType::StopLoss | OrderType::TakeProfit | OrderType::StopLimit)
+        }
     }
 
     #[derive(Encode, Decode, Debug)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub struct Trade {
         maker_order: OrderId,
         taker_order: OrderId,
         amount: Balance,
         price: Balance,
+        // Taken from what each side received
+        maker_fee: Balance,
+        taker_fee: Balance,
         timestamp: Timestamp,
         quantum_proof: DilithiumSignature,
     }
 
-    #[derive(Encode, Decode)]
+    /// Hashed-timelock terms of a bridged order. The escrow is released to
+    /// `counterparty` with the preimage of `hashlock` before `timeout`, and
+    /// refunded to the creator after it.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct BridgeEscrow {
+        target_chain: ChainId,
+        hashlock: Hash,
+        counterparty: AccountId,
+        timeout: BlockNumber,
+        // Hash of the encoded BridgeMessage relayers deliver
+        message_hash: Hash,
+    }
+
+    /// What a relayer carries to the target chain, emitted SCALE-encoded as the
+    /// `bridge_proof` of `OrderBridged`
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct BridgeMessage {
+        pub source_contract: AccountId,
+        pub order_id: OrderId,
+        pub target_chain: ChainId,
+        pub bridge_contract: Vec<u8>,
+        pub token_in: TokenId,
+        pub token_out: TokenId,
+        pub amount_in: Balance,
+        pub min_amount_out: Balance,
+        /// Creator's address on the target chain
+        pub recipient: Vec<u8>,
+        pub counterparty: AccountId,
+        pub hashlock: Hash,
+        pub timeout: BlockNumber,
+    }
+
+    #[derive(Encode, Decode, Debug, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub struct BridgeInfo {
         chain_id: ChainId,
         bridge_contract: Vec<u8>,
+        // Tokens that may be delivered on the target chain
         supported_tokens: Vec<TokenId>,
         quantum_verifier: KyberPublicKey,
+        // Circuit breaker: no new orders are bridged while set
+        paused: bool,
     }
 
     impl ZeroSpreadDEX {
@@ -78,7 +250,12 @@ mod zero_spread_dex {
         pub fn new(fee_collector: AccountId) -> Self {
             ink_lang::utils::initialize_contract(|contract: &mut Self| {
                 contract.fee_rate = 369; // 0.0369% represented as 369/1000000
+                contract.maker_fee_share = DEFAULT_MAKER_FEE_SHARE;
                 contract.fee_collector = fee_collector;
+                contract.owner = Self::env().caller();
+                contract.max_oracle_staleness = DEFAULT_MAX_ORACLE_STALENESS;
+                contract.keeper_bounty_rate = DEFAULT_KEEPER_BOUNTY_RATE;
+                contract.batch_length = DEFAULT_BATCH_LENGTH;
                 
                 // Initialize quantum-resistant keys
                 let (public_key, _) = kyber_keygen();
@@ -94,127 +271,548 @@ mod zero_spread_dex {
             amount_in: Balance,
             min_amount_out: Balance,
             order_type: OrderType,
+            lifetime: Option<BlockNumber>,
         ) -> Result<OrderId, Error> {
-            let caller = self.env().caller();
+            if order_type.is_conditional() || order_type == OrderType::Batch {
+                return Err(Error::InvalidOrder);
+            }
+            self.place_order(token_in, token_out, amount_in, min_amount_out, order_type, 0, lifetime)
+        }
+
+        /// Stop-loss, take-profit or stop-limit order that waits until the oracle price
+        /// of `token_in` in `token_out` crosses `trigger_price`. A keeper bounty of
+        /// `keeper_bounty_rate` of `amount_in` is escrowed on top of the order.
+        #[ink(message)]
+        pub fn create_conditional_order(
+            &mut self,
+            token_in: TokenId,
+            token_out: TokenId,
+            amount_in: Balance,
+            min_amount_out: Balance,
+            order_type: OrderType,
+            trigger_price: Balance,
+            lifetime: Option<BlockNumber>,
+        ) -> Result<OrderId, Error> {
+            if !order_type.is_conditional() || trigger_price == 0 {
+                return Err(Error::InvalidOrder);
+            }
+            self.place_order(token_in, token_out, amount_in, min_amount_out, order_type, trigger_price, lifetime)
+        }
+
+        /// Activate a conditional order whose trigger price has been crossed.
+        /// Anyone may call this; the caller receives the order's bounty.
+        #[ink(message)]
+        pub fn trigger_order(&mut self, order_id: OrderId) -> Result<Balance, Error> {
+            let mut order = self.orders.get(order_id)
+                .ok_or(Error::OrderNotFound)?;
             
-            // Generate quantum-resistant order ID
-            let order_id = self.generate_quantum_order_id(&caller);
+            if !order.order_type.is_conditional() || !order.is_open() {
+                return Err(Error::InvalidOrder);
+            }
+            if order.expiration < self.env().block_number() {
+                return Err(Error::OrderExpired);
+            }
+            let oracle_price = self.oracle_price(order.token_in, order.token_out)?;
+            if !self.order_triggered(&order)? {
+                return Err(Error::OrderNotTriggered);
+            }
             
-            let order = Order {
-                creator: caller,
-                token_in,
-                token_out,
-                amount_in,
-                min_amount_out,
-                expiration: self.env().block_number() + 100,
-                order_type,
+            let keeper = self.env().caller();
+            let bounty = order.bounty;
+            order.bounty = 0;
+            order.order_type = match order.order_type {
+                OrderType::StopLimit => OrderType::Limit,
+                _ => OrderType::Market,
             };
+            self.orders.insert(order_id, &order);
+            self.push(order.token_in, keeper, bounty)?;
+            
+            self.env().emit_event(OrderTriggered {
+                order_id,
+                keeper,
+                bounty,
+                oracle_price,
+            });
+            
+            self.execute_order(order_id, order)?;
+            Ok(bounty)
+        }
 
-            // Sign order with Dilithium
-            let signature = self.sign_order(&order);
-            self.dilithium_signatures.insert(order_id, &signature);
+        /// Commit to a batch auction order without revealing it. The order is
+        /// revealed with `reveal_order` in the next window and cleared in the one after.
+        #[ink(message)]
+        pub fn commit_order(&mut self, commitment: Hash) -> Result<BatchId, Error> {
+            if self.commitments.get(commitment).is_some() {
+                return Err(Error::InvalidOrder);
+            }
+            let caller = self.env().caller();
+            let batch_id = self.current_batch();
+            self.commitments.insert(commitment, &(caller, batch_id));
             
-            // Store order with MEV protection
-            self.insert_order_protected(order_id, order)?;
+            let previous = self.block_commitment.get(batch_id).unwrap_or_default();
+            let mut output = [0u8; 32];
+            ink_env::hash_bytes::<ink_env::hash::Blake2x256>(&(previous, commitment).encode(), &mut output);
+            self.block_commitment.insert(batch_id, &Hash::from(output));
             
-            // Update user orders
-            let mut user_orders = self.user_orders.get(caller).unwrap_or_default();
-            user_orders.push(order_id);
-            self.user_orders.insert(caller, &user_orders);
+            self.env().emit_event(OrderCommitted {
+                commitment,
+                owner: caller,
+                batch_id,
+            });
+            Ok(batch_id)
+        }
 
-            self.env().emit_event(OrderCreated {
-                order_id,
-                creator: caller,
+        /// Reveal a committed order in the window after its commitment. Its input
+        /// is escrowed until the batch clears.
+        #[ink(message)]
+        pub fn reveal_order(
+            &mut self,
+            token_in: TokenId,
+            token_out: TokenId,
+            amount_in: Balance,
+            min_amount_out: Balance,
+            salt: [u8; 32],
+        ) -> Result<OrderId, Error> {
+            let caller = self.env().caller();
+            let commitment = self.order_commitment(caller, token_in, token_out, amount_in, min_amount_out, salt);
+            let (owner, batch_id) = self.commitments.get(commitment)
+                .ok_or(Error::CommitmentNotFound)?;
+            if owner != caller {
+                return Err(Error::CommitmentNotFound);
+            }
+            // Revealing in the commit window or after the reveal window would let
+            // the order react to the other orders of the batch
+            if self.current_batch() != batch_id + 1 {
+                return Err(Error::MEVDetected);
+            }
+            
+            let key = Self::batch_key(batch_id, token_in, token_out);
+            let mut queue = self.order_queue.get(key).unwrap_or_default();
+            if queue.len() >= MAX_BATCH_ORDERS {
+                return Err(Error::OrderBookFull);
+            }
+            
+            // Unclaimed orders can be swept a while after the batch becomes clearable
+            let clearable_at = (batch_id + 2) * self.batch_length;
+            let lifetime = clearable_at - self.env().block_number() + DEFAULT_ORDER_LIFETIME;
+            
+            self.commitments.remove(commitment);
+            let order_id = self.place_order(
                 token_in,
                 token_out,
                 amount_in,
-            });
-
+                min_amount_out,
+                OrderType::Batch,
+                0,
+                Some(lifetime),
+            )?;
+            queue.push((order_id, commitment));
+            self.order_queue.insert(key, &queue);
             Ok(order_id)
         }
 
+        /// Clear a batch for a pair at a single price once its reveal window has
+        /// closed. Anyone may call this. The price maximizes the volume traded;
+        /// the larger side is filled pro rata and every unfilled remainder is refunded.
+        /// Returns the clearing price of the lower token address in the other.
         #[ink(message)]
-        pub fn execute_trade(
+        pub fn clear_batch(
             &mut self,
-            maker_order_id: OrderId,
-            taker_order_id: OrderId,
-        ) -> Result<TradeId, Error> {
-            // Verify both orders exist and are valid
-            let maker_order = self.orders.get(maker_order_id)
-                .ok_or(Error::OrderNotFound)?;
-            let taker_order = self.orders.get(taker_order_id)
-                .ok_or(Error::OrderNotFound)?;
+            batch_id: BatchId,
+            token_a: TokenId,
+            token_b: TokenId,
+        ) -> Result<Balance, Error> {
+            if self.current_batch() < batch_id + 2 {
+                return Err(Error::BatchInProgress);
+            }
+            let key = Self::batch_key(batch_id, token_a, token_b);
+            let mut queue = self.order_queue.get(key).ok_or(Error::OrderNotFound)?;
+            self.order_queue.remove(key);
+            let (_, token_a, token_b) = key;
             
-            // Verify signatures
-            self.verify_order_signatures(&maker_order, &taker_order)?;
+            // Ordering by commitment keeps reveal order from mattering
+            queue.sort_by(|x, y| x.1.as_ref().cmp(y.1.as_ref()));
+            let mut participants: Vec<(OrderId, Order)> = queue
+                .into_iter()
+                .filter_map(|(order_id, _)| self.orders.get(order_id).map(|order| (order_id, order)))
+                .filter(|(_, order)| order.is_open())
+                .collect();
             
-            // Calculate trade amounts with MEV protection
-            let (amount, price) = self.calculate_trade_amounts(
-                &maker_order,
-                &taker_order
-            )?;
+            // Orders whose limit fails only through rounding are dropped and the
+            // price recomputed; each pass removes at least one order
+            let mut dropped = Vec::new();
+            let (price, volume, fills) = loop {
+                let (asks, bids): (Vec<Order>, Vec<Order>) = participants
+                    .iter()
+                    .map(|(_, order)| *order)
+                    .partition(|order| order.token_in == token_a);
+                let (price, volume) = match Self::clearing_price(&asks, &bids)? {
+                    Some(cleared) => cleared,
+                    None => break (0, 0, Vec::new()),
+                };
+                let fills = Self::batch_fills(&participants, token_a, price, volume)?;
+                
+                let violation = participants.iter().zip(fills.iter()).position(|((_, order), (sold, bought))| {
+                    *sold > 0 && bought.saturating_mul(order.amount_in) < sold.saturating_mul(order.min_amount_out)
+                });
+                match violation {
+                    Some(index) => dropped.push(participants.remove(index)),
+                    None => break (price, volume, fills),
+                }
+            };
             
-            // Execute atomic swap
-            self.execute_atomic_swap(
-                &maker_order,
-                &taker_order,
-                amount,
-                price
-            )?;
+            // Settle out of escrow; buyers round up and sellers round down, the
+            // difference in token_b goes to the fee collector. Batch fills pay the taker fee.
+            let taker_rate = self.fee_rate - self.maker_fee_rate();
+            let mut paid_b: Balance = 0;
+            let mut received_b: Balance = 0;
+            for ((order_id, mut order), (sold, bought)) in participants.into_iter().zip(fills.into_iter()) {
+                if sold > 0 {
+                    if order.token_in == token_a {
+                        received_b += bought;
+                    } else {
+                        paid_b += sold;
+                    }
+                    order.filled_in += sold;
+                    order.filled_out += bought;
+                    let fee = self.charge_fee(order.token_out, order.token_in, bought, taker_rate)?;
+                    self.push(order.token_out, order.creator, bought - fee)?;
+                    if order.remaining() == 0 {
+                        order.status = OrderStatus::Filled;
+                        self.orders.insert(order_id, &order);
+                        self.remove_user_order(order.creator, order_id);
+                        continue;
+                    }
+                    order.status = OrderStatus::PartiallyFilled;
+                }
+                self.close_order(order_id, &mut order, OrderStatus::Cancelled)?;
+            }
+            for (order_id, mut order) in dropped {
+                self.close_order(order_id, &mut order, OrderStatus::Cancelled)?;
+            }
+            self.accrue_fee(token_b, token_a, paid_b - received_b)?;
             
-            // Generate trade proof
-            let trade_id = self.generate_trade_id();
-            let quantum_proof = self.generate_trade_proof(
-                &maker_order,
-                &taker_order,
-                amount,
-                price
+            self.env().emit_event(BatchCleared {
+                batch_id,
+                token_a,
+                token_b,
+                clearing_price: price,
+                volume,
+            });
+            Ok(price)
+        }
+
+        /// Commitment for `commit_order`; the salt keeps the order hidden
+        #[ink(message)]
+        pub fn order_commitment(
+            &self,
+            owner: AccountId,
+            token_in: TokenId,
+            token_out: TokenId,
+            amount_in: Balance,
+            min_amount_out: Balance,
+            salt: [u8; 32],
+        ) -> Hash {
+            let mut output = [0u8; 32];
+            ink_env::hash_bytes::<ink_env::hash::Blake2x256>(
+                &(owner, token_in, token_out, amount_in, min_amount_out, salt).encode(),
+                &mut output,
             );
+            Hash::from(output)
+        }
+
+        #[ink(message)]
+        pub fn current_batch(&self) -> BatchId {
+            self.env().block_number() / self.batch_length
+        }
+
+        /// Running hash of every commitment made in `batch_id`
+        #[ink(message)]
+        pub fn batch_commitment(&self, batch_id: BatchId) -> Option<Hash> {
+            self.block_commitment.get(batch_id)
+        }
+
+        /// Changing the window length renumbers batches, so only do it with no batch in flight
+        #[ink(message)]
+        pub fn set_batch_length(&mut self, batch_length: BlockNumber) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if batch_length == 0 || batch_length > MAX_BATCH_LENGTH {
+                return Err(Error::InvalidExpiry);
+            }
+            self.batch_length = batch_length;
+            Ok(())
+        }
+
+        /// Total fee per fill and the part of it charged to makers, both per million
+        #[ink(message)]
+        pub fn set_fee_rate(&mut self, fee_rate: Balance, maker_fee_share: Balance) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if fee_rate > MAX_FEE_RATE || maker_fee_share > 1_000_000 {
+                return Err(Error::InvalidFeeRate);
+            }
+            self.fee_rate = fee_rate;
+            self.maker_fee_share = maker_fee_share;
+            self.env().emit_event(FeeRateUpdated {
+                fee_rate,
+                maker_fee_share,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_fee_collector(&mut self, fee_collector: AccountId) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.fee_collector = fee_collector;
+            Ok(())
+        }
+
+        /// `(maker, taker)` fee rates per million
+        #[ink(message)]
+        pub fn fee_rates(&self) -> (Balance, Balance) {
+            let maker_rate = self.maker_fee_rate();
+            (maker_rate, self.fee_rate - maker_rate)
+        }
+
+        /// Fees of `token` not yet withdrawn by the collector
+        #[ink(message)]
+        pub fn collected_fees(&self, token: TokenId) -> Balance {
+            self.collected_fees.get(token).unwrap_or(0)
+        }
+
+        /// All-time fees earned on a pair, in each of its tokens
+        #[ink(message)]
+        pub fn fee_revenue(&self, token_a: TokenId, token_b: TokenId) -> (Balance, Balance) {
+            let key = Self::pair_key(token_a, token_b);
+            let (revenue_first, revenue_second) = self.pair_fee_revenue.get(key).unwrap_or_default();
+            if key.0 == token_a {
+                (revenue_first, revenue_second)
+            } else {
+                (revenue_second, revenue_first)
+            }
+        }
+
+        #[ink(message)]
+        pub fn withdraw_fees(&mut self, token: TokenId, amount: Balance) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if caller != self.fee_collector {
+                return Err(Error::NotAuthorized);
+            }
+            let collected = self.collected_fees(token);
+            if amount > collected {
+                return Err(Error::InsufficientBalance);
+            }
+            self.collected_fees.insert(token, &(collected - amount));
+            self.push(token, caller, amount)?;
             
-            let trade = Trade {
-                maker_order: maker_order_id,
-                taker_order: taker_order_id,
+            self.env().emit_event(FeesWithdrawn {
+                token,
+                to: caller,
                 amount,
-                price,
-                timestamp: self.env().block_timestamp(),
-                quantum_proof,
-            };
+            });
+            Ok(())
+        }
+
+        /// Share of conditional orders paid to keepers, per million
+        #[ink(message)]
+        pub fn set_keeper_bounty_rate(&mut self, rate: Balance) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if rate > MAX_KEEPER_BOUNTY_RATE {
+                return Err(Error::InvalidOrder);
+            }
+            self.keeper_bounty_rate = rate;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn keeper_bounty_rate(&self) -> Balance {
+            self.keeper_bounty_rate
+        }
+
+        /// Withdraw an open order and refund its unfilled escrow. Batch orders
+        /// are only settled or refunded by `clear_batch` and `sweep_expired`.
+        #[ink(message)]
+        pub fn cancel_order(&mut self, order_id: OrderId) -> Result<(), Error> {
+            let mut order = self.orders.get(order_id)
+                .ok_or(Error::OrderNotFound)?;
             
-            self.trades.insert(trade_id, &trade);
+            if order.creator != self.env().caller() {
+                return Err(Error::NotAuthorized);
+            }
+            // Pulling a revealed order after seeing the rest of the batch would
+            // be a free option on the clearing price
+            if order.order_type == OrderType::Batch {
+                return Err(Error::BatchInProgress);
+            }
+            if !order.is_open() {
+                return Err(Error::InvalidOrder);
+            }
+            
+            self.remove_from_book(order_id, &order);
+            self.close_order(order_id, &mut order, OrderStatus::Cancelled)
+        }
 
-            self.env().emit_event(TradeExecuted {
-                trade_id,
-                maker: maker_order.creator,
-                taker: taker_order.creator,
+        /// Refund every expired order in `order_ids`; anyone may call this.
+        /// Orders that are not open or not yet expired are skipped.
+        /// Returns the number of orders expired.
+        #[ink(message)]
+        pub fn sweep_expired(&mut self, order_ids: Vec<OrderId>) -> Result<u32, Error> {
+            let now = self.env().block_number();
+            let mut swept = 0;
+            
+            for order_id in order_ids {
+                let mut order = match self.orders.get(order_id) {
+                    Some(order) => order,
+                    None => continue,
+                };
+                if !order.is_open() || order.expiration >= now {
+                    continue;
+                }
+                
+                self.remove_from_book(order_id, &order);
+                self.close_order(order_id, &mut order, OrderStatus::Expired)?;
+                swept += 1;
+            }
+            Ok(swept)
+        }
+
+        /// Open orders of `account`
+        #[ink(message)]
+        pub fn get_user_orders(&self, account: AccountId) -> Vec<OrderId> {
+            self.user_orders.get(account).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn get_order(&self, order_id: OrderId) -> Option<Order> {
+            self.orders.get(order_id)
+        }
+
+        #[ink(message)]
+        pub fn get_trade(&self, trade_id: TradeId) -> Option<Trade> {
+            self.trades.get(trade_id)
+        }
+
+        /// Resting orders selling `token_in` for `token_out` in matching order
+        #[ink(message)]
+        pub fn get_order_book(&self, token_in: TokenId, token_out: TokenId) -> Vec<OrderId> {
+            self.books.get((token_in, token_out)).unwrap_or_default()
+        }
+
+        /// Move `amount` of `token` into the caller's DEX balance through its PSP22
+        /// allowance. New orders draw on this balance before pulling from the wallet.
+        #[ink(message)]
+        pub fn deposit(&mut self, token: TokenId, amount: Balance) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let balance = self.balance_of(caller, token).checked_add(amount).ok_or(Error::ArithmeticOverflow)?;
+            self.pull(token, caller, amount)?;
+            self.balances.insert((caller, token), &balance);
+            
+            self.env().emit_event(Deposited {
+                owner: caller,
+                token,
                 amount,
-                price,
             });
+            Ok(())
+        }
 
-            Ok(trade_id)
+        #[ink(message)]
+        pub fn withdraw(&mut self, token: TokenId, amount: Balance) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let balance = self.balance_of(caller, token);
+            if amount > balance {
+                return Err(Error::InsufficientBalance);
+            }
+            self.balances.insert((caller, token), &(balance - amount));
+            self.push(token, caller, amount)?;
+            
+            self.env().emit_event(Withdrawn {
+                owner: caller,
+                token,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Deposited `token` of `owner` that is free to withdraw or trade
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId, token: TokenId) -> Balance {
+            self.balances.get((owner, token)).unwrap_or(0)
         }
 
+        /// Lock a cross-chain order in a hashed-timelock escrow. `counterparty`
+        /// pays `min_amount_out` to `recipient` on the target chain under the same
+        /// hashlock; claiming there reveals the preimage, which releases the escrow
+        /// here. Returns the hash of the bridge message.
         #[ink(message)]
         pub fn bridge_order(
             &mut self,
             order_id: OrderId,
             target_chain: ChainId,
-        ) -> Result<(), Error> {
+            recipient: Vec<u8>,
+            counterparty: AccountId,
+            hashlock: Hash,
+            timeout: BlockNumber,
+        ) -> Result<Hash, Error> {
             let bridge_info = self.external_bridges.get(target_chain)
                 .ok_or(Error::BridgeNotFound)?;
+            if bridge_info.paused {
+                return Err(Error::BridgePaused);
+            }
                 
-            let order = self.orders.get(order_id)
+            let mut order = self.orders.get(order_id)
                 .ok_or(Error::OrderNotFound)?;
             
+            if order.creator != self.env().caller() {
+                return Err(Error::NotAuthorized);
+            }
+            if order.order_type != OrderType::CrossChain || order.status != OrderStatus::Open {
+                return Err(Error::InvalidOrder);
+            }
+            // An expired order belongs to `sweep_expired`, not to a new lock
+            if order.expiration < self.env().block_number() {
+                return Err(Error::OrderExpired);
+            }
+            if !(MIN_BRIDGE_TIMEOUT..=MAX_BRIDGE_TIMEOUT).contains(&timeout) {
+                return Err(Error::InvalidExpiry);
+            }
+            
             // Verify token is supported on target chain
             if !bridge_info.supported_tokens.contains(&order.token_out) {
                 return Err(Error::TokenNotSupported);
             }
+            self.record_bridge_volume(target_chain, order.token_out, order.min_amount_out)?;
+            
+            let message = BridgeMessage {
+                source_contract: self.env().account_id(),
+                order_id,
+                target_chain,
+                bridge_contract: bridge_info.bridge_contract.clone(),
+                token_in: order.token_in,
+                token_out: order.token_out,
+                amount_in: order.amount_in,
+                min_amount_out: order.min_amount_out,
+                recipient,
+                counterparty,
+                hashlock,
+                timeout: self.env().block_number() + timeout,
+            };
             
             // Create quantum-resistant bridge proof
             let bridge_proof = self.generate_bridge_proof(
-                &order,
+                &message,
                 &bridge_info
             );
+            let message_hash = Self::hash_preimage(&bridge_proof);
+            
+            // The escrow taken at creation stays locked under the hashlock
+            order.status = OrderStatus::Bridged;
+            self.orders.insert(order_id, &order);
+            self.bridge_escrows.insert(order_id, &BridgeEscrow {
+                target_chain,
+                hashlock,
+                counterparty,
+                timeout: message.timeout,
+                message_hash,
+            });
             
             // Emit bridge event with proof
             self.env().emit_event(OrderBridged {
@@ -223,15 +821,668 @@ mod zero_spread_dex {
                 bridge_proof,
             });
 
-            Ok(())
+            Ok(message_hash)
         }
 
-        // Helper functions for quantum-resistant operations
-        fn generate_quantum_order_id(&self, creator: &AccountId) -> OrderId {
-            // Implementation using Kyber for randomness
+        /// Release a bridged order's escrow to its counterparty with the preimage
+        /// of the hashlock, revealed when the creator claimed on the target chain.
+        /// Anyone may relay the preimage.
+        #[ink(message)]
+        pub fn complete_bridge(&mut self, order_id: OrderId, preimage: Vec<u8>) -> Result<(), Error> {
+            let escrow = self.bridge_escrows.get(order_id)
+                .ok_or(Error::OrderNotFound)?;
+            let mut order = self.orders.get(order_id)
+                .ok_or(Error::OrderNotFound)?;
+            
+            if self.env().block_number() > escrow.timeout {
+                return Err(Error::OrderExpired);
+            }
+            if Self::hash_preimage(&preimage) != escrow.hashlock {
+                return Err(Error::InvalidPreimage);
+            }
+            
+            // The counterparty took the order, so it pays the taker fee
+            let amount = order.amount_in;
+            let fee_rate = self.fee_rate - self.maker_fee_rate();
+            let fee = self.charge_fee(order.token_in, order.token_out, amount, fee_rate)?;
+            
+            self.bridge_escrows.remove(order_id);
+            order.filled_in = amount;
+            order.filled_out = order.min_amount_out;
+            order.status = OrderStatus::Filled;
+            self.orders.insert(order_id, &order);
+            self.remove_user_order(order.creator, order_id);
+            self.push(order.token_in, escrow.counterparty, amount - fee)?;
+            
+            self.env().emit_event(BridgeCompleted {
+                order_id,
+                counterparty: escrow.counterparty,
+                preimage,
+            });
+            Ok(())
         }
 
-        fn sign_order(&self, order: &Order) -> DilithiumSignature {
+        /// Refund a bridged order to its creator once the timelock has passed
+        /// without completion. Anyone may call this.
+        #[ink(message)]
+        pub fn refund_bridge(&mut self, order_id: OrderId) -> Result<(), Error> {
+            let escrow = self.bridge_escrows.get(order_id)
+                .ok_or(Error::OrderNotFound)?;
+            let mut order = self.orders.get(order_id)
+                .ok_or(Error::OrderNotFound)?;
+            
+            if self.env().block_number() <= escrow.timeout {
+                return Err(Error::BridgeLocked);
+            }
+            
+            self.bridge_escrows.remove(order_id);
+            self.close_order(order_id, &mut order, OrderStatus::Expired)
+        }
+
+        #[ink(message)]
+        pub fn get_bridge_escrow(&self, order_id: OrderId) -> Option<BridgeEscrow> {
+            self.bridge_escrows.get(order_id)
+        }
+
+        /// Register a bridge with no tokens allowed yet
+        #[ink(message)]
+        pub fn add_bridge(
+            &mut self,
+            chain_id: ChainId,
+            bridge_contract: Vec<u8>,
+            quantum_verifier: KyberPublicKey,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if self.external_bridges.get(chain_id).is_some() {
+                return Err(Error::BridgeAlreadyExists);
+            }
+            self.external_bridges.insert(chain_id, &BridgeInfo {
+                chain_id,
+                bridge_contract,
+                supported_tokens: Vec::new(),
+                quantum_verifier,
+                paused: false,
+            });
+            self.env().emit_event(BridgeAdded { chain_id });
+            Ok(())
+        }
+
+        /// Point a bridge at a new contract or verifier key, keeping its tokens
+        #[ink(message)]
+        pub fn update_bridge(
+            &mut self,
+            chain_id: ChainId,
+            bridge_contract: Vec<u8>,
+            quantum_verifier: KyberPublicKey,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            let mut bridge_info = self.external_bridges.get(chain_id)
+                .ok_or(Error::BridgeNotFound)?;
+            bridge_info.bridge_contract = bridge_contract;
+            bridge_info.quantum_verifier = quantum_verifier;
+            self.external_bridges.insert(chain_id, &bridge_info);
+            self.env().emit_event(BridgeUpdated { chain_id });
+            Ok(())
+        }
+
+        /// Orders already bridged can still be completed or refunded
+        #[ink(message)]
+        pub fn remove_bridge(&mut self, chain_id: ChainId) -> Result<(), Error> {
+            self.ensure_owner()?;
+            let bridge_info = self.external_bridges.get(chain_id)
+                .ok_or(Error::BridgeNotFound)?;
+            for token in bridge_info.supported_tokens {
+                self.bridge_daily_caps.remove((chain_id, token));
+                self.bridge_daily_volume.remove((chain_id, token));
+            }
+            self.external_bridges.remove(chain_id);
+            self.env().emit_event(BridgeRemoved { chain_id });
+            Ok(())
+        }
+
+        /// Allow `token` to be delivered over a bridge, at most `daily_cap` per day.
+        /// Also updates the cap of an allowed token.
+        #[ink(message)]
+        pub fn allow_bridge_token(
+            &mut self,
+            chain_id: ChainId,
+            token: TokenId,
+            daily_cap: Balance,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            let mut bridge_info = self.external_bridges.get(chain_id)
+                .ok_or(Error::BridgeNotFound)?;
+            if !bridge_info.supported_tokens.contains(&token) {
+                bridge_info.supported_tokens.push(token);
+                self.external_bridges.insert(chain_id, &bridge_info);
+            }
+            self.bridge_daily_caps.insert((chain_id, token), &daily_cap);
+            self.env().emit_event(BridgeTokenAllowed {
+                chain_id,
+                token,
+                daily_cap,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn disallow_bridge_token(&mut self, chain_id: ChainId, token: TokenId) -> Result<(), Error> {
+            self.ensure_owner()?;
+            let mut bridge_info = self.external_bridges.get(chain_id)
+                .ok_or(Error::BridgeNotFound)?;
+            if !bridge_info.supported_tokens.contains(&token) {
+                return Err(Error::TokenNotSupported);
+            }
+            bridge_info.supported_tokens.retain(|supported| *supported != token);
+            self.external_bridges.insert(chain_id, &bridge_info);
+            self.bridge_daily_caps.remove((chain_id, token));
+            self.bridge_daily_volume.remove((chain_id, token));
+            self.env().emit_event(BridgeTokenDisallowed { chain_id, token });
+            Ok(())
+        }
+
+        /// Stop new orders over a bridge. The owner or the guardian may pause.
+        #[ink(message)]
+        pub fn pause_bridge(&mut self, chain_id: ChainId) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if caller != self.owner && Some(caller) != self.bridge_guardian {
+                return Err(Error::NotAuthorized);
+            }
+            self.set_bridge_paused(chain_id, true)?;
+            self.env().emit_event(BridgePaused { chain_id, by: caller });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn unpause_bridge(&mut self, chain_id: ChainId) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.set_bridge_paused(chain_id, false)?;
+            self.env().emit_event(BridgeUnpaused { chain_id });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_bridge_guardian(&mut self, guardian: Option<AccountId>) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.bridge_guardian = guardian;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn get_bridge(&self, chain_id: ChainId) -> Option<BridgeInfo> {
+            self.external_bridges.get(chain_id)
+        }
+
+        /// `(daily_cap, delivered_today)` of a token over a bridge
+        #[ink(message)]
+        pub fn bridge_token_volume(&self, chain_id: ChainId, token: TokenId) -> Option<(Balance, Balance)> {
+            let daily_cap = self.bridge_daily_caps.get((chain_id, token))?;
+            let today = self.env().block_timestamp() / BRIDGE_CAP_PERIOD;
+            let volume = match self.bridge_daily_volume.get((chain_id, token)) {
+                Some((day, volume)) if day == today => volume,
+                _ => 0,
+            };
+            Some((daily_cap, volume))
+        }
+
+        #[ink(message)]
+        pub fn set_oracle(
+            &mut self,
+            oracle: AccountId,
+            max_staleness: Timestamp,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.oracle = Some(OracleRef::new(oracle));
+            self.max_oracle_staleness = max_staleness;
+            Ok(())
+        }
+
+        /// USD feed used to price `token_id`
+        #[ink(message)]
+        pub fn set_token_feed(
+            &mut self,
+            token_id: TokenId,
+            feed_id: FeedId,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.token_feeds.insert(token_id, &feed_id);
+            Ok(())
+        }
+
+        /// Oracle price of `token_in` in units of `token_out`, scaled by `PRICE_PRECISION`
+        #[ink(message)]
+        pub fn oracle_price(
+            &self,
+            token_in: TokenId,
+            token_out: TokenId,
+        ) -> Result<Balance, Error> {
+            let price_in = self.fresh_token_price(token_in)?;
+            let price_out = self.fresh_token_price(token_out)?;
+            
+            price_in.checked_mul(PRICE_PRECISION)
+                .and_then(|scaled| scaled.checked_div(price_out))
+                .ok_or(Error::OracleUnavailable)
+        }
+
+        /// Whether the oracle price has reached an order's limit or stop price.
+        /// Keepers use this to find orders that can be executed.
+        #[ink(message)]
+        pub fn is_order_triggered(&self, order_id: OrderId) -> Result<bool, Error> {
+            let order = self.orders.get(order_id)
+                .ok_or(Error::OrderNotFound)?;
+            self.order_triggered(&order)
+        }
+
+        fn place_order(
+            &mut self,
+            token_in: TokenId,
+            token_out: TokenId,
+            amount_in: Balance,
+            min_amount_out: Balance,
+            order_type: OrderType,
+            trigger_price: Balance,
+            lifetime: Option<BlockNumber>,
+        ) -> Result<OrderId, Error> {
+            let caller = self.env().caller();
+            
+            if token_in == token_out || amount_in == 0 {
+                return Err(Error::InvalidOrder);
+            }
+            let lifetime = lifetime.unwrap_or(DEFAULT_ORDER_LIFETIME);
+            if lifetime == 0 || lifetime > MAX_ORDER_LIFETIME {
+                return Err(Error::InvalidExpiry);
+            }
+            // Orders that can rest on the book need a price
+            if matches!(
+                order_type,
+                OrderType::Limit | OrderType::StopLimit | OrderType::CrossChain | OrderType::Batch
+            ) && min_amount_out == 0
+            {
+                return Err(Error::InvalidOrder);
+            }
+            
+            let bounty = if order_type.is_conditional() {
+                amount_in.checked_mul(self.keeper_bounty_rate)
+                    .ok_or(Error::ArithmeticOverflow)? / 1_000_000
+            } else {
+                0
+            };
+            
+            let order_id = self.generate_quantum_order_id(&caller);
+            
+            let order = Order {
+                creator: caller,
+                token_in,
+                token_out,
+                amount_in,
+                min_amount_out,
+                expiration: self.env().block_number() + lifetime,
+                order_type,
+                status: OrderStatus::Open,
+                trigger_price,
+                bounty,
+                filled_in: 0,
+                filled_out: 0,
+            };
+
+            // Escrow the input tokens for the lifetime of the order
+            let escrow = amount_in.checked_add(bounty).ok_or(Error::ArithmeticOverflow)?;
+            self.escrow(caller, token_in, escrow)?;
+
+            // Sign order with Dilithium
+            let signature = self.sign_order(&order);
+            self.dilithium_signatures.insert(order_id, &signature);
+            self.orders.insert(order_id, &order);
+            
+            let order = self.execute_order(order_id, order)?;
+            
+            // Update user orders, which only lists open orders
+            if order.is_open() {
+                let mut user_orders = self.user_orders.get(caller).unwrap_or_default();
+                user_orders.push(order_id);
+                self.user_orders.insert(caller, &user_orders);
+            }
+
+            self.env().emit_event(OrderCreated {
+                order_id,
+                creator: caller,
+                token_in,
+                token_out,
+                amount_in,
+            });
+
+            Ok(order_id)
+        }
+
+        /// Trade a new or just triggered order according to its type
+        fn execute_order(&mut self, order_id: OrderId, mut order: Order) -> Result<Order, Error> {
+            match order.order_type {
+                // Limit orders take what crosses and rest with the remainder
+                OrderType::Limit => {
+                    order = self.match_order(order_id, order)?;
+                    if order.is_open() {
+                        self.insert_order_protected(order_id, order)?;
+                    }
+                }
+                // Market orders are immediate-or-cancel
+                OrderType::Market => {
+                    order = self.match_order(order_id, order)?;
+                    if order.is_open() {
+                        self.close_order(order_id, &mut order, OrderStatus::Cancelled)?;
+                    }
+                }
+                // Conditional orders wait for `trigger_order`, cross-chain orders for
+                // `bridge_order` and batch orders for `clear_batch`
+                _ => {}
+            }
+            Ok(order)
+        }
+
+        fn maker_fee_rate(&self) -> Balance {
+            self.fee_rate * self.maker_fee_share / 1_000_000
+        }
+
+        /// Fee on `amount` of `token` received in a trade against `counter_token`
+        fn charge_fee(
+            &mut self,
+            token: TokenId,
+            counter_token: TokenId,
+            amount: Balance,
+            rate: Balance,
+        ) -> Result<Balance, Error> {
+            let fee = amount.checked_mul(rate).ok_or(Error::ArithmeticOverflow)? / 1_000_000;
+            self.accrue_fee(token, counter_token, fee)?;
+            Ok(fee)
+        }
+
+        fn accrue_fee(&mut self, token: TokenId, counter_token: TokenId, fee: Balance) -> Result<(), Error> {
+            if fee == 0 {
+                return Ok(());
+            }
+            let collected = self.collected_fees(token).checked_add(fee).ok_or(Error::ArithmeticOverflow)?;
+            self.collected_fees.insert(token, &collected);
+            
+            let key = Self::pair_key(token, counter_token);
+            let (mut revenue_a, mut revenue_b) = self.pair_fee_revenue.get(key).unwrap_or_default();
+            if token == key.0 {
+                revenue_a = revenue_a.checked_add(fee).ok_or(Error::ArithmeticOverflow)?;
+            } else {
+                revenue_b = revenue_b.checked_add(fee).ok_or(Error::ArithmeticOverflow)?;
+            }
+            self.pair_fee_revenue.insert(key, &(revenue_a, revenue_b));
+            Ok(())
+        }
+
+        fn pair_key(token_a: TokenId, token_b: TokenId) -> (TokenId, TokenId) {
+            let (_, first, second) = Self::batch_key(0, token_a, token_b);
+            (first, second)
+        }
+
+        /// Batches of a pair are keyed with the lower token address first
+        fn batch_key(batch_id: BatchId, token_in: TokenId, token_out: TokenId) -> (BatchId, TokenId, TokenId) {
+            if token_in < token_out {
+                (batch_id, token_in, token_out)
+            } else {
+                (batch_id, token_out, token_in)
+            }
+        }
+
+        /// Price of token_a in token_b at which an ask (selling token_a) is willing to trade
+        fn ask_accepts(order: &Order, price: Balance) -> Result<bool, Error> {
+            let offered = order.amount_in.checked_mul(price).ok_or(Error::ArithmeticOverflow)?;
+            let required = order.min_amount_out.checked_mul(PRICE_PRECISION).ok_or(Error::ArithmeticOverflow)?;
+            Ok(offered >= required)
+        }
+
+        /// token_a a bid (selling token_b) can buy at `price`, zero if above its limit
+        fn bid_capacity(order: &Order, price: Balance) -> Result<Balance, Error> {
+            let budget = order.amount_in.checked_mul(PRICE_PRECISION).ok_or(Error::ArithmeticOverflow)?;
+            let required = order.min_amount_out.checked_mul(price).ok_or(Error::ArithmeticOverflow)?;
+            if budget < required {
+                return Ok(0);
+            }
+            Ok(budget / price)
+        }
+
+        /// Uniform price of token_a in token_b and the token_a volume it clears.
+        /// Candidates are the orders' own limit prices; the winner trades the most,
+        /// then leaves the smallest imbalance, then is the lowest.
+        fn clearing_price(asks: &[Order], bids: &[Order]) -> Result<Option<(Balance, Balance)>, Error> {
+            let mut candidates = Vec::with_capacity(asks.len() + bids.len());
+            for ask in asks {
+                let scaled = ask.min_amount_out.checked_mul(PRICE_PRECISION).ok_or(Error::ArithmeticOverflow)?;
+                candidates.push((scaled + ask.amount_in - 1) / ask.amount_in);
+            }
+            for bid in bids {
+                let scaled = bid.amount_in.checked_mul(PRICE_PRECISION).ok_or(Error::ArithmeticOverflow)?;
+                candidates.push(scaled / bid.min_amount_out);
+            }
+            
+            let mut best: Option<(Balance, Balance, Balance)> = None;
+            for price in candidates.into_iter().filter(|price| *price > 0) {
+                let mut supply: Balance = 0;
+                for ask in asks {
+                    if Self::ask_accepts(ask, price)? {
+                        supply = supply.checked_add(ask.remaining()).ok_or(Error::ArithmeticOverflow)?;
+                    }
+                }
+                let mut demand: Balance = 0;
+                for bid in bids {
+                    demand = demand.checked_add(Self::bid_capacity(bid, price)?).ok_or(Error::ArithmeticOverflow)?;
+                }
+                
+                let volume = supply.min(demand);
+                let imbalance = supply.max(demand) - volume;
+                let better = match best {
+                    None => true,
+                    Some((best_price, best_volume, best_imbalance)) => {
+                        (volume, core::cmp::Reverse(imbalance), core::cmp::Reverse(price))
+                            > (best_volume, core::cmp::Reverse(best_imbalance), core::cmp::Reverse(best_price))
+                    }
+                };
+                if better {
+                    best = Some((price, volume, imbalance));
+                }
+            }
+            
+            Ok(best.filter(|(_, volume, _)| *volume > 0).map(|(price, volume, _)| (price, volume)))
+        }
+
+        /// `(sold, bought)` for each participant at `price`, `volume` token_a changing
+        /// hands. The side offering more than `volume` is filled pro rata.
+        fn batch_fills(
+            participants: &[(OrderId, Order)],
+            token_a: TokenId,
+            price: Balance,
+            volume: Balance,
+        ) -> Result<Vec<(Balance, Balance)>, Error> {
+            // token_a each order can sell (asks) or buy (bids) at this price
+            let mut capacity = Vec::with_capacity(participants.len());
+            for (_, order) in participants {
+                let is_ask = order.token_in == token_a;
+                capacity.push(if is_ask {
+                    if Self::ask_accepts(order, price)? { order.remaining() } else { 0 }
+                } else {
+                    Self::bid_capacity(order, price)?
+                });
+            }
+            
+            let mut allocation = vec![0; participants.len()];
+            for is_ask in [true, false] {
+                let side: Vec<usize> = (0..participants.len())
+                    .filter(|i| (participants[*i].1.token_in == token_a) == is_ask)
+                    .collect();
+                let total: Balance = side.iter().map(|i| capacity[*i]).sum();
+                if total == 0 {
+                    continue;
+                }
+                let mut allocated = 0;
+                for i in &side {
+                    allocation[*i] = capacity[*i].checked_mul(volume).ok_or(Error::ArithmeticOverflow)? / total;
+                    allocated += allocation[*i];
+                }
+                // Units lost to rounding go one each, in commitment order
+                for i in &side {
+                    if allocated == volume {
+                        break;
+                    }
+                    if allocation[*i] < capacity[*i] {
+                        allocation[*i] += 1;
+                        allocated += 1;
+                    }
+                }
+            }
+            
+            let mut fills = Vec::with_capacity(participants.len());
+            for ((_, order), amount_a) in participants.iter().zip(allocation) {
+                let value = amount_a.checked_mul(price).ok_or(Error::ArithmeticOverflow)?;
+                fills.push(if order.token_in == token_a {
+                    (amount_a, value / PRICE_PRECISION)
+                } else {
+                    ((value + PRICE_PRECISION - 1) / PRICE_PRECISION, amount_a)
+                });
+            }
+            Ok(fills)
+        }
+
+        /// Take `amount` of `token` from the deposit of `owner` and pull whatever
+        /// the deposit does not cover from its wallet
+        fn escrow(&mut self, owner: AccountId, token: TokenId, amount: Balance) -> Result<(), Error> {
+            let deposited = self.balance_of(owner, token);
+            let from_deposit = deposited.min(amount);
+            if amount > from_deposit {
+                self.pull(token, owner, amount - from_deposit)?;
+            }
+            if from_deposit > 0 {
+                self.balances.insert((owner, token), &(deposited - from_deposit));
+            }
+            Ok(())
+        }
+
+        fn ensure_owner(&self) -> Result<(), Error> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotAuthorized);
+            }
+            Ok(())
+        }
+
+        fn fresh_token_price(&self, token_id: TokenId) -> Result<Balance, Error> {
+            let oracle = self.oracle.ok_or(Error::OracleUnavailable)?;
+            let feed_id = self.token_feeds.get(token_id)
+                .ok_or(Error::OracleUnavailable)?;
+            let data = self.feed_price(oracle, feed_id)
+                .ok_or(Error::OracleUnavailable)?;
+            
+            if data.staleness > self.max_oracle_staleness {
+                return Err(Error::StalePrice);
+            }
+            if data.price == 0 {
+                return Err(Error::OracleUnavailable);
+            }
+            Ok(data.price)
+        }
+
+        /// Price implied by an order: `min_amount_out` per `amount_in`
+        fn order_price(order: &Order) -> Result<Balance, Error> {
+            order.min_amount_out.checked_mul(PRICE_PRECISION)
+                .and_then(|scaled| scaled.checked_div(order.amount_in))
+                .ok_or(Error::InsufficientBalance)
+        }
+
+        fn order_triggered(&self, order: &Order) -> Result<bool, Error> {
+            match order.order_type {
+                OrderType::Market | OrderType::CrossChain => Ok(true),
+                // A limit can fill once the market pays at least the limit price
+                OrderType::Limit => {
+                    Ok(self.oracle_price(order.token_in, order.token_out)? >= Self::order_price(order)?)
+                }
+                // Stops activate once the market falls to the trigger price
+                OrderType::StopLoss | OrderType::StopLimit => {
+                    Ok(self.oracle_price(order.token_in, order.token_out)? <= order.trigger_price)
+                }
+                // Take-profits activate once the market rises to the trigger price
+                OrderType::TakeProfit => {
+                    Ok(self.oracle_price(order.token_in, order.token_out)? >= order.trigger_price)
+                }
+            }
+        }
+
+        #[cfg(not(test))]
+        fn feed_price(&self, oracle: OracleRef, feed_id: FeedId) -> Option<PriceData> {
+            oracle.latest_price(feed_id)
+        }
+
+        // Off-chain tests cannot call the oracle contract either
+        #[cfg(test)]
+        fn feed_price(&self, _oracle: OracleRef, feed_id: FeedId) -> Option<PriceData> {
+            tests::feed_price(feed_id)
+        }
+
+        // Helper functions for quantum-resistant operations
+        fn generate_quantum_order_id(&mut self, _creator: &AccountId) -> OrderId {
+            // Sequential IDs keep time priority explicit and cannot collide
+            let order_id = self.next_order_id;
+            self.next_order_id += 1;
+            order_id
+        }
+
+        /// Move an open order to a final status, refund its unfilled escrow and drop
+        /// it from the creator's open orders. The caller removes it from its book.
+        fn close_order(
+            &mut self,
+            order_id: OrderId,
+            order: &mut Order,
+            status: OrderStatus,
+        ) -> Result<(), Error> {
+            let refund = order.remaining() + order.bounty;
+            order.bounty = 0;
+            order.status = status;
+            self.orders.insert(order_id, &*order);
+            self.remove_user_order(order.creator, order_id);
+            self.push(order.token_in, order.creator, refund)?;
+            
+            match status {
+                OrderStatus::Cancelled => self.env().emit_event(OrderCancelled {
+                    order_id,
+                    creator: order.creator,
+                    refunded: refund,
+                }),
+                OrderStatus::Expired => self.env().emit_event(OrderExpired {
+                    order_id,
+                    creator: order.creator,
+                    refunded: refund,
+                }),
+                _ => {}
+            }
+            Ok(())
+        }
+
+        fn remove_user_order(&mut self, creator: AccountId, order_id: OrderId) {
+            if let Some(mut user_orders) = self.user_orders.get(creator) {
+                user_orders.retain(|id| *id != order_id);
+                if user_orders.is_empty() {
+                    self.user_orders.remove(creator);
+                } else {
+                    self.user_orders.insert(creator, &user_orders);
+                }
+            }
+        }
+
+        fn remove_from_book(&mut self, order_id: OrderId, order: &Order) {
+            let side = (order.token_in, order.token_out);
+            if let Some(mut book) = self.books.get(side) {
+                book.retain(|id| *id != order_id);
+                self.books.insert(side, &book);
+            }
+        }
+
+        fn generate_trade_id(&mut self) -> TradeId {
+            let trade_id = self.next_trade_id;
+            self.next_trade_id += 1;
+            trade_id
+        }
+
+        fn sign_order(&self, order: &Order) -> DilithiumSignature {
             // Implementation using Dilithium
         }
 
@@ -255,37 +1506,283 @@ mod zero_spread_dex {
 
         fn generate_bridge_proof(
             &self,
-            order: &Order,
-            bridge_info: &BridgeInfo,
+            message: &BridgeMessage,
+            _bridge_info: &BridgeInfo,
         ) -> Vec<u8> {
-            // Implementation using Kyber and Dilithium
+            // The hash of the encoded message is kept in the escrow, so relayers and
+            // the target chain can check a delivered message against this contract
+            message.encode()
+        }
+
+        fn set_bridge_paused(&mut self, chain_id: ChainId, paused: bool) -> Result<(), Error> {
+            let mut bridge_info = self.external_bridges.get(chain_id)
+                .ok_or(Error::BridgeNotFound)?;
+            bridge_info.paused = paused;
+            self.external_bridges.insert(chain_id, &bridge_info);
+            Ok(())
+        }
+
+        fn record_bridge_volume(&mut self, chain_id: ChainId, token: TokenId, amount: Balance) -> Result<(), Error> {
+            let (daily_cap, volume) = self.bridge_token_volume(chain_id, token)
+                .ok_or(Error::TokenNotSupported)?;
+            let volume = volume.checked_add(amount).ok_or(Error::ArithmeticOverflow)?;
+            if volume > daily_cap {
+                return Err(Error::DailyCapExceeded);
+            }
+            let today = self.env().block_timestamp() / BRIDGE_CAP_PERIOD;
+            self.bridge_daily_volume.insert((chain_id, token), &(today, volume));
+            Ok(())
+        }
+
+        fn hash_preimage(preimage: &[u8]) -> Hash {
+            let mut output = [0u8; 32];
+            ink_env::hash_bytes::<ink_env::hash::Blake2x256>(preimage, &mut output);
+            Hash::from(output)
+        }
+
+        /// Match `order` against the opposite side of the book at maker prices,
+        /// best price first and oldest first within a price
+        fn match_order(&mut self, order_id: OrderId, mut order: Order) -> Result<Order, Error> {
+            let opposite = (order.token_out, order.token_in);
+            let book = self.books.get(opposite).unwrap_or_default();
+            let now = self.env().block_number();
+            
+            let mut remaining_book = Vec::with_capacity(book.len());
+            let mut exhausted = false;
+            
+            for maker_id in book {
+                if exhausted || !order.is_open() {
+                    remaining_book.push(maker_id);
+                    continue;
+                }
+                let mut maker = match self.orders.get(maker_id) {
+                    Some(maker) => maker,
+                    None => continue,
+                };
+                // Expired makers are refunded as they are reached
+                if maker.expiration < now {
+                    self.close_order(maker_id, &mut maker, OrderStatus::Expired)?;
+                    continue;
+                }
+                
+                match Self::calculate_trade_amounts(&maker, &order)? {
+                    Some((maker_gives, taker_gives)) => {
+                        self.execute_atomic_swap(
+                            maker_id,
+                            &mut maker,
+                            order_id,
+                            &mut order,
+                            maker_gives,
+                            taker_gives,
+                        )?;
+                        if maker.is_open() {
+                            remaining_book.push(maker_id);
+                        }
+                    }
+                    // The book is sorted, nothing further down crosses either
+                    None => {
+                        exhausted = true;
+                        remaining_book.push(maker_id);
+                    }
+                }
+            }
+            
+            self.books.insert(opposite, &remaining_book);
+            self.orders.insert(order_id, &order);
+            Ok(order)
         }
 
         // MEV protection functions
+        /// Insert a resting order into its book behind every order with the same or better price
         fn insert_order_protected(
             &mut self,
             order_id: OrderId,
             order: Order,
         ) -> Result<(), Error> {
-            // Implementation with commitment scheme
+            let side = (order.token_in, order.token_out);
+            let mut book = self.books.get(side).unwrap_or_default();
+            
+            let mut position = book.len();
+            for (index, resting_id) in book.iter().enumerate() {
+                let resting = self.orders.get(resting_id).ok_or(Error::OrderNotFound)?;
+                if Self::better_price(&order, &resting)? {
+                    position = index;
+                    break;
+                }
+            }
+            
+            if book.len() >= MAX_BOOK_DEPTH {
+                // Only a better order than the worst may displace it; the worst is refunded
+                if position == book.len() {
+                    return Err(Error::OrderBookFull);
+                }
+                let evicted_id = book.pop().ok_or(Error::OrderBookFull)?;
+                let mut evicted = self.orders.get(evicted_id).ok_or(Error::OrderNotFound)?;
+                self.close_order(evicted_id, &mut evicted, OrderStatus::Cancelled)?;
+            }
+            
+            book.insert(position, order_id);
+            self.books.insert(side, &book);
+            Ok(())
+        }
+
+        /// Whether `a` asks strictly less `token_out` per `token_in` than `b`
+        fn better_price(a: &Order, b: &Order) -> Result<bool, Error> {
+            let lhs = a.min_amount_out.checked_mul(b.amount_in).ok_or(Error::ArithmeticOverflow)?;
+            let rhs = b.min_amount_out.checked_mul(a.amount_in).ok_or(Error::ArithmeticOverflow)?;
+            Ok(lhs < rhs)
         }
 
+        /// Amounts for one fill at the maker's price: `(maker_gives, taker_gives)`.
+        /// The maker is paid rounded up and the taker rounded down, so neither side
+        /// ever trades below the ratio of its own `min_amount_out` to `amount_in`.
+        /// Returns `None` when the orders do not cross.
         fn calculate_trade_amounts(
-            &self,
             maker_order: &Order,
             taker_order: &Order,
-        ) -> Result<(Balance, Balance), Error> {
-            // Implementation with MEV protection
+        ) -> Result<Option<(Balance, Balance)>, Error> {
+            let taker_remaining = taker_order.remaining();
+            
+            // Most of the maker's token the taker's remaining input can buy
+            let affordable = taker_remaining
+                .checked_mul(maker_order.amount_in)
+                .ok_or(Error::ArithmeticOverflow)?
+                / maker_order.min_amount_out;
+            let maker_gives = maker_order.remaining().min(affordable);
+            if maker_gives == 0 {
+                return Ok(None);
+            }
+            
+            let taker_gives = maker_gives
+                .checked_mul(maker_order.min_amount_out)
+                .ok_or(Error::ArithmeticOverflow)?
+                .checked_add(maker_order.amount_in - 1)
+                .ok_or(Error::ArithmeticOverflow)?
+                / maker_order.amount_in;
+            if taker_gives > taker_remaining {
+                return Ok(None);
+            }
+            
+            // The taker's limit
+            let received = maker_gives
+                .checked_mul(taker_order.amount_in)
+                .ok_or(Error::ArithmeticOverflow)?;
+            let required = taker_gives
+                .checked_mul(taker_order.min_amount_out)
+                .ok_or(Error::ArithmeticOverflow)?;
+            if received < required {
+                return Ok(None);
+            }
+            
+            Ok(Some((maker_gives, taker_gives)))
         }
 
+        /// Settle one fill out of escrow and record the trade
         fn execute_atomic_swap(
             &mut self,
-            maker_order: &Order,
-            taker_order: &Order,
-            amount: Balance,
-            price: Balance,
-        ) -> Result<(), Error> {
-            // Implementation with atomic execution
+            maker_order_id: OrderId,
+            maker_order: &mut Order,
+            taker_order_id: OrderId,
+            taker_order: &mut Order,
+            maker_gives: Balance,
+            taker_gives: Balance,
+        ) -> Result<TradeId, Error> {
+            self.verify_order_signatures(maker_order, taker_order)?;
+            
+            maker_order.filled_in += maker_gives;
+            maker_order.filled_out += taker_gives;
+            taker_order.filled_in += taker_gives;
+            taker_order.filled_out += maker_gives;
+            for (order_id, order) in [(maker_order_id, &mut *maker_order), (taker_order_id, &mut *taker_order)] {
+                order.status = if order.remaining() == 0 {
+                    OrderStatus::Filled
+                } else {
+                    OrderStatus::PartiallyFilled
+                };
+                self.orders.insert(order_id, &*order);
+                if order.status == OrderStatus::Filled {
+                    self.remove_user_order(order.creator, order_id);
+                }
+            }
+            
+            // Both sides are already escrowed by this contract; each pays its fee
+            // out of what it receives
+            let maker_rate = self.maker_fee_rate();
+            let maker_fee = self.charge_fee(taker_order.token_in, maker_order.token_in, taker_gives, maker_rate)?;
+            let taker_fee = self.charge_fee(maker_order.token_in, taker_order.token_in, maker_gives, self.fee_rate - maker_rate)?;
+            self.push(maker_order.token_in, taker_order.creator, maker_gives - taker_fee)?;
+            self.push(taker_order.token_in, maker_order.creator, taker_gives - maker_fee)?;
+            
+            // Price of the maker's token in the taker's token
+            let price = taker_gives
+                .checked_mul(PRICE_PRECISION)
+                .ok_or(Error::ArithmeticOverflow)?
+                / maker_gives;
+            
+            let trade_id = self.generate_trade_id();
+            let quantum_proof = self.generate_trade_proof(
+                maker_order,
+                taker_order,
+                maker_gives,
+                price
+            );
+            
+            let trade = Trade {
+                maker_order: maker_order_id,
+                taker_order: taker_order_id,
+                amount: maker_gives,
+                price,
+                maker_fee,
+                taker_fee,
+                timestamp: self.env().block_timestamp(),
+                quantum_proof,
+            };
+            self.trades.insert(trade_id, &trade);
+
+            self.env().emit_event(TradeExecuted {
+                trade_id,
+                maker: maker_order.creator,
+                taker: taker_order.creator,
+                base_token: maker_order.token_in,
+                quote_token: taker_order.token_in,
+                amount: maker_gives,
+                price,
+                maker_fee,
+                taker_fee,
+            });
+
+            Ok(trade_id)
+        }
+
+        // Token movements
+        #[cfg(not(test))]
+        fn pull(&self, token: TokenId, from: AccountId, amount: Balance) -> Result<(), Error> {
+            if amount == 0 {
+                return Ok(());
+            }
+            PSP22Ref::transfer_from(&token, from, self.env().account_id(), amount, Vec::new())
+                .map_err(|_| Error::TransferFailed)
+        }
+
+        #[cfg(not(test))]
+        fn push(&self, token: TokenId, to: AccountId, amount: Balance) -> Result<(), Error> {
+            if amount == 0 {
+                return Ok(());
+            }
+            PSP22Ref::transfer(&token, to, amount, Vec::new())
+                .map_err(|_| Error::TransferFailed)
+        }
+
+        #[cfg(test)]
+        fn pull(&self, token: TokenId, from: AccountId, amount: Balance) -> Result<(), Error> {
+            test_ledger::transfer(token, from, self.env().account_id(), amount)
+                .map_err(|_| Error::InsufficientBalance)
+        }
+
+        #[cfg(test)]
+        fn push(&self, token: TokenId, to: AccountId, amount: Balance) -> Result<(), Error> {
+            test_ledger::transfer(token, self.env().account_id(), to, amount)
+                .map_err(|_| Error::InsufficientBalance)
         }
     }
 
@@ -301,6 +1798,53 @@ mod zero_spread_dex {
         amount_in: Balance,
     }
 
+    #[ink(event)]
+    pub struct OrderCommitted {
+        #[ink(topic)]
+        commitment: Hash,
+        #[ink(topic)]
+        owner: AccountId,
+        batch_id: BatchId,
+    }
+
+    #[ink(event)]
+    pub struct BatchCleared {
+        #[ink(topic)]
+        batch_id: BatchId,
+        token_a: TokenId,
+        token_b: TokenId,
+        clearing_price: Balance,
+        volume: Balance,
+    }
+
+    #[ink(event)]
+    pub struct OrderCancelled {
+        #[ink(topic)]
+        order_id: OrderId,
+        #[ink(topic)]
+        creator: AccountId,
+        refunded: Balance,
+    }
+
+    #[ink(event)]
+    pub struct OrderExpired {
+        #[ink(topic)]
+        order_id: OrderId,
+        #[ink(topic)]
+        creator: AccountId,
+        refunded: Balance,
+    }
+
+    #[ink(event)]
+    pub struct OrderTriggered {
+        #[ink(topic)]
+        order_id: OrderId,
+        #[ink(topic)]
+        keeper: AccountId,
+        bounty: Balance,
+        oracle_price: Balance,
+    }
+
     #[ink(event)]
     pub struct TradeExecuted {
         #[ink(topic)]
@@ -309,8 +1853,27 @@ mod zero_spread_dex {
         maker: AccountId,
         #[ink(topic)]
         taker: AccountId,
+        base_token: TokenId,
+        quote_token: TokenId,
         amount: Balance,
         price: Balance,
+        maker_fee: Balance,
+        taker_fee: Balance,
+    }
+
+    #[ink(event)]
+    pub struct FeeRateUpdated {
+        fee_rate: Balance,
+        maker_fee_share: Balance,
+    }
+
+    #[ink(event)]
+    pub struct FeesWithdrawn {
+        #[ink(topic)]
+        token: TokenId,
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
     }
 
     #[ink(event)]
@@ -321,6 +1884,79 @@ mod zero_spread_dex {
         bridge_proof: Vec<u8>,
     }
 
+    #[ink(event)]
+    pub struct BridgeAdded {
+        #[ink(topic)]
+        chain_id: ChainId,
+    }
+
+    #[ink(event)]
+    pub struct BridgeUpdated {
+        #[ink(topic)]
+        chain_id: ChainId,
+    }
+
+    #[ink(event)]
+    pub struct BridgeRemoved {
+        #[ink(topic)]
+        chain_id: ChainId,
+    }
+
+    #[ink(event)]
+    pub struct BridgeTokenAllowed {
+        #[ink(topic)]
+        chain_id: ChainId,
+        #[ink(topic)]
+        token: TokenId,
+        daily_cap: Balance,
+    }
+
+    #[ink(event)]
+    pub struct BridgeTokenDisallowed {
+        #[ink(topic)]
+        chain_id: ChainId,
+        #[ink(topic)]
+        token: TokenId,
+    }
+
+    #[ink(event)]
+    pub struct BridgePaused {
+        #[ink(topic)]
+        chain_id: ChainId,
+        by: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct BridgeUnpaused {
+        #[ink(topic)]
+        chain_id: ChainId,
+    }
+
+    #[ink(event)]
+    pub struct BridgeCompleted {
+        #[ink(topic)]
+        order_id: OrderId,
+        #[ink(topic)]
+        counterparty: AccountId,
+        preimage: Vec<u8>,
+    }
+
+    #[ink(event)]
+    pub struct Deposited {
+        #[ink(topic)]
+        owner: AccountId,
+        token: TokenId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        owner: AccountId,
+        token: TokenId,
+        amount: Balance,
+    }
+
     // Error types
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
@@ -332,5 +1968,938 @@ mod zero_spread_dex {
         BridgeNotFound,
         TokenNotSupported,
         MEVDetected,
+        NotAuthorized,
+        OracleUnavailable,
+        StalePrice,
+        OrderNotTriggered,
+        InvalidOrder,
+        OrderBookFull,
+        TransferFailed,
+        ArithmeticOverflow,
+        InvalidExpiry,
+        OrderExpired,
+        CommitmentNotFound,
+        BatchInProgress,
+        InvalidFeeRate,
+        InvalidPreimage,
+        BridgeLocked,
+        BridgeAlreadyExists,
+        BridgePaused,
+        DailyCapExceeded,
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink_lang as ink;
+        use oracle_consumer::test_ledger::{balance, mint, supply};
+        use std::cell::RefCell;
+        use std::collections::BTreeMap;
+
+        thread_local! {
+            // Latest oracle price by feed
+            static FEEDS: RefCell<BTreeMap<FeedId, PriceData>> = RefCell::new(BTreeMap::new());
+        }
+
+        pub(super) fn feed_price(feed_id: FeedId) -> Option<PriceData> {
+            FEEDS.with(|feeds| feeds.borrow().get(&feed_id).copied())
+        }
+
+        fn set_feed_price(feed_id: FeedId, price: Balance, staleness: Timestamp) {
+            let data = PriceData { price, confidence: 100, timestamp: 0, staleness };
+            FEEDS.with(|feeds| feeds.borrow_mut().insert(feed_id, data));
+        }
+
+        const NRSH: [u8; 32] = [0x10; 32];
+        const ELXR: [u8; 32] = [0x20; 32];
+        const DEX: [u8; 32] = [0xdd; 32];
+
+        fn accounts() -> ink_env::test::DefaultAccounts<ink_env::DefaultEnvironment> {
+            ink_env::test::default_accounts::<ink_env::DefaultEnvironment>()
+        }
+
+        fn set_caller(account: AccountId) {
+            ink_env::test::set_caller::<ink_env::DefaultEnvironment>(account);
+        }
+
+        fn setup() -> ZeroSpreadDEX {
+            ink_env::test::set_callee::<ink_env::DefaultEnvironment>(AccountId::from(DEX));
+            set_caller(accounts().alice);
+            ZeroSpreadDEX::new(accounts().alice)
+        }
+
+        fn fund(accounts: &[AccountId]) {
+            for account in accounts {
+                mint(AccountId::from(NRSH), *account, 1_000_000);
+                mint(AccountId::from(ELXR), *account, 1_000_000);
+            }
+        }
+
+        #[ink::test]
+        fn matches_best_price_then_oldest() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob, acc.charlie, acc.django, acc.eve]);
+
+            // Three asks for 100 NRSH: bob at 5, charlie at 4, django at 4
+            set_caller(acc.bob);
+            let bob = dex.create_order(nrsh, elxr, 100, 500, OrderType::Limit, None).unwrap();
+            set_caller(acc.charlie);
+            let charlie = dex.create_order(nrsh, elxr, 100, 400, OrderType::Limit, None).unwrap();
+            set_caller(acc.django);
+            let django = dex.create_order(nrsh, elxr, 100, 400, OrderType::Limit, None).unwrap();
+            assert_eq!(dex.get_order_book(nrsh, elxr), vec![charlie, django, bob]);
+
+            // Eve sells 600 ELXR for at least 120 NRSH, a price of up to 5 ELXR each
+            set_caller(acc.eve);
+            let eve = dex.create_order(elxr, nrsh, 600, 120, OrderType::Limit, None).unwrap();
+
+            // Charlie fills fully, then django half, both at their own price of 4
+            assert_eq!(dex.get_order(charlie).unwrap().filled_out, 400);
+            assert_eq!(dex.get_order(django).unwrap().filled_in, 50);
+            assert_eq!(dex.get_order(django).unwrap().filled_out, 200);
+            assert_eq!(dex.get_order(bob).unwrap().filled_in, 0);
+            assert_eq!(dex.get_order_book(nrsh, elxr), vec![django, bob]);
+
+            // Eve is filled completely and gets more than her limit
+            let eve_order = dex.get_order(eve).unwrap();
+            assert_eq!((eve_order.filled_in, eve_order.filled_out), (600, 150));
+            assert_eq!(balance(nrsh, acc.eve), 1_000_150);
+            assert_eq!(balance(elxr, acc.eve), 1_000_000 - 600);
+            assert!(dex.get_order_book(elxr, nrsh).is_empty());
+            assert_eq!(dex.get_trade(0).unwrap().price, 4 * PRICE_PRECISION);
+        }
+
+        #[ink::test]
+        fn orders_that_do_not_cross_rest() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob, acc.charlie]);
+
+            set_caller(acc.bob);
+            let ask = dex.create_order(nrsh, elxr, 100, 500, OrderType::Limit, None).unwrap();
+            // Bid at 4 is below the ask at 5
+            set_caller(acc.charlie);
+            let bid = dex.create_order(elxr, nrsh, 400, 100, OrderType::Limit, None).unwrap();
+
+            assert_eq!(dex.get_order(ask).unwrap().filled_in, 0);
+            assert_eq!(dex.get_order(bid).unwrap().filled_in, 0);
+            assert_eq!(balance(nrsh, AccountId::from(DEX)), 100);
+            assert_eq!(balance(elxr, AccountId::from(DEX)), 400);
+        }
+
+        #[ink::test]
+        fn market_orders_refund_the_unfilled_remainder() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob, acc.charlie]);
+
+            set_caller(acc.bob);
+            dex.create_order(nrsh, elxr, 100, 300, OrderType::Limit, None).unwrap();
+            set_caller(acc.charlie);
+            let market = dex.create_order(elxr, nrsh, 1_000, 0, OrderType::Market, None).unwrap();
+
+            let order = dex.get_order(market).unwrap();
+            assert_eq!((order.filled_in, order.filled_out), (300, 100));
+            assert_eq!(order.status, OrderStatus::Cancelled);
+            assert!(dex.get_user_orders(acc.charlie).is_empty());
+            assert_eq!(balance(elxr, acc.charlie), 1_000_000 - 300);
+            assert!(dex.get_order_book(elxr, nrsh).is_empty());
+            assert_eq!(balance(elxr, AccountId::from(DEX)), 0);
+        }
+
+        #[ink::test]
+        fn orders_draw_on_deposits_before_the_wallet() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob]);
+
+            set_caller(acc.bob);
+            dex.deposit(nrsh, 600).unwrap();
+            assert_eq!(dex.balance_of(acc.bob, nrsh), 600);
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000 - 600);
+            assert_eq!(balance(nrsh, AccountId::from(DEX)), 600);
+
+            // 600 of the escrow comes from the deposit, 200 from the wallet
+            let order = dex.create_order(nrsh, elxr, 800, 1_600, OrderType::Limit, None).unwrap();
+            assert_eq!(dex.balance_of(acc.bob, nrsh), 0);
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000 - 800);
+            assert_eq!(balance(nrsh, AccountId::from(DEX)), 800);
+
+            // Refunds go back to the wallet
+            dex.cancel_order(order).unwrap();
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000);
+
+            dex.deposit(elxr, 50).unwrap();
+            assert_eq!(dex.withdraw(elxr, 51), Err(Error::InsufficientBalance));
+            dex.withdraw(elxr, 20).unwrap();
+            assert_eq!(dex.balance_of(acc.bob, elxr), 30);
+            assert_eq!(balance(elxr, acc.bob), 1_000_000 - 30);
+
+            // A deposit fails as a whole if the wallet cannot cover it
+            assert_eq!(dex.deposit(elxr, 2_000_000), Err(Error::InsufficientBalance));
+            assert_eq!(dex.balance_of(acc.bob, elxr), 30);
+        }
+
+        #[ink::test]
+        fn rejects_orders_without_a_price() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            fund(&[accounts().alice]);
+
+            assert_eq!(dex.create_order(nrsh, elxr, 100, 0, OrderType::Limit, None), Err(Error::InvalidOrder));
+            assert_eq!(dex.create_order(nrsh, nrsh, 100, 100, OrderType::Limit, None), Err(Error::InvalidOrder));
+            assert_eq!(dex.create_order(nrsh, elxr, 0, 100, OrderType::Limit, None), Err(Error::InvalidOrder));
+        }
+
+        #[ink::test]
+        fn cancel_refunds_the_unfilled_part() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob, acc.charlie]);
+
+            set_caller(acc.bob);
+            let ask = dex.create_order(nrsh, elxr, 100, 400, OrderType::Limit, None).unwrap();
+            set_caller(acc.charlie);
+            dex.create_order(elxr, nrsh, 160, 40, OrderType::Limit, None).unwrap();
+
+            let order = dex.get_order(ask).unwrap();
+            assert_eq!(order.status, OrderStatus::PartiallyFilled);
+            assert_eq!(order.filled_in, 40);
+            assert_eq!(dex.get_user_orders(acc.bob), vec![ask]);
+            // Charlie's order filled completely and is no longer listed
+            assert!(dex.get_user_orders(acc.charlie).is_empty());
+
+            // Only the creator can cancel
+            assert_eq!(dex.cancel_order(ask), Err(Error::NotAuthorized));
+            set_caller(acc.bob);
+            dex.cancel_order(ask).unwrap();
+
+            assert_eq!(dex.get_order(ask).unwrap().status, OrderStatus::Cancelled);
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000 - 40);
+            assert_eq!(balance(elxr, acc.bob), 1_000_000 + 160);
+            assert!(dex.get_order_book(nrsh, elxr).is_empty());
+            assert!(dex.get_user_orders(acc.bob).is_empty());
+            assert_eq!(dex.cancel_order(ask), Err(Error::InvalidOrder));
+        }
+
+        #[ink::test]
+        fn expired_orders_are_swept_and_refunded() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob, acc.charlie]);
+
+            assert_eq!(
+                dex.create_order(nrsh, elxr, 100, 400, OrderType::Limit, Some(0)),
+                Err(Error::InvalidExpiry)
+            );
+            assert_eq!(
+                dex.create_order(nrsh, elxr, 100, 400, OrderType::Limit, Some(MAX_ORDER_LIFETIME + 1)),
+                Err(Error::InvalidExpiry)
+            );
+
+            set_caller(acc.bob);
+            let short = dex.create_order(nrsh, elxr, 100, 400, OrderType::Limit, Some(2)).unwrap();
+            let long = dex.create_order(nrsh, elxr, 100, 500, OrderType::Limit, Some(10)).unwrap();
+
+            for _ in 0..3 {
+                ink_env::test::advance_block::<ink_env::DefaultEnvironment>();
+            }
+
+            // Anyone may sweep; orders that have not expired are left alone
+            set_caller(acc.charlie);
+            assert_eq!(dex.sweep_expired(vec![short, long, 99]), Ok(1));
+            assert_eq!(dex.get_order(short).unwrap().status, OrderStatus::Expired);
+            assert_eq!(dex.get_order(long).unwrap().status, OrderStatus::Open);
+            assert_eq!(dex.get_order_book(nrsh, elxr), vec![long]);
+            assert_eq!(dex.get_user_orders(acc.bob), vec![long]);
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000 - 100);
+            assert_eq!(dex.sweep_expired(vec![short]), Ok(0));
+        }
+
+        #[ink::test]
+        fn fees_are_charged_per_fill_and_withdrawn_by_the_collector() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob, acc.charlie]);
+
+            assert_eq!(dex.set_fee_rate(MAX_FEE_RATE + 1, 0), Err(Error::InvalidFeeRate));
+            // 1% split a quarter to makers and three quarters to takers
+            dex.set_fee_rate(10_000, 250_000).unwrap();
+            assert_eq!(dex.fee_rates(), (2_500, 7_500));
+
+            set_caller(acc.bob);
+            assert_eq!(dex.set_fee_rate(0, 0), Err(Error::NotAuthorized));
+            dex.create_order(nrsh, elxr, 10_000, 40_000, OrderType::Limit, None).unwrap();
+            set_caller(acc.charlie);
+            dex.create_order(elxr, nrsh, 40_000, 10_000, OrderType::Limit, None).unwrap();
+
+            // Fees come out of what each side receives; limits apply before fees
+            assert_eq!(balance(elxr, acc.bob), 1_000_000 + 40_000 - 100);
+            assert_eq!(balance(nrsh, acc.charlie), 1_000_000 + 10_000 - 75);
+            let trade = dex.get_trade(0).unwrap();
+            assert_eq!((trade.maker_fee, trade.taker_fee), (100, 75));
+
+            assert_eq!(dex.collected_fees(elxr), 100);
+            assert_eq!(dex.collected_fees(nrsh), 75);
+            assert_eq!(dex.fee_revenue(nrsh, elxr), (75, 100));
+            assert_eq!(dex.fee_revenue(elxr, nrsh), (100, 75));
+
+            assert_eq!(dex.withdraw_fees(elxr, 100), Err(Error::NotAuthorized));
+            set_caller(acc.alice);
+            assert_eq!(dex.withdraw_fees(elxr, 101), Err(Error::InsufficientBalance));
+            dex.withdraw_fees(elxr, 100).unwrap();
+            assert_eq!(balance(elxr, acc.alice), 100);
+            assert_eq!(dex.collected_fees(elxr), 0);
+            // Revenue is all-time and survives withdrawal
+            assert_eq!(dex.fee_revenue(nrsh, elxr), (75, 100));
+            assert_eq!(balance(elxr, AccountId::from(DEX)), 0);
+        }
+
+        type Event = <ZeroSpreadDEX as ::ink_lang::reflect::ContractEventBase>::Type;
+
+        const REMOTE_CHAIN: ChainId = 2_000;
+
+        /// Register the remote chain's bridge as the owner, with uncapped tokens
+        fn add_bridge(dex: &mut ZeroSpreadDEX, supported_tokens: Vec<TokenId>) {
+            let caller = ink_env::caller::<ink_env::DefaultEnvironment>();
+            set_caller(accounts().alice);
+            if dex.get_bridge(REMOTE_CHAIN).is_none() {
+                dex.add_bridge(REMOTE_CHAIN, b"remote-htlc".to_vec(), [0u8; KYBER_PUBLICKEYBYTES]).unwrap();
+            }
+            for token in supported_tokens {
+                dex.allow_bridge_token(REMOTE_CHAIN, token, Balance::MAX).unwrap();
+            }
+            set_caller(caller);
+        }
+
+        /// Stands in for relayers and the HTLC contract on the target chain
+        #[derive(Default)]
+        struct LocalRelayer {
+            // Remote balances by recipient address
+            remote_balances: BTreeMap<Vec<u8>, Balance>,
+            // Remote locks by hashlock: (recipient, amount)
+            remote_locks: BTreeMap<Hash, (Vec<u8>, Balance)>,
+            // Preimages revealed by remote claims
+            revealed: BTreeMap<Hash, Vec<u8>>,
+        }
+
+        impl LocalRelayer {
+            /// Pick up the last `OrderBridged` event, check it against the escrow
+            /// and have the counterparty lock `min_amount_out` on the remote chain
+            fn deliver(&mut self, dex: &ZeroSpreadDEX) -> BridgeMessage {
+                let bridge_proof = ink_env::test::recorded_events()
+                    .filter_map(|event| match <Event as scale::Decode>::decode(&mut &event.data[..]) {
+                        Ok(Event::OrderBridged(bridged)) => Some(bridged.bridge_proof),
+                        _ => None,
+                    })
+                    .last()
+                    .expect("order was bridged");
+                let message = <BridgeMessage as scale::Decode>::decode(&mut &bridge_proof[..]).unwrap();
+                let escrow = dex.get_bridge_escrow(message.order_id).unwrap();
+                assert_eq!(escrow.message_hash, ZeroSpreadDEX::hash_preimage(&bridge_proof));
+
+                self.remote_locks.insert(message.hashlock, (message.recipient.clone(), message.min_amount_out));
+                message
+            }
+
+            /// The creator claims on the remote chain, revealing the preimage
+            fn claim_remote(&mut self, preimage: &[u8]) {
+                let hashlock = ZeroSpreadDEX::hash_preimage(preimage);
+                let (recipient, amount) = self.remote_locks.remove(&hashlock).expect("locked remotely");
+                *self.remote_balances.entry(recipient).or_insert(0) += amount;
+                self.revealed.insert(hashlock, preimage.to_vec());
+            }
+
+            fn relay_preimage(&self, dex: &mut ZeroSpreadDEX, message: &BridgeMessage) -> Result<(), Error> {
+                let preimage = self.revealed.get(&message.hashlock).expect("claimed remotely");
+                dex.complete_bridge(message.order_id, preimage.clone())
+            }
+        }
+
+        #[ink::test]
+        fn bridged_order_is_released_with_the_preimage() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob]);
+            add_bridge(&mut dex, vec![elxr]);
+            let mut relayer = LocalRelayer::default();
+
+            set_caller(acc.bob);
+            let order_id = dex.create_order(nrsh, elxr, 10_000, 40_000, OrderType::CrossChain, None).unwrap();
+            let preimage = b"bob's secret".to_vec();
+            let hashlock = ZeroSpreadDEX::hash_preimage(&preimage);
+
+            set_caller(acc.charlie);
+            assert_eq!(
+                dex.bridge_order(order_id, REMOTE_CHAIN, b"bob".to_vec(), acc.django, hashlock, 100),
+                Err(Error::NotAuthorized)
+            );
+            set_caller(acc.bob);
+            assert_eq!(
+                dex.bridge_order(order_id, REMOTE_CHAIN, b"bob".to_vec(), acc.django, hashlock, 1),
+                Err(Error::InvalidExpiry)
+            );
+            dex.bridge_order(order_id, REMOTE_CHAIN, b"bob".to_vec(), acc.django, hashlock, 100).unwrap();
+            assert_eq!(dex.get_order(order_id).unwrap().status, OrderStatus::Bridged);
+
+            // Locked escrow can no longer be cancelled, swept or bridged again
+            assert_eq!(dex.cancel_order(order_id), Err(Error::InvalidOrder));
+            assert_eq!(dex.sweep_expired(vec![order_id]), Ok(0));
+            assert_eq!(
+                dex.bridge_order(order_id, REMOTE_CHAIN, b"bob".to_vec(), acc.django, hashlock, 100),
+                Err(Error::InvalidOrder)
+            );
+
+            let message = relayer.deliver(&dex);
+            assert_eq!((message.amount_in, message.min_amount_out), (10_000, 40_000));
+            assert_eq!(dex.complete_bridge(order_id, b"guess".to_vec()), Err(Error::InvalidPreimage));
+
+            relayer.claim_remote(&preimage);
+            assert_eq!(relayer.remote_balances.get(&b"bob".to_vec()), Some(&40_000));
+            set_caller(acc.eve);
+            relayer.relay_preimage(&mut dex, &message).unwrap();
+
+            // Django receives the escrow less the taker fee
+            assert_eq!(balance(nrsh, acc.django), 10_000 - 3);
+            let order = dex.get_order(order_id).unwrap();
+            assert_eq!(order.status, OrderStatus::Filled);
+            assert_eq!(order.filled_out, 40_000);
+            assert!(dex.get_bridge_escrow(order_id).is_none());
+            assert_eq!(relayer.relay_preimage(&mut dex, &message), Err(Error::OrderNotFound));
+        }
+
+        #[ink::test]
+        fn bridged_order_is_refunded_after_the_timeout() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob]);
+            add_bridge(&mut dex, vec![nrsh]);
+
+            set_caller(acc.bob);
+            let order_id = dex.create_order(nrsh, elxr, 10_000, 40_000, OrderType::CrossChain, None).unwrap();
+            let preimage = b"bob's secret".to_vec();
+            let hashlock = ZeroSpreadDEX::hash_preimage(&preimage);
+            assert_eq!(
+                dex.bridge_order(order_id, REMOTE_CHAIN, b"bob".to_vec(), acc.django, hashlock, 50),
+                Err(Error::TokenNotSupported)
+            );
+            add_bridge(&mut dex, vec![elxr]);
+            dex.bridge_order(order_id, REMOTE_CHAIN, b"bob".to_vec(), acc.django, hashlock, 50).unwrap();
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000 - 10_000);
+
+            // Nobody filled it remotely
+            advance_blocks(50);
+            assert_eq!(dex.refund_bridge(order_id), Err(Error::BridgeLocked));
+            advance_blocks(1);
+            assert_eq!(dex.complete_bridge(order_id, preimage), Err(Error::OrderExpired));
+
+            set_caller(acc.eve);
+            dex.refund_bridge(order_id).unwrap();
+            assert_eq!(dex.get_order(order_id).unwrap().status, OrderStatus::Expired);
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000);
+            assert_eq!(balance(nrsh, AccountId::from(DEX)), 0);
+            assert_eq!(dex.refund_bridge(order_id), Err(Error::OrderNotFound));
+        }
+
+        #[ink::test]
+        fn expired_orders_cannot_be_bridged() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob]);
+            add_bridge(&mut dex, vec![elxr]);
+
+            set_caller(acc.bob);
+            let order_id = dex.create_order(nrsh, elxr, 10_000, 40_000, OrderType::CrossChain, Some(10)).unwrap();
+            let hashlock = ZeroSpreadDEX::hash_preimage(b"bob's secret");
+            advance_blocks(11);
+            assert_eq!(
+                dex.bridge_order(order_id, REMOTE_CHAIN, b"bob".to_vec(), acc.django, hashlock, 100),
+                Err(Error::OrderExpired)
+            );
+
+            // It is still swept and refunded as usual
+            assert_eq!(dex.sweep_expired(vec![order_id]), Ok(1));
+            assert_eq!(dex.get_order(order_id).unwrap().status, OrderStatus::Expired);
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000);
+        }
+
+        #[ink::test]
+        fn bridges_are_managed_by_governance() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            let verifier = [0u8; KYBER_PUBLICKEYBYTES];
+
+            dex.add_bridge(REMOTE_CHAIN, b"remote-htlc".to_vec(), verifier).unwrap();
+            assert_eq!(
+                dex.add_bridge(REMOTE_CHAIN, b"remote-htlc".to_vec(), verifier),
+                Err(Error::BridgeAlreadyExists)
+            );
+            dex.update_bridge(REMOTE_CHAIN, b"remote-htlc-v2".to_vec(), verifier).unwrap();
+            assert_eq!(dex.get_bridge(REMOTE_CHAIN).unwrap().bridge_contract, b"remote-htlc-v2".to_vec());
+            dex.allow_bridge_token(REMOTE_CHAIN, elxr, 1_000).unwrap();
+            assert_eq!(dex.bridge_token_volume(REMOTE_CHAIN, elxr), Some((1_000, 0)));
+
+            set_caller(acc.bob);
+            assert_eq!(dex.allow_bridge_token(REMOTE_CHAIN, nrsh, 1_000), Err(Error::NotAuthorized));
+            assert_eq!(dex.remove_bridge(REMOTE_CHAIN), Err(Error::NotAuthorized));
+            assert_eq!(dex.pause_bridge(REMOTE_CHAIN), Err(Error::NotAuthorized));
+
+            set_caller(acc.alice);
+            dex.disallow_bridge_token(REMOTE_CHAIN, elxr).unwrap();
+            assert!(dex.get_bridge(REMOTE_CHAIN).unwrap().supported_tokens.is_empty());
+            assert_eq!(dex.bridge_token_volume(REMOTE_CHAIN, elxr), None);
+            assert_eq!(dex.disallow_bridge_token(REMOTE_CHAIN, elxr), Err(Error::TokenNotSupported));
+
+            dex.remove_bridge(REMOTE_CHAIN).unwrap();
+            assert!(dex.get_bridge(REMOTE_CHAIN).is_none());
+            assert_eq!(dex.pause_bridge(REMOTE_CHAIN), Err(Error::BridgeNotFound));
+        }
+
+        #[ink::test]
+        fn bridge_volume_is_capped_per_day() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob]);
+            dex.add_bridge(REMOTE_CHAIN, b"remote-htlc".to_vec(), [0u8; KYBER_PUBLICKEYBYTES]).unwrap();
+            dex.allow_bridge_token(REMOTE_CHAIN, elxr, 50_000).unwrap();
+            let hashlock = ZeroSpreadDEX::hash_preimage(b"secret");
+
+            set_caller(acc.bob);
+            let bridge = |dex: &mut ZeroSpreadDEX, amount_out: Balance| {
+                let order_id = dex.create_order(nrsh, elxr, 100, amount_out, OrderType::CrossChain, None).unwrap();
+                dex.bridge_order(order_id, REMOTE_CHAIN, b"bob".to_vec(), acc.django, hashlock, 100)
+            };
+
+            bridge(&mut dex, 30_000).unwrap();
+            assert_eq!(bridge(&mut dex, 20_001), Err(Error::DailyCapExceeded));
+            bridge(&mut dex, 20_000).unwrap();
+            assert_eq!(dex.bridge_token_volume(REMOTE_CHAIN, elxr), Some((50_000, 50_000)));
+
+            // The cap resets the next day
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(BRIDGE_CAP_PERIOD);
+            assert_eq!(dex.bridge_token_volume(REMOTE_CHAIN, elxr), Some((50_000, 0)));
+            bridge(&mut dex, 50_000).unwrap();
+        }
+
+        #[ink::test]
+        fn paused_bridges_take_no_new_orders() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob]);
+            add_bridge(&mut dex, vec![elxr]);
+            dex.set_bridge_guardian(Some(acc.eve)).unwrap();
+            let preimage = b"bob's secret".to_vec();
+            let hashlock = ZeroSpreadDEX::hash_preimage(&preimage);
+
+            set_caller(acc.bob);
+            let bridged = dex.create_order(nrsh, elxr, 100, 400, OrderType::CrossChain, None).unwrap();
+            dex.bridge_order(bridged, REMOTE_CHAIN, b"bob".to_vec(), acc.django, hashlock, 100).unwrap();
+            let waiting = dex.create_order(nrsh, elxr, 100, 400, OrderType::CrossChain, None).unwrap();
+
+            // The guardian trips the breaker but cannot reset it
+            set_caller(acc.eve);
+            dex.pause_bridge(REMOTE_CHAIN).unwrap();
+            assert_eq!(dex.unpause_bridge(REMOTE_CHAIN), Err(Error::NotAuthorized));
+
+            set_caller(acc.bob);
+            assert_eq!(
+                dex.bridge_order(waiting, REMOTE_CHAIN, b"bob".to_vec(), acc.django, hashlock, 100),
+                Err(Error::BridgePaused)
+            );
+            // Orders already locked still settle
+            dex.complete_bridge(bridged, preimage).unwrap();
+
+            set_caller(acc.alice);
+            dex.unpause_bridge(REMOTE_CHAIN).unwrap();
+            set_caller(acc.bob);
+            dex.bridge_order(waiting, REMOTE_CHAIN, b"bob".to_vec(), acc.django, hashlock, 100).unwrap();
+        }
+
+        const NRSH_USD: FeedId = [1; 32];
+        const ELXR_USD: FeedId = [2; 32];
+
+        /// DEX with NRSH at $4.50 and ELXR at $1, and bob, charlie and django funded
+        fn setup_with_oracle() -> ZeroSpreadDEX {
+            let mut dex = setup();
+            dex.set_oracle(AccountId::from([0x0a; 32]), DEFAULT_MAX_ORACLE_STALENESS).unwrap();
+            dex.set_token_feed(AccountId::from(NRSH), NRSH_USD).unwrap();
+            dex.set_token_feed(AccountId::from(ELXR), ELXR_USD).unwrap();
+            set_feed_price(NRSH_USD, 4_500_000, 0);
+            set_feed_price(ELXR_USD, 1_000_000, 0);
+            let acc = accounts();
+            fund(&[acc.bob, acc.charlie, acc.django]);
+            dex
+        }
+
+        #[ink::test]
+        fn stop_loss_triggers_at_the_trigger_price() {
+            let mut dex = setup_with_oracle();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+
+            // Charlie bids 4 ELXR per NRSH for 1000 NRSH
+            set_caller(acc.charlie);
+            dex.create_order(elxr, nrsh, 4_000, 1_000, OrderType::Limit, None).unwrap();
+
+            // Bob sells 1000 NRSH if it falls to 4 ELXR, accepting no less than 3
+            set_caller(acc.bob);
+            let stop = dex
+                .create_conditional_order(nrsh, elxr, 1_000, 3_000, OrderType::StopLoss, 4_000_000, None)
+                .unwrap();
+            // 0.1% bounty escrowed on top
+            assert_eq!(dex.get_order(stop).unwrap().bounty, 1);
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000 - 1_001);
+            // Conditional orders never rest on the book
+            assert!(dex.get_order_book(nrsh, elxr).is_empty());
+
+            set_caller(acc.django);
+            assert_eq!(dex.trigger_order(stop), Err(Error::OrderNotTriggered));
+            set_feed_price(NRSH_USD, 4_000_001, 0);
+            assert_eq!(dex.trigger_order(stop), Err(Error::OrderNotTriggered));
+
+            // Exactly at the trigger price
+            set_feed_price(NRSH_USD, 4_000_000, 0);
+            assert_eq!(dex.trigger_order(stop), Ok(1));
+            assert_eq!(balance(nrsh, acc.django), 1_000_001);
+
+            let order = dex.get_order(stop).unwrap();
+            assert_eq!(order.status, OrderStatus::Filled);
+            assert_eq!(order.filled_out, 4_000);
+            // Less the 0.0369% taker fee
+            assert_eq!(balance(elxr, acc.bob), 1_004_000 - 1);
+            assert_eq!(dex.trigger_order(stop), Err(Error::InvalidOrder));
+        }
+
+        #[ink::test]
+        fn take_profit_triggers_on_the_way_up() {
+            let mut dex = setup_with_oracle();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+
+            set_caller(acc.bob);
+            let take = dex
+                .create_conditional_order(nrsh, elxr, 1_000, 0, OrderType::TakeProfit, 5_000_000, None)
+                .unwrap();
+
+            set_caller(acc.django);
+            assert_eq!(dex.trigger_order(take), Err(Error::OrderNotTriggered));
+            set_feed_price(NRSH_USD, 5_200_000, 0);
+            assert_eq!(dex.trigger_order(take), Ok(1));
+
+            // Nothing to sell into, so the market order is cancelled and refunded
+            let order = dex.get_order(take).unwrap();
+            assert_eq!(order.status, OrderStatus::Cancelled);
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000 - 1);
+            assert!(dex.get_user_orders(acc.bob).is_empty());
+        }
+
+        #[ink::test]
+        fn stop_limit_rests_once_triggered() {
+            let mut dex = setup_with_oracle();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+
+            set_caller(acc.bob);
+            let stop = dex
+                .create_conditional_order(nrsh, elxr, 1_000, 3_900, OrderType::StopLimit, 4_000_000, None)
+                .unwrap();
+            set_feed_price(NRSH_USD, 3_950_000, 0);
+            set_caller(acc.django);
+            dex.trigger_order(stop).unwrap();
+
+            let order = dex.get_order(stop).unwrap();
+            assert_eq!(order.order_type, OrderType::Limit);
+            assert_eq!(order.status, OrderStatus::Open);
+            assert_eq!(dex.get_order_book(nrsh, elxr), vec![stop]);
+            assert_eq!(dex.get_user_orders(acc.bob), vec![stop]);
+        }
+
+        #[ink::test]
+        fn triggers_need_a_fresh_oracle_price() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob]);
+
+            set_caller(acc.bob);
+            let stop = dex
+                .create_conditional_order(nrsh, elxr, 1_000, 0, OrderType::StopLoss, 4_000_000, None)
+                .unwrap();
+            // No oracle configured
+            assert_eq!(dex.trigger_order(stop), Err(Error::OracleUnavailable));
+
+            set_caller(acc.alice);
+            dex.set_oracle(AccountId::from([0x0a; 32]), DEFAULT_MAX_ORACLE_STALENESS).unwrap();
+            dex.set_token_feed(nrsh, NRSH_USD).unwrap();
+            dex.set_token_feed(elxr, ELXR_USD).unwrap();
+            // ELXR feed has never reported
+            set_feed_price(NRSH_USD, 3_000_000, 0);
+            assert_eq!(dex.trigger_order(stop), Err(Error::OracleUnavailable));
+
+            set_feed_price(ELXR_USD, 1_000_000, DEFAULT_MAX_ORACLE_STALENESS + 1);
+            assert_eq!(dex.trigger_order(stop), Err(Error::StalePrice));
+            set_feed_price(ELXR_USD, 1_000_000, DEFAULT_MAX_ORACLE_STALENESS);
+            assert_eq!(dex.trigger_order(stop), Ok(1));
+        }
+
+        #[ink::test]
+        fn untriggered_orders_refund_the_bounty() {
+            let mut dex = setup_with_oracle();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+
+            // Conditional types go through create_conditional_order with a trigger price
+            set_caller(acc.bob);
+            assert_eq!(
+                dex.create_order(nrsh, elxr, 1_000, 0, OrderType::StopLoss, None),
+                Err(Error::InvalidOrder)
+            );
+            assert_eq!(
+                dex.create_conditional_order(nrsh, elxr, 1_000, 0, OrderType::StopLoss, 0, None),
+                Err(Error::InvalidOrder)
+            );
+            assert_eq!(
+                dex.create_conditional_order(nrsh, elxr, 1_000, 0, OrderType::Limit, 4_000_000, None),
+                Err(Error::InvalidOrder)
+            );
+
+            let cancelled = dex
+                .create_conditional_order(nrsh, elxr, 1_000, 0, OrderType::StopLoss, 4_000_000, Some(5))
+                .unwrap();
+            let expired = dex
+                .create_conditional_order(nrsh, elxr, 1_000, 0, OrderType::StopLoss, 4_000_000, Some(5))
+                .unwrap();
+            dex.cancel_order(cancelled).unwrap();
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000 - 1_001);
+
+            for _ in 0..6 {
+                ink_env::test::advance_block::<ink_env::DefaultEnvironment>();
+            }
+            set_feed_price(NRSH_USD, 3_000_000, 0);
+            set_caller(acc.django);
+            assert_eq!(dex.trigger_order(expired), Err(Error::OrderExpired));
+            assert_eq!(dex.sweep_expired(vec![expired]), Ok(1));
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000);
+            assert_eq!(balance(nrsh, AccountId::from(DEX)), 0);
+        }
+
+        fn advance_blocks(blocks: BlockNumber) {
+            for _ in 0..blocks {
+                ink_env::test::advance_block::<ink_env::DefaultEnvironment>();
+            }
+        }
+
+        /// Commit every order, reveal them in the given order and clear the batch.
+        /// Returns each trader's (sold, bought).
+        fn run_batch(dex: &mut ZeroSpreadDEX, orders: &[(AccountId, TokenId, TokenId, Balance, Balance)]) -> Vec<(AccountId, Balance, Balance)> {
+            let salt = [7u8; 32];
+            let batch_id = dex.current_batch();
+            for (trader, token_in, token_out, amount_in, min_amount_out) in orders {
+                set_caller(*trader);
+                let commitment = dex.order_commitment(*trader, *token_in, *token_out, *amount_in, *min_amount_out, salt);
+                assert_eq!(dex.commit_order(commitment), Ok(batch_id));
+            }
+            advance_blocks(DEFAULT_BATCH_LENGTH);
+            let mut order_ids = Vec::new();
+            for (trader, token_in, token_out, amount_in, min_amount_out) in orders {
+                set_caller(*trader);
+                order_ids.push(dex.reveal_order(*token_in, *token_out, *amount_in, *min_amount_out, salt).unwrap());
+            }
+            advance_blocks(DEFAULT_BATCH_LENGTH);
+            dex.clear_batch(batch_id, AccountId::from(ELXR), AccountId::from(NRSH)).unwrap();
+
+            let mut fills: Vec<_> = order_ids
+                .into_iter()
+                .map(|id| dex.get_order(id).unwrap())
+                .map(|order| (order.creator, order.filled_in, order.filled_out))
+                .collect();
+            fills.sort();
+            fills
+        }
+
+        #[ink::test]
+        fn batch_clears_at_a_uniform_price() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob, acc.charlie, acc.django, acc.eve]);
+
+            // Asks at 3 and 5 ELXR per NRSH, bids at 6 and 4
+            let fills = run_batch(&mut dex, &[
+                (acc.bob, nrsh, elxr, 100, 300),
+                (acc.charlie, nrsh, elxr, 100, 500),
+                (acc.django, elxr, nrsh, 600, 100),
+                (acc.eve, elxr, nrsh, 400, 100),
+            ]);
+
+            // 5 clears the most: django buys 120 NRSH, split evenly between the asks,
+            // and bob sells at 5 although he asked for 3. Eve's bid is below the price.
+            let mut expected = vec![
+                (acc.bob, 60, 300),
+                (acc.charlie, 60, 300),
+                (acc.django, 600, 120),
+                (acc.eve, 0, 0),
+            ];
+            expected.sort();
+            assert_eq!(fills, expected);
+
+            // Remainders are refunded and nothing is left in escrow
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000 - 60);
+            assert_eq!(balance(elxr, acc.eve), 1_000_000);
+            assert_eq!(balance(nrsh, AccountId::from(DEX)), 0);
+            assert_eq!(balance(elxr, AccountId::from(DEX)), 0);
+            assert!(dex.get_user_orders(acc.eve).is_empty());
+        }
+
+        #[ink::test]
+        fn reveal_order_does_not_change_fills() {
+            let acc = accounts();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let mut orders = vec![
+                (acc.bob, nrsh, elxr, 70, 210),
+                (acc.charlie, nrsh, elxr, 50, 200),
+                (acc.django, elxr, nrsh, 333, 70),
+                (acc.eve, elxr, nrsh, 101, 25),
+                (acc.frank, nrsh, elxr, 41, 170),
+            ];
+
+            let mut dex = setup();
+            fund(&[acc.bob, acc.charlie, acc.django, acc.eve, acc.frank]);
+            let forward = run_batch(&mut dex, &orders);
+
+            orders.reverse();
+            let mut dex = setup();
+            fund(&[acc.bob, acc.charlie, acc.django, acc.eve, acc.frank]);
+            let reversed = run_batch(&mut dex, &orders);
+
+            assert_eq!(forward, reversed);
+            assert!(forward.iter().any(|(_, sold, _)| *sold > 0));
+        }
+
+        #[ink::test]
+        fn batch_phases_are_enforced() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            fund(&[acc.bob]);
+            let salt = [9u8; 32];
+
+            set_caller(acc.bob);
+            let commitment = dex.order_commitment(acc.bob, nrsh, elxr, 100, 300, salt);
+            let batch_id = dex.commit_order(commitment).unwrap();
+            assert_eq!(dex.commit_order(commitment), Err(Error::InvalidOrder));
+            assert!(dex.batch_commitment(batch_id).is_some());
+
+            // Not in the commit window, and only with the committed terms
+            assert_eq!(dex.reveal_order(nrsh, elxr, 100, 300, salt), Err(Error::MEVDetected));
+            advance_blocks(DEFAULT_BATCH_LENGTH);
+            assert_eq!(dex.reveal_order(nrsh, elxr, 100, 290, salt), Err(Error::CommitmentNotFound));
+            set_caller(acc.charlie);
+            assert_eq!(dex.reveal_order(nrsh, elxr, 100, 300, salt), Err(Error::CommitmentNotFound));
+
+            set_caller(acc.bob);
+            let order_id = dex.reveal_order(nrsh, elxr, 100, 300, salt).unwrap();
+            assert_eq!(dex.get_order(order_id).unwrap().order_type, OrderType::Batch);
+            assert_eq!(dex.clear_batch(batch_id, nrsh, elxr), Err(Error::BatchInProgress));
+
+            // Revealed orders stay in the batch until it clears
+            assert_eq!(dex.cancel_order(order_id), Err(Error::BatchInProgress));
+            assert!(dex.get_order(order_id).unwrap().is_open());
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000 - 100);
+
+            // A commitment revealed too late is rejected
+            let late = dex.order_commitment(acc.bob, nrsh, elxr, 50, 150, salt);
+            dex.commit_order(late).unwrap();
+            advance_blocks(2 * DEFAULT_BATCH_LENGTH);
+            assert_eq!(dex.reveal_order(nrsh, elxr, 50, 150, salt), Err(Error::MEVDetected));
+
+            // One-sided batch: nothing trades and the order is refunded
+            assert_eq!(dex.clear_batch(batch_id, elxr, nrsh), Ok(0));
+            assert_eq!(dex.get_order(order_id).unwrap().status, OrderStatus::Cancelled);
+            assert_eq!(balance(nrsh, acc.bob), 1_000_000);
+            assert_eq!(dex.clear_batch(batch_id, nrsh, elxr), Err(Error::OrderNotFound));
+        }
+
+        /// Random books: no fill may pay either side less than its own limit,
+        /// and tokens are only ever moved, never created or lost
+        #[ink::test]
+        fn fills_never_violate_min_amount_out() {
+            let mut dex = setup();
+            let (nrsh, elxr) = (AccountId::from(NRSH), AccountId::from(ELXR));
+            let acc = accounts();
+            let traders = [acc.bob, acc.charlie, acc.django, acc.eve, acc.frank];
+            fund(&traders);
+            let supply_before = (supply(nrsh), supply(elxr));
+
+            // xorshift64, fixed seed so failures reproduce
+            let mut state = 0x2545_f491_4f6c_dd1du64;
+            let mut next = move |bound: u64| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state % bound
+            };
+
+            let mut created = Vec::new();
+            // Few enough orders that neither side of the book fills up
+            for _ in 0..250 {
+                set_caller(traders[next(traders.len() as u64) as usize]);
+                let (token_in, token_out) = if next(2) == 0 { (nrsh, elxr) } else { (elxr, nrsh) };
+                let amount_in = 1 + next(1_000) as Balance;
+                let min_amount_out = 1 + next(1_000) as Balance;
+                let order_type = if next(5) == 0 { OrderType::Market } else { OrderType::Limit };
+                let order_id = dex.create_order(token_in, token_out, amount_in, min_amount_out, order_type, None).unwrap();
+                created.push(order_id);
+
+                let mut escrow = (0, 0);
+                for order_id in &created {
+                    let order = dex.get_order(*order_id).unwrap();
+                    assert!(order.filled_in <= order.amount_in);
+                    assert!(
+                        order.filled_out * order.amount_in >= order.filled_in * order.min_amount_out,
+                        "order {} filled below its limit: {:?}",
+                        order_id,
+                        order
+                    );
+                    if order.status == OrderStatus::Filled {
+                        assert!(order.filled_out >= order.min_amount_out);
+                    }
+                    if !order.is_open() {
+                        continue;
+                    }
+                    let held = order.remaining() + order.bounty;
+                    if order.token_in == nrsh { escrow.0 += held } else { escrow.1 += held }
+                }
+
+                // The contract holds open escrow and uncollected fees, nothing else
+                escrow.0 += dex.collected_fees(nrsh);
+                escrow.1 += dex.collected_fees(elxr);
+                assert_eq!((supply(nrsh), supply(elxr)), supply_before);
+                assert_eq!(escrow, (balance(nrsh, AccountId::from(DEX)), balance(elxr, AccountId::from(DEX))));
+
+                // Each book stays sorted by ask price
+                for (a, b) in [(nrsh, elxr), (elxr, nrsh)] {
+                    let book: Vec<Order> = dex.get_order_book(a, b).iter().map(|id| dex.get_order(*id).unwrap()).collect();
+                    for pair in book.windows(2) {
+                        assert!(!ZeroSpreadDEX::better_price(&pair[1], &pair[0]).unwrap());
+                    }
+                }
+            }
+            assert!(dex.get_trade(0).is_some());
+        }
     }
 }
@@ -0,0 +1,97 @@
+// DEX trade indexer
+// Follows finalized blocks, stores order book fills, pool swaps and new orders
+// in SQLite and serves candles, trade history and volume over HTTP/JSON
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use clap::Parser;
+use log::{info, warn};
+use sp_core::crypto::AccountId32;
+
+use nourish_eigenlayer::indexer::{api, node::NodeBlocks, store::Store, Indexer, IndexerConfig, IndexerError};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Path to the indexer JSON configuration
+    #[clap(short, long)]
+    config: PathBuf,
+
+    /// Override the node endpoint from the configuration
+    #[clap(long)]
+    node_url: Option<String>,
+
+    /// Override the HTTP listen address from the configuration
+    #[clap(long)]
+    listen: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), IndexerError> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let data = std::fs::read_to_string(&args.config).map_err(|e| IndexerError::Config(e.to_string()))?;
+    let mut config = IndexerConfig::from_json(&data)?;
+    if let Some(url) = args.node_url {
+        config.node_url = url;
+    }
+    if let Some(listen) = args.listen {
+        config.listen = listen;
+    }
+
+    let dex = AccountId32::from_str(&config.dex_contract)
+        .map_err(|e| IndexerError::Config(format!("dex_contract: {}", e)))?;
+    let tokens = config
+        .tokens
+        .iter()
+        .map(|token| token.token().map_err(|e| IndexerError::Config(e.to_string())))
+        .collect::<Result<Vec<_>, _>>()?;
+    let indexer = Indexer::new(dex, tokens);
+
+    // Relative database paths are resolved against the configuration file
+    let base_dir = args.config.parent().map(PathBuf::from).unwrap_or_default();
+    let store = Arc::new(Mutex::new(Store::open(base_dir.join(&config.database))?));
+    let blocks = NodeBlocks::connect(&config.node_url)?;
+
+    let addr = SocketAddr::from_str(&config.listen).map_err(|e| IndexerError::Config(format!("listen: {}", e)))?;
+    let server = axum::Server::bind(&addr).serve(api::router(store.clone()).into_make_service());
+    info!("dex indexer serving on http://{}", addr);
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            warn!("http server stopped: {}", e);
+        }
+    });
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.poll_secs.max(1)));
+    loop {
+        ticker.tick().await;
+        let cursor = store.lock().expect("store lock poisoned").cursor()?;
+        let next = cursor.map_or(config.start_block, |block| block + 1);
+        let finalized = match blocks.finalized().await {
+            Ok(finalized) => finalized,
+            Err(e) => {
+                warn!("{}", e);
+                continue;
+            }
+        };
+        for number in next..=finalized {
+            // A failing read is retried from the same block on the next tick
+            let block = match blocks.block(number).await {
+                Ok(block) => block,
+                Err(e) => {
+                    warn!("{}", e);
+                    break;
+                }
+            };
+            let indexed = indexer.index(&mut store.lock().expect("store lock poisoned"), &block)?;
+            if indexed > 0 {
+                info!("block {}: {} trades and orders", number, indexed);
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// DaemonlessOracle validator daemon
+// Polls the configured price sources and submits signed per-gallon prices
+// for spirulina culture (NRSH) and kombucha (ELXR) on schedule
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Parser;
+use futures::future::BoxFuture;
+use log::info;
+use sp_core::crypto::AccountId32;
+
+use nourish_eigenlayer::oracle_client::{
+    signer::{SignedSubmission, SubmissionSigner},
+    sources::{CsvSource, DexTwapSource, HttpJsonSource, PriceSource},
+    submitter::{validator_account, ContractSubmitter, NodeReserveReader, Submitter},
+    ClientError, SourceConfig, Validator, ValidatorConfig,
+};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Path to the validator JSON configuration
+    #[clap(short, long)]
+    config: PathBuf,
+
+    /// Secret URI of the validator key (mnemonic, hex seed or //Dev path)
+    #[clap(long, env = "ORACLE_VALIDATOR_SEED")]
+    seed: String,
+
+    /// Override the node endpoint from the configuration
+    #[clap(long)]
+    node_url: Option<String>,
+
+    /// Log signed submissions as JSON instead of sending them to the node
+    #[clap(long)]
+    dry_run: bool,
+}
+
+struct LogSubmitter;
+
+impl Submitter for LogSubmitter {
+    fn submit<'a>(&'a self, submission: &'a SignedSubmission) -> BoxFuture<'a, Result<(), ClientError>> {
+        Box::pin(async move {
+            let json = serde_json::to_string(submission).map_err(|e| ClientError::Config(e.to_string()))?;
+            println!("{}", json);
+            Ok(())
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), ClientError> {
+    env_logger::init();
+    let args = Args::parse();
+
+    let data = std::fs::read_to_string(&args.config).map_err(|e| ClientError::Config(e.to_string()))?;
+    let mut config = ValidatorConfig::from_json(&data)?;
+    if let Some(url) = args.node_url {
+        config.node_url = url;
+    }
+
+    // Relative CSV and nonce file paths are resolved against the configuration file
+    let base_dir = args.config.parent().map(PathBuf::from).unwrap_or_default();
+    config.nonce_file = config.nonce_file.map(|path| base_dir.join(path));
+
+    let contract = AccountId32::from_str(&config.oracle_contract)
+        .map_err(|e| ClientError::Config(format!("oracle_contract: {}", e)))?;
+    let signer = SubmissionSigner::from_uri(&args.seed)?;
+    let node = ContractSubmitter::connect(&config.node_url, contract, &signer)?;
+    let reader = Arc::new(NodeReserveReader::new(node.api()));
+
+    let sources: Vec<Box<dyn PriceSource>> = config
+        .sources
+        .iter()
+        .map(|source| -> Box<dyn PriceSource> {
+            match source {
+                SourceConfig::Csv { name, path } => Box::new(CsvSource::new(name.clone(), base_dir.join(path))),
+                SourceConfig::HttpJson { name, url, quotes } => {
+                    Box::new(HttpJsonSource::new(name.clone(), url.clone(), quotes.clone()))
+                }
+                SourceConfig::DexTwap { name, pair } => {
+                    Box::new(DexTwapSource::new(name.clone(), reader.clone(), pair.clone()))
+                }
+            }
+        })
+        .collect();
+
+    info!(
+        "oracle validator {} polling {} sources every {}s",
+        validator_account(&signer),
+        sources.len(),
+        config.interval_secs
+    );
+
+    let submitter: Box<dyn Submitter> = if args.dry_run { Box::new(LogSubmitter) } else { Box::new(node) };
+    Validator::new(&config, sources, signer, submitter)
+        .run(Duration::from_secs(config.interval_secs))
+        .await;
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+// Operator tool for the UnifiedLiquidityPool provider vault
+// Generates vault keys and decrypts the profiles providers stored in the pool
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::{Parser, Subcommand};
+use sp_core::crypto::AccountId32;
+
+use nourish_eigenlayer::provider_vault::{node::PoolVault, OperatorKey, VaultError};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate a vault key, write its secret seed to `out` and print the
+    /// public key to publish with the pool's `set_vault_key`
+    Keygen {
+        #[clap(long)]
+        out: PathBuf,
+    },
+    /// Print the public key of an existing seed file
+    PublicKey {
+        #[clap(long)]
+        key: PathBuf,
+    },
+    /// Fetch a provider's stored profile from the pool and decrypt it to stdout
+    Decrypt {
+        /// Seed file of the vault key the profile was sealed to
+        #[clap(long)]
+        key: PathBuf,
+        #[clap(long, default_value = "ws://127.0.0.1:9944")]
+        node_url: String,
+        /// SS58 address of the UnifiedLiquidityPool contract
+        #[clap(long)]
+        pool: String,
+        /// SS58 address of the provider
+        #[clap(long)]
+        provider: String,
+    },
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn read_key(path: &PathBuf) -> Result<OperatorKey, VaultError> {
+    let text = std::fs::read_to_string(path).map_err(|e| VaultError::Key(format!("{}: {}", path.display(), e)))?;
+    let text = text.trim().trim_start_matches("0x");
+    let seed = (0..text.len())
+        .step_by(2)
+        .map(|i| text.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+        .ok_or_else(|| VaultError::Key(format!("{}: expected 64 hex-encoded bytes", path.display())))?;
+    Ok(OperatorKey::from_seed(seed))
+}
+
+fn write_key(path: &PathBuf, key: &OperatorKey) -> Result<(), VaultError> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .map_err(|e| VaultError::Key(format!("{}: {}", path.display(), e)))?;
+    writeln!(file, "{}", hex(key.seed())).map_err(|e| VaultError::Key(e.to_string()))
+}
+
+fn account(name: &str, ss58: &str) -> Result<AccountId32, VaultError> {
+    AccountId32::from_str(ss58).map_err(|e| VaultError::Key(format!("{}: {}", name, e)))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), VaultError> {
+    env_logger::init();
+    match Args::parse().command {
+        Command::Keygen { out } => {
+            let key = OperatorKey::generate();
+            write_key(&out, &key)?;
+            println!("0x{}", hex(&key.encapsulation_key()));
+        }
+        Command::PublicKey { key } => {
+            println!("0x{}", hex(&read_key(&key)?.encapsulation_key()));
+        }
+        Command::Decrypt {
+            key,
+            node_url,
+            pool,
+            provider,
+        } => {
+            let key = read_key(&key)?;
+            let (pool, provider) = (account("pool", &pool)?, account("provider", &provider)?);
+            let vault = PoolVault::connect(&node_url, pool.clone(), provider.clone())?;
+            let data = vault
+                .provider_data(&provider)
+                .await?
+                .ok_or_else(|| VaultError::Node(format!("no profile stored for {}", provider)))?;
+            let profile = key.open(&pool, &provider, &data)?;
+            std::io::stdout()
+                .write_all(&profile)
+                .map_err(|e| VaultError::Node(e.to_string()))?;
+        }
+    }
+    Ok(())
+}
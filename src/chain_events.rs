@@ -0,0 +1,63 @@
+// SCALE mirrors of the on-chain events the off-chain services consume
+// ZeroSpreadDEX events arrive as the data of `Contracts::ContractEmitted`, encoded
+// as the contract's event enum; pallet-liquidity-pool events are plain pallet events
+
+use codec::Decode;
+use sp_core::crypto::AccountId32;
+
+/// The ZeroSpreadDEX events read off-chain. Indexes follow the declaration order
+/// of the events in the contract, variants not listed here do not decode.
+#[derive(Clone, Debug, PartialEq, Eq, Decode)]
+pub enum DexEvent {
+    #[codec(index = 0)]
+    OrderCreated {
+        order_id: u64,
+        creator: AccountId32,
+        token_in: AccountId32,
+        token_out: AccountId32,
+        amount_in: u128,
+    },
+    #[codec(index = 6)]
+    TradeExecuted {
+        trade_id: u64,
+        maker: AccountId32,
+        taker: AccountId32,
+        /// The maker's token, `amount` is in it
+        base_token: AccountId32,
+        /// The taker's token, `price` is in it
+        quote_token: AccountId32,
+        amount: u128,
+        /// `quote_token` per `base_token`, scaled by 1_000_000
+        price: u128,
+        maker_fee: u128,
+        taker_fee: u128,
+    },
+}
+
+impl DexEvent {
+    /// Decode the field bytes of `Contracts::ContractEmitted` if `dex` emitted them
+    pub fn from_contract_emitted(dex: &AccountId32, mut fields: &[u8]) -> Option<Self> {
+        let (contract, data) = <(AccountId32, Vec<u8>)>::decode(&mut fields).ok()?;
+        if &contract != dex {
+            return None;
+        }
+        Self::decode(&mut &data[..]).ok()
+    }
+}
+
+/// `LiquidityPool::Swap(who, asset_in, asset_out, amount_in, amount_out, fee_amount)`
+#[derive(Clone, Debug, PartialEq, Eq, Decode)]
+pub struct PoolSwap {
+    pub who: AccountId32,
+    pub asset_in: u32,
+    pub asset_out: u32,
+    pub amount_in: u128,
+    pub amount_out: u128,
+    pub fee: u128,
+}
+
+impl PoolSwap {
+    pub fn from_fields(mut fields: &[u8]) -> Option<Self> {
+        Self::decode(&mut fields).ok()
+    }
+}
@@ -0,0 +1,154 @@
+// HTTP/JSON API over the indexed trades
+//
+//   GET /pairs/:base/:quote/candles?interval=<secs>&from=<ms>&to=<ms>
+//   GET /pairs/:base/:quote/volume?window=<secs>
+//   GET /accounts/:account/trades?limit=<n>
+//   GET /accounts/:account/orders?limit=<n>
+
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
+use tower_http::cors::CorsLayer;
+
+use super::store::Store;
+use super::{candles, volume, IndexerError};
+
+type SharedStore = Arc<Mutex<Store>>;
+
+const MAX_LIMIT: u32 = 1_000;
+const MAX_CANDLES_SPAN: u64 = 1_000;
+
+struct ApiError(StatusCode, String);
+
+impl From<IndexerError> for ApiError {
+    fn from(e: IndexerError) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[derive(Deserialize)]
+struct CandleQuery {
+    /// Seconds per candle
+    #[serde(default = "default_interval")]
+    interval: u64,
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+fn default_interval() -> u64 {
+    3_600
+}
+
+#[derive(Deserialize)]
+struct VolumeQuery {
+    /// Seconds back from now
+    #[serde(default = "default_window")]
+    window: u64,
+}
+
+fn default_window() -> u64 {
+    24 * 60 * 60
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    #[serde(default = "default_limit")]
+    limit: u32,
+}
+
+fn default_limit() -> u32 {
+    100
+}
+
+async fn pair_candles(
+    State(store): State<SharedStore>,
+    Path((base, quote)): Path<(String, String)>,
+    Query(query): Query<CandleQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let interval = query.interval.max(1).saturating_mul(1_000);
+    let to = query.to.unwrap_or_else(now_ms);
+    let from = query
+        .from
+        .unwrap_or_else(|| to.saturating_sub(interval.saturating_mul(100)));
+    if to.saturating_sub(from) / interval > MAX_CANDLES_SPAN {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            format!("at most {} candles per request", MAX_CANDLES_SPAN),
+        ));
+    }
+    let trades = store
+        .lock()
+        .expect("store lock poisoned")
+        .pair_trades(&base, &quote, from, to)?;
+    Ok(Json(candles(&trades, &base, interval)))
+}
+
+async fn pair_volume(
+    State(store): State<SharedStore>,
+    Path((base, quote)): Path<(String, String)>,
+    Query(query): Query<VolumeQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let to = now_ms();
+    let from = to.saturating_sub(query.window.saturating_mul(1_000));
+    let trades = store
+        .lock()
+        .expect("store lock poisoned")
+        .pair_trades(&base, &quote, from, to)?;
+    Ok(Json(volume(&trades, &base)))
+}
+
+async fn account_trades(
+    State(store): State<SharedStore>,
+    Path(account): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let trades = store
+        .lock()
+        .expect("store lock poisoned")
+        .account_trades(&account, query.limit.min(MAX_LIMIT))?;
+    Ok(Json(trades))
+}
+
+async fn account_orders(
+    State(store): State<SharedStore>,
+    Path(account): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let orders = store
+        .lock()
+        .expect("store lock poisoned")
+        .account_orders(&account, query.limit.min(MAX_LIMIT))?;
+    Ok(Json(orders))
+}
+
+/// Routes of the API; any origin may call it, the web demo is served elsewhere
+pub fn router(store: SharedStore) -> Router {
+    Router::new()
+        .route("/pairs/:base/:quote/candles", get(pair_candles))
+        .route("/pairs/:base/:quote/volume", get(pair_volume))
+        .route("/accounts/:account/trades", get(account_trades))
+        .route("/accounts/:account/orders", get(account_orders))
+        .layer(CorsLayer::permissive())
+        .with_state(store)
+}
@@ -0,0 +1,500 @@
+// Trade indexer for the ZeroSpreadDEX order book and the pallet-liquidity-pool pools
+// Turns `TradeExecuted`, `OrderCreated` and `Swap` events into SQLite rows and
+// aggregates them into OHLCV candles and volume statistics for the web demo
+
+pub mod api;
+pub mod node;
+pub mod store;
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::AccountId32;
+use thiserror::Error;
+
+use crate::chain_events::{DexEvent, PoolSwap};
+use crate::router::{Token, TokenConfig, PRICE_PRECISION};
+use store::{OrderRecord, Store, TradeRecord, Venue};
+
+#[derive(Debug, Error)]
+pub enum IndexerError {
+    #[error("database error: {0}")]
+    Database(String),
+    #[error("node error: {0}")]
+    Node(String),
+    #[error("config error: {0}")]
+    Config(String),
+}
+
+/// Indexer configuration, loaded from JSON
+#[derive(Clone, Debug, Deserialize)]
+pub struct IndexerConfig {
+    /// Node websocket endpoint
+    pub node_url: String,
+    /// SS58 address of the deployed ZeroSpreadDEX contract
+    pub dex_contract: String,
+    /// Symbols shown for known tokens; others are shown by address or asset ID
+    #[serde(default)]
+    pub tokens: Vec<TokenConfig>,
+    /// SQLite database file
+    pub database: PathBuf,
+    /// Address the HTTP API listens on
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    /// Seconds between polls for new finalized blocks
+    #[serde(default = "default_poll_secs")]
+    pub poll_secs: u64,
+    /// First block indexed on an empty database
+    #[serde(default)]
+    pub start_block: u32,
+}
+
+fn default_listen() -> String {
+    "127.0.0.1:8088".into()
+}
+
+// One relay chain block
+fn default_poll_secs() -> u64 {
+    6
+}
+
+impl IndexerConfig {
+    pub fn from_json(data: &str) -> Result<Self, IndexerError> {
+        serde_json::from_str(data).map_err(|e| IndexerError::Config(e.to_string()))
+    }
+}
+
+/// An event as read from the node
+#[derive(Clone, Debug)]
+pub struct RawEvent {
+    pub pallet: String,
+    pub variant: String,
+    pub fields: Vec<u8>,
+}
+
+/// The events of one finalized block
+#[derive(Clone, Debug)]
+pub struct Block {
+    pub number: u32,
+    /// Milliseconds, from `Timestamp::Now`
+    pub timestamp: u64,
+    pub events: Vec<RawEvent>,
+}
+
+pub struct Indexer {
+    dex: AccountId32,
+    tokens: Vec<Token>,
+}
+
+impl Indexer {
+    pub fn new(dex: AccountId32, tokens: Vec<Token>) -> Self {
+        Self { dex, tokens }
+    }
+
+    fn dex_label(&self, token: &AccountId32) -> String {
+        let raw: &[u8; 32] = token.as_ref();
+        self.tokens
+            .iter()
+            .find(|known| known.dex_token.as_ref() == Some(raw))
+            .map(|known| known.symbol.clone())
+            .unwrap_or_else(|| token.to_string())
+    }
+
+    fn pool_label(&self, asset: u32) -> String {
+        self.tokens
+            .iter()
+            .find(|known| known.pool_asset == Some(asset))
+            .map(|known| known.symbol.clone())
+            .unwrap_or_else(|| format!("asset:{}", asset))
+    }
+
+    /// Trades and orders in `block`, in event order
+    pub fn records(&self, block: &Block) -> (Vec<TradeRecord>, Vec<OrderRecord>) {
+        let mut trades = Vec::new();
+        let mut orders = Vec::new();
+        for (index, event) in block.events.iter().enumerate() {
+            match (event.pallet.as_str(), event.variant.as_str()) {
+                ("Contracts", "ContractEmitted") => match DexEvent::from_contract_emitted(&self.dex, &event.fields) {
+                    // The event carries the price rather than what the taker paid,
+                    // so the sold amount can be a unit below the actual one
+                    Some(DexEvent::TradeExecuted {
+                        trade_id,
+                        maker,
+                        taker,
+                        base_token,
+                        quote_token,
+                        amount,
+                        price,
+                        taker_fee,
+                        ..
+                    }) => trades.push(TradeRecord {
+                        block: block.number,
+                        event_index: index as u32,
+                        timestamp: block.timestamp,
+                        venue: Venue::Book,
+                        trade_id: Some(trade_id),
+                        maker: Some(maker.to_string()),
+                        taker: taker.to_string(),
+                        bought: self.dex_label(&base_token),
+                        sold: self.dex_label(&quote_token),
+                        amount_bought: amount,
+                        amount_sold: amount.saturating_mul(price) / PRICE_PRECISION,
+                        fee: taker_fee,
+                    }),
+                    Some(DexEvent::OrderCreated {
+                        order_id,
+                        creator,
+                        token_in,
+                        token_out,
+                        amount_in,
+                    }) => orders.push(OrderRecord {
+                        order_id,
+                        block: block.number,
+                        timestamp: block.timestamp,
+                        creator: creator.to_string(),
+                        token_in: self.dex_label(&token_in),
+                        token_out: self.dex_label(&token_out),
+                        amount_in,
+                    }),
+                    None => {}
+                },
+                ("LiquidityPool", "Swap") => {
+                    if let Some(swap) = PoolSwap::from_fields(&event.fields) {
+                        trades.push(TradeRecord {
+                            block: block.number,
+                            event_index: index as u32,
+                            timestamp: block.timestamp,
+                            venue: Venue::Pool,
+                            trade_id: None,
+                            maker: None,
+                            taker: swap.who.to_string(),
+                            bought: self.pool_label(swap.asset_out),
+                            sold: self.pool_label(swap.asset_in),
+                            amount_bought: swap.amount_out,
+                            amount_sold: swap.amount_in,
+                            fee: swap.fee,
+                        })
+                    }
+                }
+                _ => {}
+            }
+        }
+        (trades, orders)
+    }
+
+    /// Index `block` and advance the store's cursor past it
+    pub fn index(&self, store: &mut Store, block: &Block) -> Result<usize, IndexerError> {
+        let (trades, orders) = self.records(block);
+        store.insert_block(block.number, &trades, &orders)?;
+        Ok(trades.len() + orders.len())
+    }
+}
+
+/// Amounts of a trade seen along the `base`/`quote` pair: `(base, quote)`
+fn pair_amounts(trade: &TradeRecord, base: &str) -> (u128, u128) {
+    if trade.bought == base {
+        (trade.amount_bought, trade.amount_sold)
+    } else {
+        (trade.amount_sold, trade.amount_bought)
+    }
+}
+
+/// Prices are `quote` per `base`, scaled by `PRICE_PRECISION`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Candle {
+    /// Start of the interval, milliseconds
+    pub time: u64,
+    pub open: u128,
+    pub high: u128,
+    pub low: u128,
+    pub close: u128,
+    /// Traded `base`
+    pub volume: u128,
+    /// Traded `quote`
+    pub quote_volume: u128,
+    pub trades: u32,
+}
+
+/// OHLCV candles of `interval` milliseconds from `trades` sorted oldest first,
+/// book fills and pool swaps alike. Intervals without trades are left out.
+pub fn candles(trades: &[TradeRecord], base: &str, interval: u64) -> Vec<Candle> {
+    let interval = interval.max(1);
+    let mut candles: Vec<Candle> = Vec::new();
+    for trade in trades {
+        let (base_amount, quote_amount) = pair_amounts(trade, base);
+        if base_amount == 0 {
+            continue;
+        }
+        let price = quote_amount.saturating_mul(PRICE_PRECISION) / base_amount;
+        let time = trade.timestamp - trade.timestamp % interval;
+        match candles.last_mut() {
+            Some(candle) if candle.time == time => {
+                candle.high = candle.high.max(price);
+                candle.low = candle.low.min(price);
+                candle.close = price;
+                candle.volume += base_amount;
+                candle.quote_volume += quote_amount;
+                candle.trades += 1;
+            }
+            _ => candles.push(Candle {
+                time,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume: base_amount,
+                quote_volume: quote_amount,
+                trades: 1,
+            }),
+        }
+    }
+    candles
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct VolumeStats {
+    pub trades: u32,
+    pub book_trades: u32,
+    pub pool_trades: u32,
+    pub volume: u128,
+    pub quote_volume: u128,
+    /// Volume-weighted average price, `quote` per `base` scaled by `PRICE_PRECISION`
+    pub vwap: u128,
+}
+
+pub fn volume(trades: &[TradeRecord], base: &str) -> VolumeStats {
+    let mut stats = VolumeStats::default();
+    for trade in trades {
+        let (base_amount, quote_amount) = pair_amounts(trade, base);
+        stats.trades += 1;
+        match trade.venue {
+            Venue::Book => stats.book_trades += 1,
+            Venue::Pool => stats.pool_trades += 1,
+        }
+        stats.volume += base_amount;
+        stats.quote_volume += quote_amount;
+    }
+    if stats.volume > 0 {
+        stats.vwap = stats.quote_volume.saturating_mul(PRICE_PRECISION) / stats.volume;
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+
+    const NRSH: [u8; 32] = [0x10; 32];
+    const ELXR: [u8; 32] = [0x20; 32];
+    const DEX: [u8; 32] = [0xdd; 32];
+    const ALICE: [u8; 32] = [1; 32];
+    const BOB: [u8; 32] = [2; 32];
+
+    fn indexer() -> Indexer {
+        let tokens = vec![
+            Token {
+                symbol: "NRSH".into(),
+                pool_asset: Some(1),
+                dex_token: Some(NRSH),
+            },
+            Token {
+                symbol: "ELXR".into(),
+                pool_asset: Some(2),
+                dex_token: Some(ELXR),
+            },
+        ];
+        Indexer::new(DEX.into(), tokens)
+    }
+
+    fn emitted(contract: [u8; 32], data: Vec<u8>) -> RawEvent {
+        RawEvent {
+            pallet: "Contracts".into(),
+            variant: "ContractEmitted".into(),
+            fields: (AccountId32::from(contract), data).encode(),
+        }
+    }
+
+    // The DEX event enum: variant index, then the fields in declaration order
+    fn trade_executed(
+        trade_id: u64,
+        maker: [u8; 32],
+        taker: [u8; 32],
+        base: [u8; 32],
+        quote: [u8; 32],
+        amount: u128,
+        price: u128,
+    ) -> RawEvent {
+        let mut data = vec![6u8];
+        (
+            trade_id,
+            maker,
+            taker,
+            base,
+            quote,
+            amount,
+            price,
+            0u128,
+            amount * 369 / 1_000_000,
+        )
+            .encode_to(&mut data);
+        emitted(DEX, data)
+    }
+
+    fn order_created(
+        order_id: u64,
+        creator: [u8; 32],
+        token_in: [u8; 32],
+        token_out: [u8; 32],
+        amount_in: u128,
+    ) -> RawEvent {
+        let mut data = vec![0u8];
+        (order_id, creator, token_in, token_out, amount_in).encode_to(&mut data);
+        emitted(DEX, data)
+    }
+
+    fn swap(who: [u8; 32], asset_in: u32, asset_out: u32, amount_in: u128, amount_out: u128) -> RawEvent {
+        RawEvent {
+            pallet: "LiquidityPool".into(),
+            variant: "Swap".into(),
+            fields: (
+                who,
+                asset_in,
+                asset_out,
+                amount_in,
+                amount_out,
+                amount_in * 369 / 100_000,
+            )
+                .encode(),
+        }
+    }
+
+    #[test]
+    fn indexes_book_and_pool_events() {
+        let indexer = indexer();
+        let mut store = Store::open_in_memory().unwrap();
+        assert_eq!(store.cursor().unwrap(), None);
+
+        let block = Block {
+            number: 7,
+            timestamp: 1_700_000_000_000,
+            events: vec![
+                order_created(1, ALICE, NRSH, ELXR, 100),
+                // ELXR sold for NRSH at 2 ELXR per NRSH, bob taking alice's order
+                trade_executed(1, ALICE, BOB, NRSH, ELXR, 100, 2 * PRICE_PRECISION),
+                swap(BOB, 2, 1, 1_000, 495),
+                // Emitted by another contract
+                RawEvent {
+                    fields: (AccountId32::from([9u8; 32]), vec![0u8]).encode(),
+                    ..order_created(2, BOB, NRSH, ELXR, 5)
+                },
+                RawEvent {
+                    pallet: "Balances".into(),
+                    variant: "Transfer".into(),
+                    fields: vec![],
+                },
+            ],
+        };
+        assert_eq!(indexer.index(&mut store, &block).unwrap(), 3);
+        assert_eq!(store.cursor().unwrap(), Some(7));
+
+        let alice = AccountId32::from(ALICE).to_string();
+        let bob = AccountId32::from(BOB).to_string();
+        let orders = store.account_orders(&alice, 10).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!((orders[0].token_in.as_str(), orders[0].amount_in), ("NRSH", 100));
+
+        let bob_trades = store.account_trades(&bob, 10).unwrap();
+        assert_eq!(bob_trades.len(), 2);
+        let book = bob_trades.iter().find(|trade| trade.venue == Venue::Book).unwrap();
+        assert_eq!((book.bought.as_str(), book.sold.as_str()), ("NRSH", "ELXR"));
+        assert_eq!((book.amount_bought, book.amount_sold), (100, 200));
+        assert_eq!(book.maker.as_deref(), Some(alice.as_str()));
+        let pool = bob_trades.iter().find(|trade| trade.venue == Venue::Pool).unwrap();
+        assert_eq!(
+            (pool.bought.as_str(), pool.amount_bought, pool.amount_sold),
+            ("NRSH", 495, 1_000)
+        );
+
+        // The maker sees the fill in its history too
+        assert_eq!(store.account_trades(&alice, 10).unwrap().len(), 1);
+
+        // Re-indexing a block replaces its rows instead of duplicating them
+        indexer.index(&mut store, &block).unwrap();
+        assert_eq!(store.pair_trades("ELXR", "NRSH", 0, u64::MAX).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn candles_and_volume_follow_the_requested_orientation() {
+        let indexer = indexer();
+        let mut store = Store::open_in_memory().unwrap();
+        let minute = 60_000;
+        let blocks = [
+            // Buying NRSH with ELXR at 2.0, then 2.5 on the book
+            (
+                1,
+                0,
+                trade_executed(1, ALICE, BOB, NRSH, ELXR, 100, 2 * PRICE_PRECISION),
+            ),
+            (2, 10_000, trade_executed(2, ALICE, BOB, NRSH, ELXR, 40, 2_500_000)),
+            // Selling NRSH into the pool at 1.5
+            (3, 30_000, swap(BOB, 1, 2, 200, 300)),
+            // Next minute: buying NRSH at 3.0 in the pool
+            (4, minute + 5_000, swap(ALICE, 2, 1, 90, 30)),
+        ];
+        for (number, timestamp, event) in blocks {
+            indexer
+                .index(
+                    &mut store,
+                    &Block {
+                        number,
+                        timestamp,
+                        events: vec![event],
+                    },
+                )
+                .unwrap();
+        }
+
+        let trades = store.pair_trades("NRSH", "ELXR", 0, u64::MAX).unwrap();
+        assert_eq!(trades.len(), 4);
+        let nrsh_elxr = candles(&trades, "NRSH", minute);
+        assert_eq!(
+            nrsh_elxr,
+            vec![
+                Candle {
+                    time: 0,
+                    open: 2_000_000,
+                    high: 2_500_000,
+                    low: 1_500_000,
+                    close: 1_500_000,
+                    volume: 340,
+                    quote_volume: 600,
+                    trades: 3,
+                },
+                Candle {
+                    time: minute,
+                    open: 3_000_000,
+                    high: 3_000_000,
+                    low: 3_000_000,
+                    close: 3_000_000,
+                    volume: 30,
+                    quote_volume: 90,
+                    trades: 1,
+                },
+            ]
+        );
+
+        // The same trades quoted the other way round
+        let elxr_nrsh = candles(&trades, "ELXR", minute);
+        assert_eq!(elxr_nrsh[0].open, 500_000);
+        assert_eq!(elxr_nrsh[0].volume, 600);
+
+        let stats = volume(&trades, "NRSH");
+        assert_eq!((stats.trades, stats.book_trades, stats.pool_trades), (4, 2, 2));
+        assert_eq!((stats.volume, stats.quote_volume), (370, 690));
+        assert_eq!(stats.vwap, 690 * PRICE_PRECISION / 370);
+
+        // Time ranges are half-open
+        assert_eq!(store.pair_trades("NRSH", "ELXR", 10_000, minute).unwrap().len(), 2);
+    }
+}
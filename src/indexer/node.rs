@@ -0,0 +1,80 @@
+// Finalized blocks and their events, read from the node
+
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use substrate_api_client::{
+    ac_primitives::AssetRuntimeConfig, rpc::JsonrpseeClient, Api, FetchEvents, GetChainInfo, GetStorage,
+};
+
+use super::{Block, IndexerError, RawEvent};
+
+type NodeApi = Api<AssetRuntimeConfig, JsonrpseeClient>;
+
+fn node_error(e: impl std::fmt::Debug) -> IndexerError {
+    IndexerError::Node(format!("{:?}", e))
+}
+
+pub struct NodeBlocks {
+    api: Arc<NodeApi>,
+}
+
+impl NodeBlocks {
+    pub fn connect(url: &str) -> Result<Self, IndexerError> {
+        let client = JsonrpseeClient::new(url).map_err(node_error)?;
+        let api = NodeApi::new(client).map_err(node_error)?;
+        Ok(Self { api: Arc::new(api) })
+    }
+
+    /// Number of the latest finalized block; only finalized blocks are indexed,
+    /// so the database never has to roll back a fork
+    pub fn finalized(&self) -> BoxFuture<'_, Result<u32, IndexerError>> {
+        let api = self.api.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let hash = api.get_finalized_head().map_err(node_error)?;
+                let header = api.get_header(hash).map_err(node_error)?;
+                header
+                    .map(|header| header.number)
+                    .ok_or_else(|| IndexerError::Node("no finalized header".into()))
+            })
+            .await
+            .map_err(|e| IndexerError::Node(e.to_string()))?
+        })
+    }
+
+    pub fn block(&self, number: u32) -> BoxFuture<'_, Result<Block, IndexerError>> {
+        let api = self.api.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let hash = api
+                    .get_block_hash(Some(number))
+                    .map_err(node_error)?
+                    .ok_or_else(|| IndexerError::Node(format!("block {} not found", number)))?;
+                let timestamp = api
+                    .get_storage::<u64>("Timestamp", "Now", Some(hash))
+                    .map_err(node_error)?
+                    .unwrap_or_default();
+                let events = api
+                    .fetch_events_from_block(hash)
+                    .map_err(node_error)?
+                    .iter()
+                    .map(|event| {
+                        event.map_err(node_error).map(|event| RawEvent {
+                            pallet: event.pallet_name().to_owned(),
+                            variant: event.variant_name().to_owned(),
+                            fields: event.field_bytes().to_vec(),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Block {
+                    number,
+                    timestamp,
+                    events,
+                })
+            })
+            .await
+            .map_err(|e| IndexerError::Node(e.to_string()))?
+        })
+    }
+}
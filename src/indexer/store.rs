@@ -0,0 +1,259 @@
+// SQLite storage of indexed trades and orders
+// Amounts are u128 and kept as decimal text, SQLite integers are only 64 bits wide
+
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+use super::IndexerError;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS trades (
+        block INTEGER NOT NULL,
+        event_index INTEGER NOT NULL,
+        timestamp INTEGER NOT NULL,
+        venue TEXT NOT NULL,
+        trade_id INTEGER,
+        maker TEXT,
+        taker TEXT NOT NULL,
+        bought TEXT NOT NULL,
+        sold TEXT NOT NULL,
+        amount_bought TEXT NOT NULL,
+        amount_sold TEXT NOT NULL,
+        fee TEXT NOT NULL,
+        PRIMARY KEY (block, event_index)
+    );
+    CREATE INDEX IF NOT EXISTS trades_by_pair ON trades (bought, sold, timestamp);
+    CREATE INDEX IF NOT EXISTS trades_by_maker ON trades (maker, timestamp);
+    CREATE INDEX IF NOT EXISTS trades_by_taker ON trades (taker, timestamp);
+
+    CREATE TABLE IF NOT EXISTS orders (
+        order_id INTEGER PRIMARY KEY,
+        block INTEGER NOT NULL,
+        timestamp INTEGER NOT NULL,
+        creator TEXT NOT NULL,
+        token_in TEXT NOT NULL,
+        token_out TEXT NOT NULL,
+        amount_in TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS orders_by_creator ON orders (creator, timestamp);
+
+    CREATE TABLE IF NOT EXISTS cursor (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        block INTEGER NOT NULL
+    );
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Venue {
+    Book,
+    Pool,
+}
+
+/// One fill, from the taker's side: the taker bought `amount_bought` of `bought`
+/// for `amount_sold` of `sold`. Pool swaps have no maker or trade ID.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct TradeRecord {
+    pub block: u32,
+    pub event_index: u32,
+    /// Milliseconds, from `Timestamp::Now` of the block
+    pub timestamp: u64,
+    pub venue: Venue,
+    pub trade_id: Option<u64>,
+    pub maker: Option<String>,
+    pub taker: String,
+    pub bought: String,
+    pub sold: String,
+    pub amount_bought: u128,
+    pub amount_sold: u128,
+    /// Taker fee, in `bought` on the book and in `sold` in a pool
+    pub fee: u128,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OrderRecord {
+    pub order_id: u64,
+    pub block: u32,
+    pub timestamp: u64,
+    pub creator: String,
+    pub token_in: String,
+    pub token_out: String,
+    pub amount_in: u128,
+}
+
+fn db_error(e: rusqlite::Error) -> IndexerError {
+    IndexerError::Database(e.to_string())
+}
+
+fn amount(row: &Row, column: &str) -> rusqlite::Result<u128> {
+    let text: String = row.get(column)?;
+    text.parse()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+fn trade_from_row(row: &Row) -> rusqlite::Result<TradeRecord> {
+    let venue: String = row.get("venue")?;
+    Ok(TradeRecord {
+        block: row.get("block")?,
+        event_index: row.get("event_index")?,
+        timestamp: row.get::<_, i64>("timestamp")? as u64,
+        venue: if venue == "book" { Venue::Book } else { Venue::Pool },
+        trade_id: row.get::<_, Option<i64>>("trade_id")?.map(|id| id as u64),
+        maker: row.get("maker")?,
+        taker: row.get("taker")?,
+        bought: row.get("bought")?,
+        sold: row.get("sold")?,
+        amount_bought: amount(row, "amount_bought")?,
+        amount_sold: amount(row, "amount_sold")?,
+        fee: amount(row, "fee")?,
+    })
+}
+
+fn order_from_row(row: &Row) -> rusqlite::Result<OrderRecord> {
+    Ok(OrderRecord {
+        order_id: row.get::<_, i64>("order_id")? as u64,
+        block: row.get("block")?,
+        timestamp: row.get::<_, i64>("timestamp")? as u64,
+        creator: row.get("creator")?,
+        token_in: row.get("token_in")?,
+        token_out: row.get("token_out")?,
+        amount_in: amount(row, "amount_in")?,
+    })
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IndexerError> {
+        Self::init(Connection::open(path).map_err(db_error)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, IndexerError> {
+        Self::init(Connection::open_in_memory().map_err(db_error)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, IndexerError> {
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(Self { conn })
+    }
+
+    /// Last block fully indexed
+    pub fn cursor(&self) -> Result<Option<u32>, IndexerError> {
+        self.conn
+            .query_row("SELECT block FROM cursor WHERE id = 0", [], |row| row.get(0))
+            .optional()
+            .map_err(db_error)
+    }
+
+    /// Store everything indexed from block `number` and advance the cursor to it
+    /// in one transaction, so a restart never indexes a block twice or skips one
+    pub fn insert_block(
+        &mut self,
+        number: u32,
+        trades: &[TradeRecord],
+        orders: &[OrderRecord],
+    ) -> Result<(), IndexerError> {
+        let tx = self.conn.transaction().map_err(db_error)?;
+        for trade in trades {
+            tx.execute(
+                "INSERT OR REPLACE INTO trades (block, event_index, timestamp, venue, trade_id, maker, taker,
+                     bought, sold, amount_bought, amount_sold, fee)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    trade.block,
+                    trade.event_index,
+                    trade.timestamp as i64,
+                    if trade.venue == Venue::Book { "book" } else { "pool" },
+                    trade.trade_id.map(|id| id as i64),
+                    trade.maker,
+                    trade.taker,
+                    trade.bought,
+                    trade.sold,
+                    trade.amount_bought.to_string(),
+                    trade.amount_sold.to_string(),
+                    trade.fee.to_string(),
+                ],
+            )
+            .map_err(db_error)?;
+        }
+        for order in orders {
+            tx.execute(
+                "INSERT OR REPLACE INTO orders (order_id, block, timestamp, creator, token_in, token_out, amount_in)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    order.order_id as i64,
+                    order.block,
+                    order.timestamp as i64,
+                    order.creator,
+                    order.token_in,
+                    order.token_out,
+                    order.amount_in.to_string(),
+                ],
+            )
+            .map_err(db_error)?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO cursor (id, block) VALUES (0, ?1)",
+            params![number],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)
+    }
+
+    /// Trades between `token_a` and `token_b` in either direction, oldest first
+    pub fn pair_trades(
+        &self,
+        token_a: &str,
+        token_b: &str,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<TradeRecord>, IndexerError> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT * FROM trades
+                 WHERE ((bought = ?1 AND sold = ?2) OR (bought = ?2 AND sold = ?1))
+                     AND timestamp >= ?3 AND timestamp < ?4
+                 ORDER BY timestamp, block, event_index",
+            )
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(
+                params![token_a, token_b, from as i64, to.min(i64::MAX as u64) as i64],
+                trade_from_row,
+            )
+            .map_err(db_error)?;
+        rows.collect::<Result<_, _>>().map_err(db_error)
+    }
+
+    /// Most recent trades `account` took part in on either side, newest first
+    pub fn account_trades(&self, account: &str, limit: u32) -> Result<Vec<TradeRecord>, IndexerError> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT * FROM trades WHERE maker = ?1 OR taker = ?1
+                 ORDER BY timestamp DESC, block DESC, event_index DESC LIMIT ?2",
+            )
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![account, limit], trade_from_row)
+            .map_err(db_error)?;
+        rows.collect::<Result<_, _>>().map_err(db_error)
+    }
+
+    /// Most recent orders placed by `account`, newest first
+    pub fn account_orders(&self, account: &str, limit: u32) -> Result<Vec<OrderRecord>, IndexerError> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT * FROM orders WHERE creator = ?1 ORDER BY order_id DESC LIMIT ?2")
+            .map_err(db_error)?;
+        let rows = statement
+            .query_map(params![account, limit], order_from_row)
+            .map_err(db_error)?;
+        rows.collect::<Result<_, _>>().map_err(db_error)
+    }
+}
@@ -0,0 +1,10 @@
+// Nourish Chain off-chain services
+// Client-side components that run alongside the NRSH and ELXR parachains
+// Copyright © 2025 NRSH/ELXR
+
+pub mod chain_events;
+pub mod indexer;
+pub mod node;
+pub mod oracle_client;
+pub mod provider_vault;
+pub mod router;
@@ -0,0 +1,56 @@
+// Access to contracts on the node shared by the off-chain services
+
+use codec::{Compact, Decode, Encode};
+use sp_core::crypto::AccountId32;
+use substrate_api_client::{
+    ac_primitives::AssetRuntimeConfig,
+    rpc::{JsonrpseeClient, Request},
+    rpc_params, Api,
+};
+
+type NodeApi = Api<AssetRuntimeConfig, JsonrpseeClient>;
+
+fn debug_string(e: impl std::fmt::Debug) -> String {
+    format!("{:?}", e)
+}
+
+/// Dry-run a contract message through the `ContractsApi_call` runtime API and
+/// return its SCALE-encoded output
+pub fn dry_run(api: &NodeApi, origin: &AccountId32, contract: &AccountId32, data: Vec<u8>) -> Result<Vec<u8>, String> {
+    let params = (
+        origin.clone(),
+        contract.clone(),
+        0u128,
+        None::<(Compact<u64>, Compact<u64>)>,
+        None::<u128>,
+        data,
+    )
+        .encode();
+    let hex_params = format!("0x{}", params.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+    let result: String = api
+        .client()
+        .request("state_call", rpc_params!["ContractsApi_call", hex_params])
+        .map_err(debug_string)?;
+    let hex = result.trim_start_matches("0x");
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(debug_string)?;
+
+    // ContractExecResult { gas_consumed, gas_required, storage_deposit, debug_message, result }
+    let mut input = &bytes[..];
+    let _gas = <[(Compact<u64>, Compact<u64>); 2]>::decode(&mut input).map_err(debug_string)?;
+    let _storage_deposit = <(u8, u128)>::decode(&mut input).map_err(debug_string)?;
+    let _debug_message = <Vec<u8>>::decode(&mut input).map_err(debug_string)?;
+    if u8::decode(&mut input).map_err(debug_string)? != 0 {
+        return Err("dry run failed".into());
+    }
+    // ExecReturnValue { flags, data }, a set first bit means the message reverted
+    let flags = u32::decode(&mut input).map_err(debug_string)?;
+    let data = <Vec<u8>>::decode(&mut input).map_err(debug_string)?;
+    if flags & 1 != 0 {
+        return Err("dry run reverted".into());
+    }
+    Ok(data)
+}
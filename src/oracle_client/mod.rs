@@ -0,0 +1,401 @@
+// Off-chain validator client for DaemonlessOracle
+// Polls pluggable price sources, normalizes quotes to per-gallon prices,
+// aggregates them per feed, signs the result and submits it on schedule
+
+pub mod normalize;
+pub mod signer;
+pub mod sources;
+pub mod submitter;
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use serde::Deserialize;
+use thiserror::Error;
+
+use normalize::{to_per_gallon, NormalizedQuote};
+use signer::{feed_id, PricePayload, SignedSubmission, SubmissionSigner};
+use sources::{DexPairConfig, JsonQuoteMapping, PriceSource};
+use submitter::Submitter;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("source {0} failed: {1}")]
+    Source(String, String),
+    #[error("invalid quote: {0}")]
+    InvalidQuote(String),
+    #[error("signer error: {0}")]
+    Signer(String),
+    #[error("node error: {0}")]
+    Node(String),
+    #[error("config error: {0}")]
+    Config(String),
+}
+
+/// Validator configuration, loaded from JSON
+#[derive(Clone, Debug, Deserialize)]
+pub struct ValidatorConfig {
+    /// Node websocket endpoint
+    pub node_url: String,
+    /// SS58 address of the deployed DaemonlessOracle contract
+    pub oracle_contract: String,
+    /// Seconds between polling rounds
+    pub interval_secs: u64,
+    /// Quotes older than this are ignored
+    pub max_quote_age_secs: u64,
+    /// Minimum number of fresh quotes needed to publish a feed
+    #[serde(default = "default_min_sources")]
+    pub min_sources: usize,
+    /// Publish when the aggregate moves by at least this many basis points...
+    #[serde(default = "default_deviation_bps")]
+    pub deviation_bps: u32,
+    /// ...or when this many seconds have passed since the last publication
+    #[serde(default = "default_heartbeat_secs")]
+    pub heartbeat_secs: u64,
+    /// File holding the next attestation nonce; without it every start counts from 0
+    #[serde(default)]
+    pub nonce_file: Option<PathBuf>,
+    pub sources: Vec<SourceConfig>,
+}
+
+// Matches the ">5% deviation" event-triggered update in the price determination doc
+fn default_deviation_bps() -> u32 {
+    500
+}
+
+// Weekly recalculation
+fn default_heartbeat_secs() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_min_sources() -> usize {
+    1
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceConfig {
+    Csv { name: String, path: PathBuf },
+    HttpJson { name: String, url: String, quotes: Vec<JsonQuoteMapping> },
+    DexTwap { name: String, pair: DexPairConfig },
+}
+
+impl ValidatorConfig {
+    pub fn from_json(data: &str) -> Result<Self, ClientError> {
+        serde_json::from_str(data).map_err(|e| ClientError::Config(e.to_string()))
+    }
+}
+
+/// Aggregated price for one feed in a polling round
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeedUpdate {
+    pub feed: String,
+    pub price: u128,
+    pub confidence: u8,
+    pub observed_at: u64,
+    pub sources: usize,
+}
+
+/// Median of the fresh quotes per feed. Confidence starts at 100 and drops by the
+/// widest deviation from the median in percent, so agreeing sources publish with
+/// high confidence and a lone outlier visibly lowers it.
+pub fn aggregate(quotes: &[NormalizedQuote], now: u64, max_age: u64, min_sources: usize) -> Vec<FeedUpdate> {
+    let mut by_feed: BTreeMap<&str, Vec<&NormalizedQuote>> = BTreeMap::new();
+    for quote in quotes {
+        if now.saturating_sub(quote.observed_at) > max_age {
+            debug!("dropping stale quote for {} from {}", quote.feed, quote.source);
+            continue;
+        }
+        by_feed.entry(quote.feed.as_str()).or_default().push(quote);
+    }
+
+    by_feed
+        .into_iter()
+        .filter(|(_, quotes)| quotes.len() >= min_sources.max(1))
+        .map(|(feed, quotes)| {
+            let mut prices: Vec<u128> = quotes.iter().map(|q| q.price_per_gallon).collect();
+            prices.sort_unstable();
+            let mid = prices.len() / 2;
+            let median = if prices.len() % 2 == 0 {
+                (prices[mid - 1] + prices[mid]) / 2
+            } else {
+                prices[mid]
+            };
+
+            let max_deviation_pct = prices
+                .iter()
+                .map(|p| p.abs_diff(median).saturating_mul(100) / median.max(1))
+                .max()
+                .unwrap_or(0);
+
+            FeedUpdate {
+                feed: feed.to_owned(),
+                price: median,
+                confidence: 100u128.saturating_sub(max_deviation_pct) as u8,
+                observed_at: quotes.iter().map(|q| q.observed_at).max().unwrap_or(now),
+                sources: quotes.len(),
+            }
+        })
+        .collect()
+}
+
+/// Next nonce stored in `path`, 0 if there is none yet
+fn load_nonce(path: &std::path::Path) -> u64 {
+    match std::fs::read_to_string(path) {
+        Ok(data) => data.trim().parse().unwrap_or_else(|e| {
+            warn!("ignoring unreadable nonce in {}: {}", path.display(), e);
+            0
+        }),
+        Err(_) => 0,
+    }
+}
+
+/// The validator daemon
+pub struct Validator {
+    sources: Vec<Box<dyn PriceSource>>,
+    signer: SubmissionSigner,
+    submitter: Box<dyn Submitter>,
+    max_quote_age: u64,
+    min_sources: usize,
+    deviation_bps: u32,
+    heartbeat: u64,
+    nonce: u64,
+    nonce_file: Option<PathBuf>,
+    // feed -> (price, published_at)
+    last_published: HashMap<String, (u128, u64)>,
+}
+
+impl Validator {
+    pub fn new(
+        config: &ValidatorConfig,
+        sources: Vec<Box<dyn PriceSource>>,
+        signer: SubmissionSigner,
+        submitter: Box<dyn Submitter>,
+    ) -> Self {
+        Self {
+            sources,
+            signer,
+            submitter,
+            max_quote_age: config.max_quote_age_secs,
+            min_sources: config.min_sources,
+            deviation_bps: config.deviation_bps,
+            heartbeat: config.heartbeat_secs,
+            nonce: config.nonce_file.as_deref().map_or(0, load_nonce),
+            nonce_file: config.nonce_file.clone(),
+            last_published: HashMap::new(),
+        }
+    }
+
+    /// Record the next nonce so a restart does not reuse this one
+    fn advance_nonce(&mut self) {
+        self.nonce += 1;
+        if let Some(path) = &self.nonce_file {
+            if let Err(e) = std::fs::write(path, self.nonce.to_string()) {
+                warn!("storing nonce {} in {} failed: {}", self.nonce, path.display(), e);
+            }
+        }
+    }
+
+    fn should_publish(&self, update: &FeedUpdate, now: u64) -> bool {
+        match self.last_published.get(&update.feed) {
+            None => true,
+            Some(&(price, published_at)) => {
+                let moved_bps = update.price.abs_diff(price).saturating_mul(10_000) / price.max(1);
+                moved_bps >= self.deviation_bps as u128 || now.saturating_sub(published_at) >= self.heartbeat
+            }
+        }
+    }
+
+    /// Run a single polling round at unix time `now` and return what was submitted.
+    /// A failing source is logged and skipped; a failing submission is logged and
+    /// retried on the next round.
+    pub async fn run_once(&mut self, now: u64) -> Vec<SignedSubmission> {
+        let mut quotes = Vec::new();
+        for source in &self.sources {
+            match source.fetch(now).await {
+                Ok(raw) => {
+                    for quote in raw {
+                        match to_per_gallon(&quote) {
+                            Ok(normalized) => quotes.push(normalized),
+                            Err(e) => warn!("{}", e),
+                        }
+                    }
+                }
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        let mut submitted = Vec::new();
+        for update in aggregate(&quotes, now, self.max_quote_age, self.min_sources) {
+            if !self.should_publish(&update, now) {
+                debug!("{} unchanged at {}, skipping", update.feed, update.price);
+                continue;
+            }
+
+            let payload = PricePayload {
+                feed_id: feed_id(&update.feed),
+                price: update.price,
+                confidence: update.confidence,
+                observed_at: update.observed_at,
+                nonce: self.nonce,
+            };
+            let submission = self.signer.sign(&update.feed, payload);
+
+            match self.submitter.submit(&submission).await {
+                Ok(()) => {
+                    info!(
+                        "submitted {} = {} (confidence {}, {} sources)",
+                        update.feed, update.price, update.confidence, update.sources
+                    );
+                    self.advance_nonce();
+                    self.last_published.insert(update.feed.clone(), (update.price, now));
+                    submitted.push(submission);
+                }
+                Err(e) => warn!("submitting {} failed: {}", update.feed, e),
+            }
+        }
+        submitted
+    }
+
+    /// Poll every `interval` until the process is stopped
+    pub async fn run(mut self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            self.run_once(now).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::BoxFuture;
+    use signer::verify;
+    use sources::{CsvSource, DexTwapSource, HttpJsonSource, ReserveReader};
+    use std::sync::{Arc, Mutex};
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/oracle");
+
+    #[derive(Clone, Default)]
+    struct RecordingSubmitter(Arc<Mutex<Vec<SignedSubmission>>>);
+
+    impl Submitter for RecordingSubmitter {
+        fn submit<'a>(&'a self, s: &'a SignedSubmission) -> BoxFuture<'a, Result<(), ClientError>> {
+            self.0.lock().unwrap().push(s.clone());
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    struct FixedReserves((u128, u128));
+
+    impl ReserveReader for FixedReserves {
+        fn reserves(&self, _pair: (u32, u32)) -> BoxFuture<'_, Result<(u128, u128), ClientError>> {
+            let reserves = self.0;
+            Box::pin(async move { Ok(reserves) })
+        }
+    }
+
+    // Replays the recorded HTTP response without a network round trip
+    struct RecordedHttp(HttpJsonSource, serde_json::Value);
+
+    impl PriceSource for RecordedHttp {
+        fn name(&self) -> &str {
+            self.0.name()
+        }
+
+        fn fetch(&self, now: u64) -> BoxFuture<'_, Result<Vec<normalize::RawQuote>, ClientError>> {
+            Box::pin(async move { self.0.extract(&self.1, now) })
+        }
+    }
+
+    #[tokio::test]
+    async fn publishes_signed_medians_from_fixtures() {
+        let config = ValidatorConfig::from_json(
+            &std::fs::read_to_string(format!("{}/validator.json", FIXTURES)).unwrap(),
+        )
+        .unwrap();
+        let now = 1_700_000_600;
+
+        let mut sources: Vec<Box<dyn PriceSource>> = Vec::new();
+        for source in &config.sources {
+            match source {
+                SourceConfig::Csv { name, path } => {
+                    sources.push(Box::new(CsvSource::new(name.clone(), format!("{}/{}", FIXTURES, path.display()))))
+                }
+                SourceConfig::HttpJson { name, url, quotes } => {
+                    let body = std::fs::read_to_string(format!("{}/http_prices.json", FIXTURES)).unwrap();
+                    sources.push(Box::new(RecordedHttp(
+                        HttpJsonSource::new(name.clone(), url.clone(), quotes.clone()),
+                        serde_json::from_str(&body).unwrap(),
+                    )))
+                }
+                SourceConfig::DexTwap { name, pair } => {
+                    // 10_000 NRSH against 3_330_000 USDT, both 6 decimals: $333 per token
+                    let reader = Arc::new(FixedReserves((10_000_000_000, 3_330_000_000_000)));
+                    sources.push(Box::new(DexTwapSource::new(name.clone(), reader, pair.clone())))
+                }
+            }
+        }
+
+        let recorder = RecordingSubmitter::default();
+        let mut validator = Validator::new(
+            &config,
+            sources,
+            SubmissionSigner::from_uri("//Alice").unwrap(),
+            Box::new(recorder.clone()),
+        );
+
+        let submitted = validator.run_once(now).await;
+        assert_eq!(submitted.len(), 2);
+
+        let nrsh = submitted.iter().find(|s| s.feed == "NRSH/USD").unwrap();
+        assert_eq!(nrsh.payload.price, 333_000_000);
+        assert_eq!(nrsh.payload.feed_id, feed_id("NRSH/USD"));
+        assert!(verify(nrsh));
+
+        let elxr = submitted.iter().find(|s| s.feed == "ELXR/USD").unwrap();
+        assert_eq!(elxr.payload.price, 75_000_000);
+        assert_eq!(recorder.0.lock().unwrap().len(), 2);
+
+        // Nothing moved and the heartbeat has not elapsed
+        assert!(validator.run_once(now + 60).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn nonces_survive_a_restart() {
+        let mut config = ValidatorConfig::from_json(
+            &std::fs::read_to_string(format!("{}/validator.json", FIXTURES)).unwrap(),
+        )
+        .unwrap();
+        let nonce_file = std::env::temp_dir().join(format!("oracle-nonce-{}", std::process::id()));
+        let _ = std::fs::remove_file(&nonce_file);
+        config.nonce_file = Some(nonce_file.clone());
+        let now = 1_700_000_600;
+        let sources = || -> Vec<Box<dyn PriceSource>> {
+            vec![Box::new(CsvSource::new("producer-survey", format!("{}/prices.csv", FIXTURES)))]
+        };
+        let validator = |config: &ValidatorConfig| {
+            Validator::new(
+                config,
+                sources(),
+                SubmissionSigner::from_uri("//Alice").unwrap(),
+                Box::new(RecordingSubmitter::default()),
+            )
+        };
+
+        let first: Vec<u64> = validator(&config).run_once(now).await.iter().map(|s| s.payload.nonce).collect();
+        assert_eq!(first, vec![0, 1]);
+
+        // A restarted validator carries on where the last one stopped
+        let second: Vec<u64> = validator(&config).run_once(now).await.iter().map(|s| s.payload.nonce).collect();
+        assert_eq!(second, vec![2, 3]);
+        std::fs::remove_file(&nonce_file).unwrap();
+    }
+}
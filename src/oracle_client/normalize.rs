@@ -0,0 +1,128 @@
+// Unit normalization for oracle quotes
+// Every quote is converted to a fixed-point price per US gallon before aggregation
+
+use serde::{Deserialize, Serialize};
+
+use super::ClientError;
+
+/// Prices submitted to DaemonlessOracle are micro-USD (6 decimals)
+pub const PRICE_DECIMALS: u32 = 6;
+pub const PRICE_SCALE: f64 = 1_000_000.0;
+
+/// Millilitres in one US liquid gallon
+pub const GALLON_ML: f64 = 3_785.411_784;
+
+/// Commodity a quote refers to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Commodity {
+    /// Live spirulina culture (NRSH)
+    SpirulinaCulture,
+    /// Kombucha (ELXR)
+    Kombucha,
+}
+
+/// Volume unit a raw quote is denominated in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeUnit {
+    Gallon,
+    Liter,
+    Milliliter,
+    FluidOunce,
+}
+
+impl VolumeUnit {
+    pub fn millilitres(&self) -> f64 {
+        match self {
+            VolumeUnit::Gallon => GALLON_ML,
+            VolumeUnit::Liter => 1_000.0,
+            VolumeUnit::Milliliter => 1.0,
+            VolumeUnit::FluidOunce => 29.573_529_562_5,
+        }
+    }
+}
+
+/// A price as reported by a source, before normalization
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawQuote {
+    /// Feed name, e.g. "NRSH/USD"
+    pub feed: String,
+    pub commodity: Commodity,
+    /// Price in USD for `volume` of `unit`
+    pub price: f64,
+    pub volume: f64,
+    pub unit: VolumeUnit,
+    /// Unix timestamp (seconds) the source observed the price
+    pub observed_at: u64,
+    /// Name of the source that produced the quote
+    pub source: String,
+}
+
+/// A quote normalized to micro-USD per gallon
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NormalizedQuote {
+    pub feed: String,
+    pub commodity: Commodity,
+    pub price_per_gallon: u128,
+    pub observed_at: u64,
+    pub source: String,
+}
+
+/// Convert a raw quote to micro-USD per gallon
+pub fn to_per_gallon(quote: &RawQuote) -> Result<NormalizedQuote, ClientError> {
+    if !quote.price.is_finite() || quote.price < 0.0 {
+        return Err(ClientError::InvalidQuote(format!(
+            "{}: price {} is not a valid amount",
+            quote.source, quote.price
+        )));
+    }
+    if !quote.volume.is_finite() || quote.volume <= 0.0 {
+        return Err(ClientError::InvalidQuote(format!(
+            "{}: volume {} must be positive",
+            quote.source, quote.volume
+        )));
+    }
+
+    let per_ml = quote.price / (quote.volume * quote.unit.millilitres());
+    let per_gallon = (per_ml * GALLON_ML * PRICE_SCALE).round();
+
+    Ok(NormalizedQuote {
+        feed: quote.feed.clone(),
+        commodity: quote.commodity,
+        price_per_gallon: per_gallon as u128,
+        observed_at: quote.observed_at,
+        source: quote.source.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(price: f64, volume: f64, unit: VolumeUnit) -> RawQuote {
+        RawQuote {
+            feed: "ELXR/USD".into(),
+            commodity: Commodity::Kombucha,
+            price,
+            volume,
+            unit,
+            observed_at: 0,
+            source: "test".into(),
+        }
+    }
+
+    #[test]
+    fn converts_units_to_per_gallon() {
+        // $3.99 for a 16 oz bottle is $31.92 per gallon
+        let bottle = to_per_gallon(&quote(3.99, 16.0, VolumeUnit::FluidOunce)).unwrap();
+        assert_eq!(bottle.price_per_gallon, 31_920_000);
+
+        // $175 for 5 gallons of draft
+        let keg = to_per_gallon(&quote(175.0, 5.0, VolumeUnit::Gallon)).unwrap();
+        assert_eq!(keg.price_per_gallon, 35_000_000);
+
+        assert!(to_per_gallon(&quote(1.0, 0.0, VolumeUnit::Liter)).is_err());
+        assert!(to_per_gallon(&quote(-1.0, 1.0, VolumeUnit::Liter)).is_err());
+    }
+}
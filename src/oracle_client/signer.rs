@@ -0,0 +1,88 @@
+// Signing of oracle price submissions
+// The validator's sr25519 key signs both the contract call extrinsic and a detached
+// attestation over the SCALE-encoded payload, which is kept in the audit log
+
+use codec::Encode;
+use serde::Serialize;
+use sp_core::{crypto::Pair as _, hashing::blake2_256, sr25519};
+
+use super::ClientError;
+
+/// Feed identifiers on DaemonlessOracle are the blake2-256 hash of the feed name
+pub type FeedId = [u8; 32];
+
+pub fn feed_id(name: &str) -> FeedId {
+    blake2_256(name.as_bytes())
+}
+
+/// The data a validator attests to for one feed update
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Serialize)]
+pub struct PricePayload {
+    pub feed_id: FeedId,
+    /// Micro-USD per gallon
+    pub price: u128,
+    pub confidence: u8,
+    /// Unix timestamp (seconds) of the newest quote used
+    pub observed_at: u64,
+    /// Per-validator counter ordering this validator's attestations in the
+    /// audit log, kept across restarts in `ValidatorConfig::nonce_file`. The
+    /// contract never sees it, so it does not stop replays on-chain
+    pub nonce: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SignedSubmission {
+    pub feed: String,
+    pub payload: PricePayload,
+    #[serde(with = "hex_bytes")]
+    pub signer: [u8; 32],
+    #[serde(with = "hex_bytes")]
+    pub signature: [u8; 64],
+}
+
+pub struct SubmissionSigner {
+    pair: sr25519::Pair,
+}
+
+impl SubmissionSigner {
+    /// Load from a secret URI (mnemonic, hex seed or dev path such as `//Alice`)
+    pub fn from_uri(uri: &str) -> Result<Self, ClientError> {
+        let pair = sr25519::Pair::from_string(uri, None)
+            .map_err(|e| ClientError::Signer(format!("{:?}", e)))?;
+        Ok(Self { pair })
+    }
+
+    pub fn pair(&self) -> &sr25519::Pair {
+        &self.pair
+    }
+
+    pub fn public(&self) -> [u8; 32] {
+        self.pair.public().0
+    }
+
+    pub fn sign(&self, feed: &str, payload: PricePayload) -> SignedSubmission {
+        let signature = self.pair.sign(&payload.encode());
+        SignedSubmission {
+            feed: feed.to_owned(),
+            payload,
+            signer: self.public(),
+            signature: signature.0,
+        }
+    }
+}
+
+/// Check a detached attestation produced by `SubmissionSigner::sign`
+pub fn verify(submission: &SignedSubmission) -> bool {
+    let signature = sr25519::Signature::from_raw(submission.signature);
+    let public = sr25519::Public::from_raw(submission.signer);
+    sr25519::Pair::verify(&signature, submission.payload.encode(), &public)
+}
+
+mod hex_bytes {
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer, const N: usize>(bytes: &[u8; N], s: S) -> Result<S::Ok, S::Error> {
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        s.serialize_str(&format!("0x{}", hex))
+    }
+}
@@ -0,0 +1,286 @@
+// Pluggable price sources for the oracle validator
+// Each source returns raw quotes which are normalized and aggregated by the validator
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use futures::future::BoxFuture;
+use serde::Deserialize;
+
+use super::normalize::{Commodity, RawQuote, VolumeUnit};
+use super::ClientError;
+
+/// A source of raw commodity quotes
+pub trait PriceSource: Send + Sync {
+    /// Name used in logs and on every quote produced by this source
+    fn name(&self) -> &str;
+
+    /// Fetch the current quotes. `now` is the unix time (seconds) of the polling round.
+    fn fetch(&self, now: u64) -> BoxFuture<'_, Result<Vec<RawQuote>, ClientError>>;
+}
+
+// CSV source
+
+#[derive(Debug, Deserialize)]
+struct CsvRow {
+    feed: String,
+    commodity: Commodity,
+    price: f64,
+    volume: f64,
+    unit: VolumeUnit,
+    observed_at: u64,
+}
+
+/// Reads quotes from a CSV file with the header
+/// `feed,commodity,price,volume,unit,observed_at`.
+/// The file is re-read on every poll so it can be updated by an external process.
+pub struct CsvSource {
+    name: String,
+    path: PathBuf,
+}
+
+impl CsvSource {
+    pub fn new(name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self { name: name.into(), path: path.into() }
+    }
+
+    pub fn parse(&self, data: &[u8]) -> Result<Vec<RawQuote>, ClientError> {
+        let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(data);
+        let mut quotes = Vec::new();
+        for row in reader.deserialize::<CsvRow>() {
+            let row = row.map_err(|e| ClientError::Source(self.name.clone(), e.to_string()))?;
+            quotes.push(RawQuote {
+                feed: row.feed,
+                commodity: row.commodity,
+                price: row.price,
+                volume: row.volume,
+                unit: row.unit,
+                observed_at: row.observed_at,
+                source: self.name.clone(),
+            });
+        }
+        Ok(quotes)
+    }
+}
+
+impl PriceSource for CsvSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn fetch(&self, _now: u64) -> BoxFuture<'_, Result<Vec<RawQuote>, ClientError>> {
+        Box::pin(async move {
+            let data = tokio::fs::read(&self.path)
+                .await
+                .map_err(|e| ClientError::Source(self.name.clone(), e.to_string()))?;
+            self.parse(&data)
+        })
+    }
+}
+
+// HTTP JSON source
+
+/// Where to find a quote inside an HTTP JSON response
+#[derive(Clone, Debug, Deserialize)]
+pub struct JsonQuoteMapping {
+    pub feed: String,
+    pub commodity: Commodity,
+    /// JSON pointer (RFC 6901) to the numeric price, e.g. "/data/spirulina/price"
+    pub price_pointer: String,
+    /// Optional JSON pointer to a unix timestamp; the poll time is used when absent
+    pub timestamp_pointer: Option<String>,
+    /// Volume the quoted price is for
+    pub volume: f64,
+    pub unit: VolumeUnit,
+}
+
+/// Polls an HTTP endpoint returning JSON and extracts one quote per mapping
+pub struct HttpJsonSource {
+    name: String,
+    url: String,
+    mappings: Vec<JsonQuoteMapping>,
+    client: reqwest::Client,
+}
+
+impl HttpJsonSource {
+    pub fn new(name: impl Into<String>, url: impl Into<String>, mappings: Vec<JsonQuoteMapping>) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            mappings,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn extract(&self, body: &serde_json::Value, now: u64) -> Result<Vec<RawQuote>, ClientError> {
+        self.mappings
+            .iter()
+            .map(|mapping| {
+                let price = body
+                    .pointer(&mapping.price_pointer)
+                    .and_then(json_number)
+                    .ok_or_else(|| {
+                        ClientError::Source(
+                            self.name.clone(),
+                            format!("no numeric price at {}", mapping.price_pointer),
+                        )
+                    })?;
+                let observed_at = match &mapping.timestamp_pointer {
+                    Some(pointer) => body
+                        .pointer(pointer)
+                        .and_then(json_number)
+                        .map(|ts| ts as u64)
+                        .unwrap_or(now),
+                    None => now,
+                };
+                Ok(RawQuote {
+                    feed: mapping.feed.clone(),
+                    commodity: mapping.commodity,
+                    price,
+                    volume: mapping.volume,
+                    unit: mapping.unit,
+                    observed_at,
+                    source: self.name.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+// Price APIs disagree on whether numbers are quoted, accept both
+fn json_number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+impl PriceSource for HttpJsonSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn fetch(&self, now: u64) -> BoxFuture<'_, Result<Vec<RawQuote>, ClientError>> {
+        Box::pin(async move {
+            let body: serde_json::Value = self
+                .client
+                .get(&self.url)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| ClientError::Source(self.name.clone(), e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| ClientError::Source(self.name.clone(), e.to_string()))?;
+            self.extract(&body, now)
+        })
+    }
+}
+
+// DEX TWAP source
+
+/// Reads `(reserve_a, reserve_b)` for a pallet-liquidity-pool pair
+pub trait ReserveReader: Send + Sync {
+    fn reserves(&self, pair: (u32, u32)) -> BoxFuture<'_, Result<(u128, u128), ClientError>>;
+}
+
+/// Pool-side configuration for a DEX TWAP feed
+#[derive(Clone, Debug, Deserialize)]
+pub struct DexPairConfig {
+    pub feed: String,
+    pub commodity: Commodity,
+    /// Asset ID of the commodity token (e.g. NRSH)
+    pub base_asset: u32,
+    /// Asset ID of the USD-pegged quote token (e.g. USDT)
+    pub quote_asset: u32,
+    /// Decimals of the base and quote assets
+    pub base_decimals: u32,
+    pub quote_decimals: u32,
+    /// Volume of commodity one base token represents
+    pub volume_per_token: f64,
+    pub unit: VolumeUnit,
+    /// Averaging window in seconds
+    pub window_secs: u64,
+}
+
+/// Samples spot prices from a constant-product pool on every poll and reports
+/// the time-weighted average over the configured window
+pub struct DexTwapSource<R: ReserveReader> {
+    name: String,
+    reader: Arc<R>,
+    pair: DexPairConfig,
+    samples: Mutex<VecDeque<(u64, f64)>>,
+}
+
+impl<R: ReserveReader> DexTwapSource<R> {
+    pub fn new(name: impl Into<String>, reader: Arc<R>, pair: DexPairConfig) -> Self {
+        Self {
+            name: name.into(),
+            reader,
+            pair,
+            samples: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Record a spot sample and return the TWAP over the window ending at `now`
+    fn record(&self, now: u64, spot: f64) -> f64 {
+        let mut samples = self.samples.lock().expect("sample lock poisoned");
+        samples.push_back((now, spot));
+        let cutoff = now.saturating_sub(self.pair.window_secs);
+        // Keep the last sample before the cutoff so the window start is covered
+        while samples.len() > 1 && samples[1].0 <= cutoff {
+            samples.pop_front();
+        }
+
+        let mut weighted = 0.0;
+        let mut elapsed = 0u64;
+        for (&(start, price), &(end, _)) in samples.iter().zip(samples.iter().skip(1)) {
+            let duration = end.saturating_sub(start.max(cutoff));
+            weighted += price * duration as f64;
+            elapsed += duration;
+        }
+        if elapsed == 0 {
+            spot
+        } else {
+            weighted / elapsed as f64
+        }
+    }
+}
+
+impl<R: ReserveReader> PriceSource for DexTwapSource<R> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn fetch(&self, now: u64) -> BoxFuture<'_, Result<Vec<RawQuote>, ClientError>> {
+        Box::pin(async move {
+            let (base, quote) = (self.pair.base_asset, self.pair.quote_asset);
+            // pallet-liquidity-pool keys pools by sorted asset IDs
+            let (reserve_base, reserve_quote) = if base < quote {
+                self.reader.reserves((base, quote)).await?
+            } else {
+                let (a, b) = self.reader.reserves((quote, base)).await?;
+                (b, a)
+            };
+            if reserve_base == 0 || reserve_quote == 0 {
+                return Err(ClientError::Source(self.name.clone(), "pool has no liquidity".into()));
+            }
+
+            let base_units = reserve_base as f64 / 10f64.powi(self.pair.base_decimals as i32);
+            let quote_units = reserve_quote as f64 / 10f64.powi(self.pair.quote_decimals as i32);
+            let twap = self.record(now, quote_units / base_units);
+
+            Ok(vec![RawQuote {
+                feed: self.pair.feed.clone(),
+                commodity: self.pair.commodity,
+                price: twap,
+                volume: self.pair.volume_per_token,
+                unit: self.pair.unit,
+                observed_at: now,
+                source: self.name.clone(),
+            }])
+        })
+    }
+}
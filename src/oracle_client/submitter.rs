@@ -0,0 +1,124 @@
+// Submission of signed price updates to DaemonlessOracle
+// and reserve reads from pallet-liquidity-pool on the same node
+
+use std::sync::Arc;
+
+use codec::{Compact, Encode};
+use futures::future::BoxFuture;
+use sp_core::{crypto::AccountId32, hashing::blake2_256, sr25519};
+use sp_runtime::MultiAddress;
+use substrate_api_client::{
+    ac_primitives::{AssetRuntimeConfig, ExtrinsicSigner},
+    compose_extrinsic,
+    rpc::JsonrpseeClient,
+    Api, GetStorage, SubmitAndWatch, XtStatus,
+};
+
+use super::signer::{SignedSubmission, SubmissionSigner};
+use super::sources::ReserveReader;
+use super::ClientError;
+
+type NodeApi = Api<AssetRuntimeConfig, JsonrpseeClient>;
+
+/// Destination for signed price updates
+pub trait Submitter: Send + Sync {
+    fn submit<'a>(&'a self, submission: &'a SignedSubmission) -> BoxFuture<'a, Result<(), ClientError>>;
+}
+
+/// ink! message selector: first four bytes of blake2-256 of the message name
+pub fn selector(message: &str) -> [u8; 4] {
+    let hash = blake2_256(message.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Calls `submit_price_update` on a deployed DaemonlessOracle through pallet-contracts
+pub struct ContractSubmitter {
+    api: Arc<NodeApi>,
+    contract: AccountId32,
+    gas_limit: (u64, u64),
+}
+
+impl ContractSubmitter {
+    pub fn connect(url: &str, contract: AccountId32, signer: &SubmissionSigner) -> Result<Self, ClientError> {
+        let client = JsonrpseeClient::new(url).map_err(|e| ClientError::Node(format!("{:?}", e)))?;
+        let mut api = NodeApi::new(client).map_err(|e| ClientError::Node(format!("{:?}", e)))?;
+        api.set_signer(ExtrinsicSigner::<AssetRuntimeConfig>::new(signer.pair().clone()));
+        Ok(Self {
+            api: Arc::new(api),
+            contract,
+            gas_limit: (50_000_000_000, 1_000_000),
+        })
+    }
+
+    pub fn api(&self) -> Arc<NodeApi> {
+        self.api.clone()
+    }
+
+    fn call_data(submission: &SignedSubmission) -> Vec<u8> {
+        let mut data = selector("submit_price_update").to_vec();
+        submission.payload.feed_id.encode_to(&mut data);
+        submission.payload.price.encode_to(&mut data);
+        submission.payload.confidence.encode_to(&mut data);
+        data
+    }
+}
+
+impl Submitter for ContractSubmitter {
+    fn submit<'a>(&'a self, submission: &'a SignedSubmission) -> BoxFuture<'a, Result<(), ClientError>> {
+        let api = self.api.clone();
+        let contract = self.contract.clone();
+        let gas_limit = self.gas_limit;
+        let data = Self::call_data(submission);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                // Weight { ref_time, proof_size } encodes as two compact u64s
+                let xt = compose_extrinsic!(
+                    api,
+                    "Contracts",
+                    "call",
+                    MultiAddress::<AccountId32, ()>::Id(contract),
+                    Compact(0u128),
+                    (Compact(gas_limit.0), Compact(gas_limit.1)),
+                    None::<Compact<u128>>,
+                    data
+                );
+                api.submit_and_watch_extrinsic_until(xt, XtStatus::InBlock)
+                    .map(|_| ())
+                    .map_err(|e| ClientError::Node(format!("{:?}", e)))
+            })
+            .await
+            .map_err(|e| ClientError::Node(e.to_string()))?
+        })
+    }
+}
+
+/// Reads `LiquidityPools` storage of pallet-liquidity-pool
+pub struct NodeReserveReader {
+    api: Arc<NodeApi>,
+}
+
+impl NodeReserveReader {
+    pub fn new(api: Arc<NodeApi>) -> Self {
+        Self { api }
+    }
+}
+
+impl ReserveReader for NodeReserveReader {
+    fn reserves(&self, pair: (u32, u32)) -> BoxFuture<'_, Result<(u128, u128), ClientError>> {
+        let api = self.api.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                api.get_storage_map::<(u32, u32), (u128, u128)>("LiquidityPool", "LiquidityPools", pair, None)
+                    .map(|reserves| reserves.unwrap_or_default())
+                    .map_err(|e| ClientError::Node(format!("{:?}", e)))
+            })
+            .await
+            .map_err(|e| ClientError::Node(e.to_string()))?
+        })
+    }
+}
+
+/// On-chain account the validator submits from
+pub fn validator_account(signer: &SubmissionSigner) -> AccountId32 {
+    sr25519::Public::from_raw(signer.public()).into()
+}
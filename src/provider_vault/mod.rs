@@ -0,0 +1,365 @@
+// Client-side encryption of liquidity provider profiles for UnifiedLiquidityPool
+// Profiles are sealed to the pool's vault key with ML-KEM-768 and
+// XChaCha20-Poly1305 before they leave the provider's machine; only operators
+// holding the decapsulation key can open them
+
+pub mod node;
+
+use chacha20poly1305::aead::{rand_core::RngCore, Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use codec::{Decode, Encode};
+use ml_kem::kem::{Decapsulate, DecapsulationKey, EncapsulationKey};
+use ml_kem::{Ciphertext, EncapsulateDeterministic, Encoded, EncodedSizeUser, KemCore, MlKem768, MlKem768Params, B32};
+use sp_core::crypto::AccountId32;
+use thiserror::Error;
+
+/// Bound into every ciphertext with the pool, the provider and the key version,
+/// so stored data cannot be replayed for another account or key
+pub const DOMAIN: &[u8] = b"nourish-provider-vault/v1";
+pub const ENCAPSULATION_KEY_BYTES: usize = 1_184;
+pub const KEM_CIPHERTEXT_BYTES: usize = 1_088;
+/// Largest profile the pool stores
+pub const MAX_PLAINTEXT_BYTES: usize = 4_096;
+
+#[derive(Debug, Error)]
+pub enum VaultError {
+    #[error("invalid key: {0}")]
+    Key(String),
+    #[error("profile too large: {0} bytes")]
+    TooLarge(usize),
+    #[error("decryption failed, wrong key or tampered data")]
+    Decryption,
+    #[error("node error: {0}")]
+    Node(String),
+}
+
+/// `EncryptedData` of the pool, in field order
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct EncryptedData {
+    pub key_version: u32,
+    pub kem_ciphertext: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub nonce: [u8; 24],
+}
+
+/// Vault key as published by the pool's `vault_key`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultKey {
+    pub version: u32,
+    pub encapsulation_key: Vec<u8>,
+}
+
+fn associated_data(pool: &AccountId32, provider: &AccountId32, key_version: u32) -> Vec<u8> {
+    let mut aad = DOMAIN.to_vec();
+    aad.extend_from_slice(pool.as_ref());
+    aad.extend_from_slice(provider.as_ref());
+    aad.extend_from_slice(&key_version.to_le_bytes());
+    aad
+}
+
+/// Seal `profile` for `provider` to the pool's current vault key
+pub fn seal(
+    key: &VaultKey,
+    pool: &AccountId32,
+    provider: &AccountId32,
+    profile: &[u8],
+) -> Result<EncryptedData, VaultError> {
+    let mut message = [0u8; 32];
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut message);
+    OsRng.fill_bytes(&mut nonce);
+    seal_with(key, pool, provider, profile, message, nonce)
+}
+
+/// `seal` with the encapsulation randomness and nonce given, for known-answer tests
+fn seal_with(
+    key: &VaultKey,
+    pool: &AccountId32,
+    provider: &AccountId32,
+    profile: &[u8],
+    message: [u8; 32],
+    nonce: [u8; 24],
+) -> Result<EncryptedData, VaultError> {
+    if profile.len() > MAX_PLAINTEXT_BYTES {
+        return Err(VaultError::TooLarge(profile.len()));
+    }
+    let encoded = Encoded::<EncapsulationKey<MlKem768Params>>::try_from(&key.encapsulation_key[..])
+        .map_err(|_| VaultError::Key(format!("expected {} bytes", ENCAPSULATION_KEY_BYTES)))?;
+    let encapsulation_key = EncapsulationKey::<MlKem768Params>::from_bytes(&encoded);
+    let (kem_ciphertext, shared_key) = encapsulation_key
+        .encapsulate_deterministic(&B32::from(message))
+        .map_err(|_| VaultError::Key("encapsulation failed".into()))?;
+
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(shared_key.as_slice()));
+    let aad = associated_data(pool, provider, key.version);
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: profile,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| VaultError::TooLarge(profile.len()))?;
+    Ok(EncryptedData {
+        key_version: key.version,
+        kem_ciphertext: kem_ciphertext.as_slice().to_vec(),
+        ciphertext,
+        nonce,
+    })
+}
+
+/// Decapsulation key of an operator, kept as the 64-byte ML-KEM seed `d || z`
+pub struct OperatorKey {
+    seed: [u8; 64],
+    decapsulation_key: DecapsulationKey<MlKem768Params>,
+    encapsulation_key: EncapsulationKey<MlKem768Params>,
+}
+
+impl OperatorKey {
+    pub fn generate() -> Self {
+        let mut seed = [0u8; 64];
+        OsRng.fill_bytes(&mut seed);
+        Self::from_seed(seed)
+    }
+
+    pub fn from_seed(seed: [u8; 64]) -> Self {
+        let d = B32::try_from(&seed[..32]).expect("32 bytes");
+        let z = B32::try_from(&seed[32..]).expect("32 bytes");
+        let (decapsulation_key, encapsulation_key) = MlKem768::generate_deterministic(&d, &z);
+        Self {
+            seed,
+            decapsulation_key,
+            encapsulation_key,
+        }
+    }
+
+    pub fn seed(&self) -> &[u8; 64] {
+        &self.seed
+    }
+
+    /// Public half, to publish with the pool's `set_vault_key`
+    pub fn encapsulation_key(&self) -> Vec<u8> {
+        self.encapsulation_key.as_bytes().as_slice().to_vec()
+    }
+
+    /// Open `data` stored by `provider` in `pool`. Data sealed to another
+    /// key, or for another pool or provider, fails as `Decryption`.
+    pub fn open(
+        &self,
+        pool: &AccountId32,
+        provider: &AccountId32,
+        data: &EncryptedData,
+    ) -> Result<Vec<u8>, VaultError> {
+        let kem_ciphertext =
+            Ciphertext::<MlKem768>::try_from(&data.kem_ciphertext[..]).map_err(|_| VaultError::Decryption)?;
+        let shared_key = self
+            .decapsulation_key
+            .decapsulate(&kem_ciphertext)
+            .map_err(|_| VaultError::Decryption)?;
+        let aad = associated_data(pool, provider, data.key_version);
+        XChaCha20Poly1305::new(Key::from_slice(shared_key.as_slice()))
+            .decrypt(
+                XNonce::from_slice(&data.nonce),
+                Payload {
+                    msg: &data.ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| VaultError::Decryption)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha3::{Digest, Sha3_256};
+
+    // Known answers computed with an independent FIPS 203 implementation,
+    // cross-checked against OpenSSL's ML-KEM-768
+
+    const SEED: [u8; 64] = {
+        let mut seed = [0u8; 64];
+        let mut i = 0;
+        while i < 64 {
+            seed[i] = i as u8;
+            i += 1;
+        }
+        seed
+    };
+    const MESSAGE: [u8; 32] = [0x42; 32];
+    const PROFILE: &[u8] = br#"{"name":"Spirulina Farm 7","jurisdiction":"US-CA"}"#;
+
+    fn nonce() -> [u8; 24] {
+        std::array::from_fn(|i| 0x60 + i as u8)
+    }
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn sha3(bytes: &[u8]) -> Vec<u8> {
+        Sha3_256::digest(bytes).to_vec()
+    }
+
+    fn pool() -> AccountId32 {
+        AccountId32::new([0xee; 32])
+    }
+
+    fn provider() -> AccountId32 {
+        AccountId32::new([0x01; 32])
+    }
+
+    fn vault_key(operator: &OperatorKey) -> VaultKey {
+        VaultKey {
+            version: 1,
+            encapsulation_key: operator.encapsulation_key(),
+        }
+    }
+
+    #[test]
+    fn ml_kem_matches_known_answers() {
+        let operator = OperatorKey::from_seed(SEED);
+        let encapsulation_key = operator.encapsulation_key();
+        assert_eq!(encapsulation_key.len(), ENCAPSULATION_KEY_BYTES);
+        assert_eq!(
+            sha3(&encapsulation_key),
+            unhex("a24e16d8f8f9383a95b77050f4d9fd2f5733eec1d63ef3c23ebf9918173669a7")
+        );
+
+        let sealed = seal_with(&vault_key(&operator), &pool(), &provider(), PROFILE, MESSAGE, nonce()).unwrap();
+        assert_eq!(sealed.kem_ciphertext.len(), KEM_CIPHERTEXT_BYTES);
+        assert_eq!(
+            sha3(&sealed.kem_ciphertext),
+            unhex("e9a0824664dba3f8f3c86ecb43a0c889030947ff01d276d04d46c204b62fc221")
+        );
+        let kem_ciphertext = Ciphertext::<MlKem768>::try_from(&sealed.kem_ciphertext[..]).unwrap();
+        let shared_key = operator.decapsulation_key.decapsulate(&kem_ciphertext).unwrap();
+        assert_eq!(
+            shared_key.as_slice().to_vec(),
+            unhex("b83e7f23b33f909715c7a50b0d4b1f6684d53e1f4b9056f803b29f058ccb5566")
+        );
+    }
+
+    #[test]
+    fn sealed_profiles_match_known_answers() {
+        let operator = OperatorKey::from_seed(SEED);
+        let sealed = seal_with(&vault_key(&operator), &pool(), &provider(), PROFILE, MESSAGE, nonce()).unwrap();
+        assert_eq!(sealed.key_version, 1);
+        assert_eq!(sealed.nonce, nonce());
+        assert_eq!(
+            sealed.ciphertext,
+            unhex(concat!(
+                "35bef763bb67126cbb19e5b8c5fe8e58e54dfa34d075d4a8b6ffa2b0938696cc",
+                "384a16953527f7f72e2e4226a2b95d2a165ba44211c31459b41bba6f3adc905d",
+                "4f9b"
+            ))
+        );
+        assert_eq!(operator.open(&pool(), &provider(), &sealed).unwrap(), PROFILE);
+    }
+
+    #[test]
+    fn aead_matches_the_xchacha_draft_vector() {
+        // draft-irtf-cfrg-xchacha-03, appendix A.3.1
+        let key: Vec<u8> = (0x80..0xa0).collect();
+        let nonce: Vec<u8> = (0x40..0x58).collect();
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        let aad = unhex("50515253c0c1c2c3c4c5c6c7");
+        let ciphertext = XChaCha20Poly1305::new_from_slice(&key)
+            .unwrap()
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: &aad,
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            ciphertext,
+            unhex(concat!(
+                "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb",
+                "731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452",
+                "2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9",
+                "21f9664c97637da9768812f615c68b13b52e",
+                "c0875924c1c7987947deafd8780acf49"
+            ))
+        );
+    }
+
+    #[test]
+    fn sealed_data_only_opens_for_its_pool_provider_and_key() {
+        let operator = OperatorKey::from_seed(SEED);
+        let key = vault_key(&operator);
+        let sealed = seal(&key, &pool(), &provider(), PROFILE).unwrap();
+        assert_eq!(operator.open(&pool(), &provider(), &sealed).unwrap(), PROFILE);
+        // Fresh randomness every time
+        assert_ne!(seal(&key, &pool(), &provider(), PROFILE).unwrap(), sealed);
+
+        let other = AccountId32::new([0x02; 32]);
+        assert!(matches!(
+            operator.open(&pool(), &other, &sealed),
+            Err(VaultError::Decryption)
+        ));
+        assert!(matches!(
+            operator.open(&other, &provider(), &sealed),
+            Err(VaultError::Decryption)
+        ));
+
+        let mut relabelled = sealed.clone();
+        relabelled.key_version = 2;
+        assert!(matches!(
+            operator.open(&pool(), &provider(), &relabelled),
+            Err(VaultError::Decryption)
+        ));
+        let mut tampered = sealed.clone();
+        tampered.ciphertext[0] ^= 1;
+        assert!(matches!(
+            operator.open(&pool(), &provider(), &tampered),
+            Err(VaultError::Decryption)
+        ));
+
+        let stranger = OperatorKey::from_seed([7; 64]);
+        assert!(matches!(
+            stranger.open(&pool(), &provider(), &sealed),
+            Err(VaultError::Decryption)
+        ));
+    }
+
+    #[test]
+    fn oversized_profiles_and_bad_keys_are_rejected() {
+        let operator = OperatorKey::from_seed(SEED);
+        let key = vault_key(&operator);
+        assert!(matches!(
+            seal(&key, &pool(), &provider(), &vec![0; MAX_PLAINTEXT_BYTES + 1]),
+            Err(VaultError::TooLarge(_))
+        ));
+        seal(&key, &pool(), &provider(), &vec![0; MAX_PLAINTEXT_BYTES]).unwrap();
+
+        let truncated = VaultKey {
+            version: 1,
+            encapsulation_key: key.encapsulation_key[1..].to_vec(),
+        };
+        assert!(matches!(
+            seal(&truncated, &pool(), &provider(), PROFILE),
+            Err(VaultError::Key(_))
+        ));
+    }
+
+    #[test]
+    fn encrypted_data_encodes_like_the_contract() {
+        let data = EncryptedData {
+            key_version: 3,
+            kem_ciphertext: vec![1, 2],
+            ciphertext: vec![3],
+            nonce: [9; 24],
+        };
+        let mut expected = vec![3, 0, 0, 0, 8, 1, 2, 4, 3];
+        expected.extend_from_slice(&[9; 24]);
+        assert_eq!(data.encode(), expected);
+        assert_eq!(EncryptedData::decode(&mut &expected[..]).unwrap(), data);
+    }
+}
@@ -0,0 +1,149 @@
+// Reading the vault key and stored profiles from UnifiedLiquidityPool, and
+// storing or rotating a provider's sealed profile
+
+use std::sync::Arc;
+
+use codec::{Compact, Decode, Encode};
+use futures::future::BoxFuture;
+use sp_core::{crypto::AccountId32, sr25519, Pair};
+use sp_runtime::MultiAddress;
+use substrate_api_client::{
+    ac_primitives::{AssetRuntimeConfig, ExtrinsicSigner},
+    compose_extrinsic,
+    rpc::JsonrpseeClient,
+    Api, SubmitAndWatch, XtStatus,
+};
+
+use super::{seal, EncryptedData, VaultError, VaultKey, ENCAPSULATION_KEY_BYTES};
+use crate::node::dry_run;
+use crate::oracle_client::submitter::selector;
+
+type NodeApi = Api<AssetRuntimeConfig, JsonrpseeClient>;
+
+fn node_error(e: impl std::fmt::Debug) -> VaultError {
+    VaultError::Node(format!("{:?}", e))
+}
+
+fn call_data(message: &str, args: impl Encode) -> Vec<u8> {
+    let mut data = selector(message).to_vec();
+    args.encode_to(&mut data);
+    data
+}
+
+pub struct PoolVault {
+    api: Arc<NodeApi>,
+    pool: AccountId32,
+    /// Dry-runs are made from this account, and extrinsics signed by it
+    account: AccountId32,
+    gas_limit: (u64, u64),
+}
+
+impl PoolVault {
+    /// Read-only access, enough for operators to fetch stored profiles
+    pub fn connect(url: &str, pool: AccountId32, origin: AccountId32) -> Result<Self, VaultError> {
+        let client = JsonrpseeClient::new(url).map_err(node_error)?;
+        let api = NodeApi::new(client).map_err(node_error)?;
+        Ok(Self {
+            api: Arc::new(api),
+            pool,
+            account: origin,
+            gas_limit: (50_000_000_000, 1_000_000),
+        })
+    }
+
+    /// Access as the provider `pair`, which can also store its own profile
+    pub fn connect_as(url: &str, pool: AccountId32, pair: sr25519::Pair) -> Result<Self, VaultError> {
+        let client = JsonrpseeClient::new(url).map_err(node_error)?;
+        let mut api = NodeApi::new(client).map_err(node_error)?;
+        let account = pair.public().into();
+        api.set_signer(ExtrinsicSigner::<AssetRuntimeConfig>::new(pair));
+        Ok(Self {
+            api: Arc::new(api),
+            pool,
+            account,
+            gas_limit: (50_000_000_000, 1_000_000),
+        })
+    }
+
+    pub fn pool(&self) -> &AccountId32 {
+        &self.pool
+    }
+
+    pub fn vault_key(&self) -> BoxFuture<'_, Result<VaultKey, VaultError>> {
+        let api = self.api.clone();
+        let (pool, origin) = (self.pool.clone(), self.account.clone());
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let output = dry_run(&api, &origin, &pool, call_data("vault_key", ())).map_err(VaultError::Node)?;
+                let (version, key) =
+                    <(u32, [u8; ENCAPSULATION_KEY_BYTES])>::decode(&mut &output[..]).map_err(node_error)?;
+                Ok(VaultKey {
+                    version,
+                    encapsulation_key: key.to_vec(),
+                })
+            })
+            .await
+            .map_err(|e| VaultError::Node(e.to_string()))?
+        })
+    }
+
+    pub fn provider_data<'a>(
+        &'a self,
+        provider: &'a AccountId32,
+    ) -> BoxFuture<'a, Result<Option<EncryptedData>, VaultError>> {
+        let api = self.api.clone();
+        let (pool, origin) = (self.pool.clone(), self.account.clone());
+        let data = call_data("provider_data", provider);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let output = dry_run(&api, &origin, &pool, data).map_err(VaultError::Node)?;
+                Option::<EncryptedData>::decode(&mut &output[..]).map_err(node_error)
+            })
+            .await
+            .map_err(|e| VaultError::Node(e.to_string()))?
+        })
+    }
+
+    /// Seal `profile` to the current vault key and store it, replacing any
+    /// profile stored before. Also how a provider rotates after a key change.
+    pub fn store<'a>(&'a self, profile: &'a [u8]) -> BoxFuture<'a, Result<EncryptedData, VaultError>> {
+        Box::pin(async move {
+            let key = self.vault_key().await?;
+            if key.version == 0 {
+                return Err(VaultError::Key("the pool has no vault key yet".into()));
+            }
+            let sealed = seal(&key, &self.pool, &self.account, profile)?;
+            let message = match self.provider_data(&self.account).await? {
+                Some(_) => "rotate_provider_data",
+                None => "store_provider_data",
+            };
+            self.submit(call_data(message, &sealed)).await?;
+            Ok(sealed)
+        })
+    }
+
+    fn submit(&self, data: Vec<u8>) -> BoxFuture<'_, Result<(), VaultError>> {
+        let api = self.api.clone();
+        let pool = self.pool.clone();
+        let gas_limit = self.gas_limit;
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let xt = compose_extrinsic!(
+                    api,
+                    "Contracts",
+                    "call",
+                    MultiAddress::<AccountId32, ()>::Id(pool),
+                    Compact(0u128),
+                    (Compact(gas_limit.0), Compact(gas_limit.1)),
+                    None::<Compact<u128>>,
+                    data
+                );
+                api.submit_and_watch_extrinsic_until(xt, XtStatus::InBlock)
+                    .map(|_| ())
+                    .map_err(node_error)
+            })
+            .await
+            .map_err(|e| VaultError::Node(e.to_string()))?
+        })
+    }
+}
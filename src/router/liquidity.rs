@@ -0,0 +1,130 @@
+// Liquidity snapshots of the two venues and their exact-output quotes
+// Both quotes replay the on-chain arithmetic, rounding included, so a
+// route quoted against an unchanged snapshot executes to the unit
+
+use futures::future::BoxFuture;
+
+use super::{RouterError, Token};
+
+/// Fee rates of the DEX are per million
+pub const FEE_PRECISION: u128 = 1_000_000;
+
+/// Trading fee of pallet-liquidity-pool: 0.369%, as a `Perquintill`
+pub const POOL_FEE_PARTS: u128 = 3_690_000_000_000_000;
+const PERQUINTILL: u128 = 1_000_000_000_000_000_000;
+
+/// One resting order of the DEX, seen from the taker: the maker sells
+/// `remaining` of its `amount_in` and asks `min_amount_out` for all of it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookLevel {
+    pub amount_in: u128,
+    pub min_amount_out: u128,
+    pub remaining: u128,
+}
+
+/// Resting orders selling the wanted token, best price first
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BookSnapshot {
+    pub levels: Vec<BookLevel>,
+    pub taker_fee_rate: u128,
+}
+
+/// Outcome of walking the book with a market order
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BookQuote {
+    /// Taker input consumed by the fills
+    pub amount_in: u128,
+    /// Received before the taker fee
+    pub received: u128,
+    /// Received after the taker fee
+    pub amount_out: u128,
+    /// Worst fill as `(received, paid)`, before fees
+    pub worst_fill: Option<(u128, u128)>,
+}
+
+impl BookSnapshot {
+    /// Replays `match_order` of the DEX for a market order of `amount_in`: each
+    /// maker is filled at its own price, the maker's side rounded down and the
+    /// taker's rounded up, and the taker fee comes out of what is received
+    pub fn quote(&self, amount_in: u128) -> BookQuote {
+        let mut quote = BookQuote::default();
+        let mut left = amount_in;
+        for level in &self.levels {
+            if left == 0 {
+                break;
+            }
+            let affordable = left.saturating_mul(level.amount_in) / level.min_amount_out.max(1);
+            let gives = level.remaining.min(affordable);
+            if gives == 0 {
+                break;
+            }
+            let pays = (gives.saturating_mul(level.min_amount_out) + level.amount_in - 1) / level.amount_in;
+            if pays > left {
+                break;
+            }
+            left -= pays;
+            quote.amount_in += pays;
+            quote.received += gives;
+            quote.amount_out += gives - gives.saturating_mul(self.taker_fee_rate) / FEE_PRECISION;
+            // Levels are sorted, but rounding can make a later fill marginally worse
+            quote.worst_fill = match quote.worst_fill {
+                Some((received, paid)) if received * pays <= gives * paid => Some((received, paid)),
+                _ => Some((gives, pays)),
+            };
+        }
+        quote
+    }
+
+    /// Maker liquidity left on this side of the book
+    pub fn depth(&self) -> u128 {
+        self.levels.iter().map(|level| level.remaining).sum()
+    }
+}
+
+/// Reserves of one constant-product pool, oriented along the swap
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolReserves {
+    pub reserve_in: u128,
+    pub reserve_out: u128,
+}
+
+impl PoolReserves {
+    /// Replays `LiquidityPool::swap`: the fee is floored off the input and the
+    /// output is `reserve_out * net / (reserve_in + net)` rounded down, which
+    /// never reaches `reserve_out`
+    pub fn quote(&self, amount_in: u128) -> u128 {
+        if amount_in == 0 || self.reserve_in == 0 || self.reserve_out == 0 {
+            return 0;
+        }
+        let net = amount_in - mul_floor(amount_in, POOL_FEE_PARTS);
+        // The pallet rejects swaps it cannot compute, so they quote nothing
+        match (self.reserve_out.checked_mul(net), self.reserve_in.checked_add(net)) {
+            (Some(numerator), Some(denominator)) => numerator / denominator,
+            _ => 0,
+        }
+    }
+}
+
+// `Perquintill::mul_floor` without overflowing on large amounts
+fn mul_floor(amount: u128, parts: u128) -> u128 {
+    let whole = amount / PERQUINTILL;
+    let rest = amount % PERQUINTILL;
+    whole * parts + rest.saturating_mul(parts / 1_000_000_000) / (PERQUINTILL / 1_000_000_000)
+}
+
+/// Source of venue snapshots for a token pair, `None` where the venue has no market
+pub trait LiquidityReader: Send + Sync {
+    /// Orders selling `token_out` for `token_in`
+    fn book<'a>(
+        &'a self,
+        token_in: &'a Token,
+        token_out: &'a Token,
+    ) -> BoxFuture<'a, Result<Option<BookSnapshot>, RouterError>>;
+
+    /// Pool reserves oriented from `token_in` to `token_out`
+    fn pool<'a>(
+        &'a self,
+        token_in: &'a Token,
+        token_out: &'a Token,
+    ) -> BoxFuture<'a, Result<Option<PoolReserves>, RouterError>>;
+}
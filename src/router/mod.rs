@@ -0,0 +1,711 @@
+// Smart order routing across the ZeroSpreadDEX order book and the
+// pallet-liquidity-pool constant-product pools
+// Quotes every simple path up to `max_hops`, splits each hop between the two
+// venues, and compares the realized output of an executed route with its quote
+
+pub mod liquidity;
+pub mod node;
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use sp_core::crypto::AccountId32;
+use thiserror::Error;
+
+use liquidity::{BookSnapshot, LiquidityReader, PoolReserves, FEE_PRECISION};
+
+/// Prices are reported in millionths of the output token per input token,
+/// the precision of the oracle and the DEX
+pub const PRICE_PRECISION: u128 = 1_000_000;
+
+#[derive(Debug, Error)]
+pub enum RouterError {
+    #[error("unknown token {0}")]
+    UnknownToken(String),
+    #[error("no route from {0} to {1}")]
+    NoRoute(String, String),
+    #[error("node error: {0}")]
+    Node(String),
+    #[error("config error: {0}")]
+    Config(String),
+}
+
+/// Router configuration, loaded from JSON
+#[derive(Clone, Debug, Deserialize)]
+pub struct RouterConfig {
+    /// Node websocket endpoint
+    pub node_url: String,
+    /// SS58 address of the deployed ZeroSpreadDEX contract
+    pub dex_contract: String,
+    pub tokens: Vec<TokenConfig>,
+    /// Longest path considered, ELXR -> NRSH -> USDT is two hops
+    #[serde(default = "default_max_hops")]
+    pub max_hops: usize,
+    /// Tolerated shortfall against the quote for every leg
+    #[serde(default = "default_slippage_bps")]
+    pub slippage_bps: u32,
+    /// Each hop is split between the venues in this many increments
+    #[serde(default = "default_split_steps")]
+    pub split_steps: u32,
+    /// Pool swaps are rejected once this many blocks have passed since submission
+    #[serde(default = "default_deadline_blocks")]
+    pub deadline_blocks: u32,
+}
+
+fn default_max_hops() -> usize {
+    3
+}
+
+fn default_slippage_bps() -> u32 {
+    50
+}
+
+fn default_split_steps() -> u32 {
+    20
+}
+
+pub(crate) fn default_deadline_blocks() -> u32 {
+    10
+}
+
+/// A token and its identifiers on each venue; either may be missing
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenConfig {
+    pub symbol: String,
+    /// Asset ID in pallet-liquidity-pool
+    pub pool_asset: Option<u32>,
+    /// SS58 address of the PSP22 contract traded on the DEX
+    pub dex_token: Option<String>,
+}
+
+impl RouterConfig {
+    pub fn from_json(data: &str) -> Result<Self, RouterError> {
+        serde_json::from_str(data).map_err(|e| RouterError::Config(e.to_string()))
+    }
+
+    pub fn tokens(&self) -> Result<Vec<Token>, RouterError> {
+        self.tokens.iter().map(TokenConfig::token).collect()
+    }
+}
+
+impl TokenConfig {
+    pub fn token(&self) -> Result<Token, RouterError> {
+        let dex_token = match &self.dex_token {
+            Some(address) => Some(
+                AccountId32::from_str(address)
+                    .map_err(|e| RouterError::Config(format!("{}: {}", self.symbol, e)))?
+                    .into(),
+            ),
+            None => None,
+        };
+        Ok(Token {
+            symbol: self.symbol.clone(),
+            pool_asset: self.pool_asset,
+            dex_token,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub symbol: String,
+    pub pool_asset: Option<u32>,
+    pub dex_token: Option<[u8; 32]>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Venue {
+    Book,
+    Pool,
+}
+
+/// The part of a hop sent to one venue
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Leg {
+    pub venue: Venue,
+    pub amount_in: u128,
+    pub expected_out: u128,
+    /// Limit passed to the venue: `min_amount_out` of the market order or the swap
+    pub min_out: u128,
+    /// Least the leg delivers without reverting, net of fees
+    pub guaranteed_out: u128,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hop {
+    pub token_in: Token,
+    pub token_out: Token,
+    pub legs: Vec<Leg>,
+}
+
+impl Hop {
+    pub fn amount_in(&self) -> u128 {
+        self.legs.iter().map(|leg| leg.amount_in).sum()
+    }
+
+    pub fn expected_out(&self) -> u128 {
+        self.legs.iter().map(|leg| leg.expected_out).sum()
+    }
+
+    pub fn guaranteed_out(&self) -> u128 {
+        self.legs.iter().map(|leg| leg.guaranteed_out).sum()
+    }
+}
+
+/// A quoted execution. Every hop after the first spends only what the previous
+/// one is guaranteed to deliver, so the batch cannot run short of funds halfway;
+/// whatever a hop delivers above that stays with the trader in the intermediate token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Route {
+    pub amount_in: u128,
+    pub hops: Vec<Hop>,
+}
+
+impl Route {
+    pub fn path(&self) -> Vec<&str> {
+        let mut path: Vec<&str> = self.hops.iter().map(|hop| hop.token_in.symbol.as_str()).collect();
+        if let Some(last) = self.hops.last() {
+            path.push(&last.token_out.symbol);
+        }
+        path
+    }
+
+    pub fn token_out(&self) -> Option<&Token> {
+        self.hops.last().map(|hop| &hop.token_out)
+    }
+
+    pub fn expected_out(&self) -> u128 {
+        self.hops.last().map(Hop::expected_out).unwrap_or(0)
+    }
+
+    pub fn min_out(&self) -> u128 {
+        self.hops.last().map(Hop::guaranteed_out).unwrap_or(0)
+    }
+
+    pub fn expected_price(&self) -> u128 {
+        price(self.expected_out(), self.amount_in)
+    }
+}
+
+fn price(amount_out: u128, amount_in: u128) -> u128 {
+    amount_out.saturating_mul(PRICE_PRECISION) / amount_in.max(1)
+}
+
+/// Expected against realized execution of a route
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionReport {
+    pub route: Route,
+    pub expected_out: u128,
+    pub realized_out: u128,
+    pub expected_price: u128,
+    pub realized_price: u128,
+    /// Shortfall of the realized output against the quote, negative when it beat it
+    pub slippage_bps: i64,
+}
+
+impl ExecutionReport {
+    pub fn new(route: Route, realized_out: u128) -> Self {
+        let expected_out = route.expected_out();
+        let shortfall = expected_out as i128 - realized_out as i128;
+        Self {
+            expected_out,
+            realized_out,
+            expected_price: route.expected_price(),
+            realized_price: price(realized_out, route.amount_in),
+            slippage_bps: (shortfall * 10_000 / expected_out.max(1) as i128) as i64,
+            route,
+        }
+    }
+}
+
+/// What each venue offers for one ordered pair
+#[derive(Clone, Debug, Default)]
+struct Market {
+    book: Option<BookSnapshot>,
+    pool: Option<PoolReserves>,
+}
+
+impl Market {
+    fn is_empty(&self) -> bool {
+        self.book.as_ref().map_or(true, |book| book.depth() == 0)
+            && self
+                .pool
+                .map_or(true, |pool| pool.reserve_in == 0 || pool.reserve_out == 0)
+    }
+
+    fn book_out(&self, amount_in: u128) -> u128 {
+        self.book.as_ref().map_or(0, |book| book.quote(amount_in).amount_out)
+    }
+
+    fn pool_out(&self, amount_in: u128) -> u128 {
+        self.pool.map_or(0, |pool| pool.quote(amount_in))
+    }
+}
+
+pub struct Router<R: LiquidityReader> {
+    reader: R,
+    tokens: Vec<Token>,
+    max_hops: usize,
+    slippage_bps: u32,
+    split_steps: u32,
+}
+
+impl<R: LiquidityReader> Router<R> {
+    pub fn new(config: &RouterConfig, tokens: Vec<Token>, reader: R) -> Self {
+        Self {
+            reader,
+            tokens,
+            max_hops: config.max_hops.max(1),
+            slippage_bps: config.slippage_bps,
+            split_steps: config.split_steps.max(1),
+        }
+    }
+
+    pub fn reader(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn token(&self, symbol: &str) -> Result<&Token, RouterError> {
+        self.tokens
+            .iter()
+            .find(|token| token.symbol == symbol)
+            .ok_or_else(|| RouterError::UnknownToken(symbol.to_owned()))
+    }
+
+    /// Best route for selling `amount_in` of `from` for `to`. Paths are compared by
+    /// expected output; on a tie the shorter path wins.
+    pub async fn quote(&self, from: &str, to: &str, amount_in: u128) -> Result<Route, RouterError> {
+        let from_index = self.index(from)?;
+        let to_index = self.index(to)?;
+        let no_route = || RouterError::NoRoute(from.to_owned(), to.to_owned());
+        if from_index == to_index || amount_in == 0 {
+            return Err(no_route());
+        }
+
+        let mut markets: HashMap<(usize, usize), Market> = HashMap::new();
+        let mut best: Option<Route> = None;
+        for path in self.paths(from_index, to_index) {
+            let mut hops = Vec::with_capacity(path.len() - 1);
+            let mut amount = amount_in;
+            for pair in path.windows(2) {
+                let key = (pair[0], pair[1]);
+                if !markets.contains_key(&key) {
+                    let market = self.market(key.0, key.1).await?;
+                    markets.insert(key, market);
+                }
+                let market = &markets[&key];
+                if market.is_empty() {
+                    break;
+                }
+                let legs = self.split(market, amount);
+                let hop = Hop {
+                    token_in: self.tokens[pair[0]].clone(),
+                    token_out: self.tokens[pair[1]].clone(),
+                    legs,
+                };
+                amount = hop.guaranteed_out();
+                hops.push(hop);
+                if amount == 0 {
+                    break;
+                }
+            }
+            if hops.len() + 1 != path.len() || amount == 0 {
+                continue;
+            }
+
+            let route = Route { amount_in, hops };
+            if best
+                .as_ref()
+                .map_or(true, |best| route.expected_out() > best.expected_out())
+            {
+                best = Some(route);
+            }
+        }
+        best.ok_or_else(no_route)
+    }
+
+    fn index(&self, symbol: &str) -> Result<usize, RouterError> {
+        self.tokens
+            .iter()
+            .position(|token| token.symbol == symbol)
+            .ok_or_else(|| RouterError::UnknownToken(symbol.to_owned()))
+    }
+
+    /// Simple paths of at most `max_hops` hops, shortest first
+    fn paths(&self, from: usize, to: usize) -> Vec<Vec<usize>> {
+        let mut paths = Vec::new();
+        let mut frontier = vec![vec![from]];
+        for _ in 0..self.max_hops {
+            let mut next = Vec::new();
+            for path in frontier {
+                for token in 0..self.tokens.len() {
+                    if path.contains(&token) {
+                        continue;
+                    }
+                    let mut extended = path.clone();
+                    extended.push(token);
+                    if token == to {
+                        paths.push(extended);
+                    } else {
+                        next.push(extended);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        paths
+    }
+
+    async fn market(&self, token_in: usize, token_out: usize) -> Result<Market, RouterError> {
+        let (token_in, token_out) = (&self.tokens[token_in], &self.tokens[token_out]);
+        Ok(Market {
+            book: self.reader.book(token_in, token_out).await?,
+            pool: self.reader.pool(token_in, token_out).await?,
+        })
+    }
+
+    /// Split `amount_in` between the venues one increment at a time, each going
+    /// to the venue whose output grows most from it. The split is kept only if it
+    /// beats sending everything to the better venue alone.
+    fn split(&self, market: &Market, amount_in: u128) -> Vec<Leg> {
+        let step = (amount_in + self.split_steps as u128 - 1) / self.split_steps as u128;
+        let (mut to_book, mut to_pool) = (0u128, 0u128);
+        while to_book + to_pool < amount_in {
+            let increment = step.min(amount_in - to_book - to_pool);
+            let book_gain = market
+                .book_out(to_book + increment)
+                .saturating_sub(market.book_out(to_book));
+            let pool_gain = market
+                .pool_out(to_pool + increment)
+                .saturating_sub(market.pool_out(to_pool));
+            if book_gain > pool_gain {
+                to_book += increment;
+            } else {
+                to_pool += increment;
+            }
+        }
+        // Whatever the book cannot absorb goes to the pool rather than being refunded
+        if market.pool.is_some() {
+            let consumed = market.book.as_ref().map_or(0, |book| book.quote(to_book).amount_in);
+            to_pool += to_book - consumed;
+            to_book = consumed;
+        }
+
+        let split = market.book_out(to_book) + market.pool_out(to_pool);
+        let all_book = market.book_out(amount_in);
+        let all_pool = market.pool_out(amount_in);
+        if all_pool >= split && all_pool >= all_book {
+            (to_book, to_pool) = (0, amount_in);
+        } else if all_book > split {
+            let consumed = market.book.as_ref().map_or(0, |book| book.quote(amount_in).amount_in);
+            (to_book, to_pool) = (consumed, 0);
+        }
+
+        let mut legs = Vec::new();
+        if let (Some(book), true) = (&market.book, to_book > 0) {
+            legs.push(self.book_leg(book, to_book));
+        }
+        if let (Some(pool), true) = (market.pool, to_pool > 0) {
+            legs.push(self.pool_leg(pool, to_pool));
+        }
+        legs.retain(|leg| leg.expected_out > 0);
+        legs
+    }
+
+    fn tolerate(&self, amount: u128) -> u128 {
+        amount.saturating_mul(10_000 - self.slippage_bps.min(10_000) as u128) / 10_000
+    }
+
+    // The DEX holds a market order's limit against every fill rather than the
+    // total, so the limit is the worst fill's price less the tolerance. The
+    // guarantee assumes the book still holds the quoted depth; if it does not,
+    // the order fills less and the next hop reverts the batch.
+    fn book_leg(&self, book: &BookSnapshot, amount_in: u128) -> Leg {
+        let quote = book.quote(amount_in);
+        let (received, paid) = quote.worst_fill.unwrap_or((0, 1));
+        let min_received = self.tolerate(quote.received);
+        Leg {
+            venue: Venue::Book,
+            amount_in: quote.amount_in,
+            expected_out: quote.amount_out,
+            min_out: self.tolerate(quote.amount_in.saturating_mul(received) / paid).max(1),
+            guaranteed_out: min_received - min_received.saturating_mul(book.taker_fee_rate) / FEE_PRECISION,
+        }
+    }
+
+    fn pool_leg(&self, pool: PoolReserves, amount_in: u128) -> Leg {
+        let expected_out = pool.quote(amount_in);
+        let min_out = self.tolerate(expected_out).max(1);
+        Leg {
+            venue: Venue::Pool,
+            amount_in,
+            expected_out,
+            min_out,
+            guaranteed_out: min_out,
+        }
+    }
+}
+
+/// Output of one executed leg, as reported by the venue's event
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fill {
+    /// `LiquidityPool::Swap`
+    Pool { asset_out: u32, amount_out: u128 },
+    /// `TradeExecuted` of the DEX with the trader as taker
+    Book {
+        token_out: [u8; 32],
+        amount: u128,
+        taker_fee: u128,
+    },
+}
+
+/// What the trader received of the route's final token
+pub fn realized_output(route: &Route, fills: &[Fill]) -> u128 {
+    let token = match route.token_out() {
+        Some(token) => token,
+        None => return 0,
+    };
+    fills
+        .iter()
+        .map(|fill| match fill {
+            Fill::Pool { asset_out, amount_out } if Some(*asset_out) == token.pool_asset => *amount_out,
+            Fill::Book {
+                token_out,
+                amount,
+                taker_fee,
+            } if Some(*token_out) == token.dex_token => amount - taker_fee,
+            _ => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::liquidity::BookLevel;
+    use super::*;
+    use futures::future::BoxFuture;
+
+    #[derive(Default)]
+    struct FixedLiquidity {
+        books: HashMap<(&'static str, &'static str), BookSnapshot>,
+        pools: HashMap<(&'static str, &'static str), (u128, u128)>,
+    }
+
+    impl FixedLiquidity {
+        fn with_pool(mut self, a: &'static str, b: &'static str, reserve_a: u128, reserve_b: u128) -> Self {
+            self.pools.insert((a, b), (reserve_a, reserve_b));
+            self
+        }
+
+        // `levels` are (sells, asks) of `token_out` for `token_in`
+        fn with_book(mut self, token_in: &'static str, token_out: &'static str, levels: &[(u128, u128)]) -> Self {
+            let levels = levels
+                .iter()
+                .map(|&(amount_in, min_amount_out)| BookLevel {
+                    amount_in,
+                    min_amount_out,
+                    remaining: amount_in,
+                })
+                .collect();
+            self.books.insert(
+                (token_in, token_out),
+                BookSnapshot {
+                    levels,
+                    taker_fee_rate: 369,
+                },
+            );
+            self
+        }
+    }
+
+    impl LiquidityReader for FixedLiquidity {
+        fn book<'a>(
+            &'a self,
+            token_in: &'a Token,
+            token_out: &'a Token,
+        ) -> BoxFuture<'a, Result<Option<BookSnapshot>, RouterError>> {
+            let book = self
+                .books
+                .get(&(token_in.symbol.as_str(), token_out.symbol.as_str()))
+                .cloned();
+            Box::pin(async move { Ok(book) })
+        }
+
+        fn pool<'a>(
+            &'a self,
+            token_in: &'a Token,
+            token_out: &'a Token,
+        ) -> BoxFuture<'a, Result<Option<PoolReserves>, RouterError>> {
+            let (a, b) = (token_in.symbol.as_str(), token_out.symbol.as_str());
+            let pool = match (self.pools.get(&(a, b)), self.pools.get(&(b, a))) {
+                (Some(&(reserve_in, reserve_out)), _) | (_, Some(&(reserve_out, reserve_in))) => Some(PoolReserves {
+                    reserve_in,
+                    reserve_out,
+                }),
+                _ => None,
+            };
+            Box::pin(async move { Ok(pool) })
+        }
+    }
+
+    fn router(liquidity: FixedLiquidity) -> Router<FixedLiquidity> {
+        let config = RouterConfig::from_json(
+            r#"{
+                "node_url": "ws://127.0.0.1:9944",
+                "dex_contract": "5C4hrfjw9DjXZTzV3MwzrrAr9P1MJhSrvWGWqi1eSuyUpnhM",
+                "tokens": [
+                    { "symbol": "NRSH", "pool_asset": 1, "dex_token": "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty" },
+                    { "symbol": "ELXR", "pool_asset": 2, "dex_token": "5FLSigC9HGRKVhB9FiEo4Y3koPsNmBmLJbpXg2mp1hXcS59Y" },
+                    { "symbol": "USDT", "pool_asset": 3 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let tokens = config.tokens().unwrap();
+        Router::new(&config, tokens, liquidity)
+    }
+
+    #[test]
+    fn book_quote_matches_dex_rounding() {
+        let book = BookSnapshot {
+            levels: vec![
+                // 3 ELXR per NRSH, then 7 for 2
+                BookLevel {
+                    amount_in: 100,
+                    min_amount_out: 300,
+                    remaining: 40,
+                },
+                BookLevel {
+                    amount_in: 2,
+                    min_amount_out: 7,
+                    remaining: 2,
+                },
+            ],
+            taker_fee_rate: 0,
+        };
+        // 40 NRSH for 120 ELXR, then one NRSH priced at 3.5 costs 4
+        let quote = book.quote(125);
+        assert_eq!(quote.amount_in, 124);
+        assert_eq!(quote.amount_out, 41);
+        assert_eq!(quote.worst_fill, Some((1, 4)));
+
+        // The second level is exhausted, the rest would be refunded
+        assert_eq!(book.quote(10_000).amount_out, 42);
+    }
+
+    #[test]
+    fn pool_quote_matches_pallet() {
+        let pool = PoolReserves {
+            reserve_in: 1_000_000,
+            reserve_out: 2_000_000,
+        };
+        // fee = floor(10_000 * 0.369%) = 36
+        let out = pool.quote(10_000);
+        assert_eq!(out, 2_000_000 * (10_000 - 36) / (1_000_000 + 10_000 - 36));
+        assert_eq!(pool.quote(0), 0);
+        // However much is sold, the pool keeps some of its output
+        assert!(pool.quote(1 << 100) < pool.reserve_out);
+    }
+
+    #[tokio::test]
+    async fn split_beats_either_venue_alone() {
+        // Book: 5_000 ELXR at 2 NRSH each; pool prices ELXR at 2 NRSH before fees
+        let liquidity = FixedLiquidity::default()
+            .with_pool("NRSH", "ELXR", 1_000_000, 500_000)
+            .with_book("NRSH", "ELXR", &[(5_000, 10_000)]);
+        let router = router(liquidity);
+        let amount_in = 40_000;
+
+        let route = router.quote("NRSH", "ELXR", amount_in).await.unwrap();
+        assert_eq!(route.path(), vec!["NRSH", "ELXR"]);
+        let legs = &route.hops[0].legs;
+        assert_eq!(legs.len(), 2);
+        assert_eq!(route.hops[0].amount_in(), amount_in);
+
+        let market = router.market(0, 1).await.unwrap();
+        assert!(route.expected_out() > market.pool_out(amount_in));
+        assert!(route.expected_out() > market.book_out(amount_in));
+        for leg in legs {
+            assert!(leg.min_out <= leg.expected_out);
+            assert!(leg.guaranteed_out <= leg.expected_out);
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_through_intermediate_token() {
+        // No ELXR/USDT market at all
+        let liquidity = FixedLiquidity::default()
+            .with_pool("ELXR", "NRSH", 4_000_000, 1_000_000)
+            .with_pool("NRSH", "USDT", 1_000_000, 333_000_000);
+        let router = router(liquidity);
+
+        let route = router.quote("ELXR", "USDT", 10_000).await.unwrap();
+        assert_eq!(route.path(), vec!["ELXR", "NRSH", "USDT"]);
+
+        // The second hop spends what the first is guaranteed to deliver
+        assert_eq!(route.hops[1].amount_in(), route.hops[0].guaranteed_out());
+        let nrsh = PoolReserves {
+            reserve_in: 4_000_000,
+            reserve_out: 1_000_000,
+        }
+        .quote(10_000);
+        assert_eq!(route.hops[0].expected_out(), nrsh);
+        assert!(route.min_out() < route.expected_out());
+
+        assert!(matches!(
+            router.quote("ELXR", "IMRT", 10_000).await,
+            Err(RouterError::UnknownToken(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn prefers_multi_hop_when_the_direct_pair_is_thin() {
+        let liquidity = FixedLiquidity::default()
+            .with_pool("ELXR", "USDT", 1_000, 80_000)
+            .with_pool("ELXR", "NRSH", 4_000_000, 1_000_000)
+            .with_pool("NRSH", "USDT", 1_000_000, 333_000_000);
+        let router = router(liquidity);
+
+        let route = router.quote("ELXR", "USDT", 10_000).await.unwrap();
+        assert_eq!(route.path(), vec!["ELXR", "NRSH", "USDT"]);
+    }
+
+    #[tokio::test]
+    async fn reports_realized_against_expected() {
+        let liquidity = FixedLiquidity::default()
+            .with_pool("NRSH", "ELXR", 1_000_000, 500_000)
+            .with_book("NRSH", "ELXR", &[(5_000, 10_000)]);
+        let router = router(liquidity);
+        let route = router.quote("NRSH", "ELXR", 40_000).await.unwrap();
+        let elxr = router.token("ELXR").unwrap().clone();
+
+        let book_leg = route.hops[0].legs.iter().find(|leg| leg.venue == Venue::Book).unwrap();
+        let pool_leg = route.hops[0].legs.iter().find(|leg| leg.venue == Venue::Pool).unwrap();
+        let book_gross = 5_000;
+        let fills = vec![
+            Fill::Book {
+                token_out: elxr.dex_token.unwrap(),
+                amount: book_gross,
+                taker_fee: book_gross * 369 / 1_000_000,
+            },
+            // The pool moved against the trader by 100 ELXR
+            Fill::Pool {
+                asset_out: 2,
+                amount_out: pool_leg.expected_out - 100,
+            },
+            // Another token's fill is not part of the output
+            Fill::Pool {
+                asset_out: 1,
+                amount_out: 7,
+            },
+        ];
+        assert_eq!(book_leg.expected_out, book_gross - book_gross * 369 / 1_000_000);
+
+        let report = ExecutionReport::new(route.clone(), realized_output(&route, &fills));
+        assert_eq!(report.realized_out, route.expected_out() - 100);
+        assert_eq!(report.expected_price, route.expected_out() * PRICE_PRECISION / 40_000);
+        assert!(report.realized_price < report.expected_price);
+        assert_eq!(report.slippage_bps, (100 * 10_000 / route.expected_out()) as i64);
+    }
+}
@@ -0,0 +1,366 @@
+// Node access for the router: venue snapshots through DEX dry-runs and pool
+// storage reads, and execution of a whole route as one `Utility::batch_all`,
+// so either every leg settles or none does
+
+use std::sync::Arc;
+
+use codec::{Compact, Decode, Encode, Output};
+use futures::future::BoxFuture;
+use sp_core::{crypto::AccountId32, sr25519, Pair};
+use sp_runtime::MultiAddress;
+use substrate_api_client::{
+    ac_primitives::{AssetRuntimeConfig, ExtrinsicSigner},
+    compose_call, compose_extrinsic,
+    rpc::JsonrpseeClient,
+    Api, GetChainInfo, GetStorage, SubmitAndWatchUntilSuccess,
+};
+
+use super::liquidity::{BookLevel, BookSnapshot, LiquidityReader, PoolReserves};
+use super::{default_deadline_blocks, realized_output, ExecutionReport, Fill, Route, RouterError, Token, Venue};
+use crate::chain_events::{DexEvent, PoolSwap};
+use crate::node::dry_run;
+use crate::oracle_client::submitter::selector;
+
+type NodeApi = Api<AssetRuntimeConfig, JsonrpseeClient>;
+
+// `OrderType::Market` of the DEX
+const MARKET: u8 = 0;
+// `OrderStatus::Open` and `OrderStatus::PartiallyFilled`
+const OPEN: u8 = 0;
+const PARTIALLY_FILLED: u8 = 1;
+
+/// `Order` of the DEX in field order, its enums read as their variant index
+#[derive(Decode)]
+struct DexOrder {
+    _creator: AccountId32,
+    _token_in: AccountId32,
+    _token_out: AccountId32,
+    amount_in: u128,
+    min_amount_out: u128,
+    expiration: u32,
+    _order_type: u8,
+    status: u8,
+    _trigger_price: u128,
+    _bounty: u128,
+    filled_in: u128,
+    _filled_out: u128,
+}
+
+fn node_error(e: impl std::fmt::Debug) -> RouterError {
+    RouterError::Node(format!("{:?}", e))
+}
+
+fn call_data(message: &str, args: impl Encode) -> Vec<u8> {
+    let mut data = selector(message).to_vec();
+    args.encode_to(&mut data);
+    data
+}
+
+/// Dry-run a DEX message and decode what it returns
+fn query<R: Decode>(
+    api: &NodeApi,
+    origin: &AccountId32,
+    contract: &AccountId32,
+    data: Vec<u8>,
+) -> Result<R, RouterError> {
+    let output = dry_run(api, origin, contract, data).map_err(RouterError::Node)?;
+    R::decode(&mut &output[..]).map_err(node_error)
+}
+
+/// Reads the DEX book through contract dry-runs and pool reserves from storage
+pub struct NodeLiquidity {
+    api: Arc<NodeApi>,
+    dex: AccountId32,
+    origin: AccountId32,
+}
+
+impl NodeLiquidity {
+    /// `origin` only pays for the dry-runs, any funded account will do
+    pub fn new(api: Arc<NodeApi>, dex: AccountId32, origin: AccountId32) -> Self {
+        Self { api, dex, origin }
+    }
+}
+
+impl LiquidityReader for NodeLiquidity {
+    fn book<'a>(
+        &'a self,
+        token_in: &'a Token,
+        token_out: &'a Token,
+    ) -> BoxFuture<'a, Result<Option<BookSnapshot>, RouterError>> {
+        let api = self.api.clone();
+        let dex = self.dex.clone();
+        let origin = self.origin.clone();
+        let tokens = token_in.dex_token.zip(token_out.dex_token);
+        Box::pin(async move {
+            let (token_in, token_out) = match tokens {
+                Some((token_in, token_out)) => (AccountId32::from(token_in), AccountId32::from(token_out)),
+                None => return Ok(None),
+            };
+            tokio::task::spawn_blocking(move || {
+                // The makers sell `token_out` for `token_in`
+                let ids: Vec<u64> = query(
+                    &api,
+                    &origin,
+                    &dex,
+                    call_data("get_order_book", (&token_out, &token_in)),
+                )?;
+                if ids.is_empty() {
+                    return Ok(None);
+                }
+                let (_, taker_fee_rate): (u128, u128) = query(&api, &origin, &dex, call_data("fee_rates", ()))?;
+                let now = api
+                    .get_header(None)
+                    .map_err(node_error)?
+                    .map(|header| header.number)
+                    .unwrap_or(0);
+
+                let mut levels = Vec::with_capacity(ids.len());
+                for id in ids {
+                    let order: Option<DexOrder> = query(&api, &origin, &dex, call_data("get_order", id))?;
+                    // Makers expiring before the next block are refunded rather than filled
+                    let order = match order {
+                        Some(order)
+                            if order.expiration > now && (order.status == OPEN || order.status == PARTIALLY_FILLED) =>
+                        {
+                            order
+                        }
+                        _ => continue,
+                    };
+                    levels.push(BookLevel {
+                        amount_in: order.amount_in,
+                        min_amount_out: order.min_amount_out,
+                        remaining: order.amount_in - order.filled_in,
+                    });
+                }
+                Ok(Some(BookSnapshot { levels, taker_fee_rate }))
+            })
+            .await
+            .map_err(|e| RouterError::Node(e.to_string()))?
+        })
+    }
+
+    fn pool<'a>(
+        &'a self,
+        token_in: &'a Token,
+        token_out: &'a Token,
+    ) -> BoxFuture<'a, Result<Option<PoolReserves>, RouterError>> {
+        let api = self.api.clone();
+        let assets = token_in.pool_asset.zip(token_out.pool_asset);
+        Box::pin(async move {
+            let (asset_in, asset_out) = match assets {
+                Some(assets) => assets,
+                None => return Ok(None),
+            };
+            tokio::task::spawn_blocking(move || {
+                // Pools are keyed by the lower asset ID first
+                let key = (asset_in.min(asset_out), asset_in.max(asset_out));
+                let reserves = api
+                    .get_storage_map::<(u32, u32), (u128, u128)>("LiquidityPool", "LiquidityPools", key, None)
+                    .map_err(node_error)?;
+                Ok(reserves.filter(|&(a, b)| a > 0 && b > 0).map(|(a, b)| {
+                    if asset_in < asset_out {
+                        PoolReserves {
+                            reserve_in: a,
+                            reserve_out: b,
+                        }
+                    } else {
+                        PoolReserves {
+                            reserve_in: b,
+                            reserve_out: a,
+                        }
+                    }
+                }))
+            })
+            .await
+            .map_err(|e| RouterError::Node(e.to_string()))?
+        })
+    }
+}
+
+/// An already encoded call inside `Utility::batch_all`
+struct RawCall(Vec<u8>);
+
+impl Encode for RawCall {
+    fn size_hint(&self) -> usize {
+        self.0.len()
+    }
+
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        dest.write(&self.0)
+    }
+}
+
+/// Submits routes from the trader's account
+pub struct RouteExecutor {
+    api: Arc<NodeApi>,
+    dex: AccountId32,
+    trader: AccountId32,
+    gas_limit: (u64, u64),
+    deadline_blocks: u32,
+}
+
+impl RouteExecutor {
+    pub fn connect(url: &str, dex: AccountId32, trader: sr25519::Pair) -> Result<Self, RouterError> {
+        let client = JsonrpseeClient::new(url).map_err(node_error)?;
+        let mut api = NodeApi::new(client).map_err(node_error)?;
+        let account = trader.public().into();
+        api.set_signer(ExtrinsicSigner::<AssetRuntimeConfig>::new(trader));
+        Ok(Self {
+            api: Arc::new(api),
+            dex,
+            trader: account,
+            gas_limit: (50_000_000_000, 1_000_000),
+            deadline_blocks: default_deadline_blocks(),
+        })
+    }
+
+    /// Blocks after submission past which pool swaps no longer execute,
+    /// usually `RouterConfig::deadline_blocks`
+    pub fn with_deadline_blocks(mut self, deadline_blocks: u32) -> Self {
+        self.deadline_blocks = deadline_blocks;
+        self
+    }
+
+    pub fn api(&self) -> Arc<NodeApi> {
+        self.api.clone()
+    }
+
+    pub fn trader(&self) -> &AccountId32 {
+        &self.trader
+    }
+
+    fn contract_call(&self, contract: &AccountId32, data: Vec<u8>) -> RawCall {
+        // Weight { ref_time, proof_size } encodes as two compact u64s
+        let call = compose_call!(
+            self.api.metadata(),
+            "Contracts",
+            "call",
+            MultiAddress::<AccountId32, ()>::Id(contract.clone()),
+            Compact(0u128),
+            (Compact(self.gas_limit.0), Compact(self.gas_limit.1)),
+            None::<Compact<u128>>,
+            data
+        );
+        RawCall(call.encode())
+    }
+
+    /// Book legs approve the DEX and place a market order, which fills against
+    /// the book at once and refunds what it could not fill; pool legs swap
+    /// until `deadline`
+    fn calls(&self, route: &Route, deadline: u32) -> Result<Vec<RawCall>, RouterError> {
+        let mut calls = Vec::new();
+        for hop in &route.hops {
+            for leg in &hop.legs {
+                match leg.venue {
+                    Venue::Book => {
+                        let (token_in, token_out) = hop
+                            .token_in
+                            .dex_token
+                            .zip(hop.token_out.dex_token)
+                            .map(|(a, b)| (AccountId32::from(a), AccountId32::from(b)))
+                            .ok_or_else(|| RouterError::Config(format!("{} has no DEX token", hop.token_in.symbol)))?;
+                        calls.push(
+                            self.contract_call(&token_in, call_data("PSP22::approve", (&self.dex, leg.amount_in))),
+                        );
+                        calls.push(self.contract_call(
+                            &self.dex,
+                            call_data(
+                                "create_order",
+                                (&token_in, &token_out, leg.amount_in, leg.min_out, MARKET, None::<u32>),
+                            ),
+                        ));
+                    }
+                    Venue::Pool => {
+                        let (asset_in, asset_out) =
+                            hop.token_in.pool_asset.zip(hop.token_out.pool_asset).ok_or_else(|| {
+                                RouterError::Config(format!("{} has no pool asset", hop.token_in.symbol))
+                            })?;
+                        let call = compose_call!(
+                            self.api.metadata(),
+                            "LiquidityPool",
+                            "swap",
+                            asset_in,
+                            asset_out,
+                            leg.amount_in,
+                            leg.min_out,
+                            deadline
+                        );
+                        calls.push(RawCall(call.encode()));
+                    }
+                }
+            }
+        }
+        Ok(calls)
+    }
+
+    /// Execute `route` atomically and compare what arrived with the quote
+    pub async fn execute(&self, route: Route) -> Result<ExecutionReport, RouterError> {
+        let api = self.api.clone();
+        let now = tokio::task::spawn_blocking(move || {
+            api.get_header(None)
+                .map_err(node_error)?
+                .map(|header| header.number)
+                .ok_or_else(|| RouterError::Node("no best block".into()))
+        })
+        .await
+        .map_err(|e| RouterError::Node(e.to_string()))??;
+        let calls = self.calls(&route, now.saturating_add(self.deadline_blocks))?;
+        let api = self.api.clone();
+        let dex = self.dex.clone();
+        let trader = self.trader.clone();
+        let fills = tokio::task::spawn_blocking(move || {
+            let xt = compose_extrinsic!(api, "Utility", "batch_all", calls);
+            let report = api
+                .submit_and_watch_extrinsic_until_success(xt, false)
+                .map_err(node_error)?;
+            Ok::<_, RouterError>(fills(
+                &dex,
+                &trader,
+                report
+                    .events
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|event| (event.pallet_name(), event.variant_name(), event.field_bytes())),
+            ))
+        })
+        .await
+        .map_err(|e| RouterError::Node(e.to_string()))??;
+
+        let realized_out = realized_output(&route, &fills);
+        Ok(ExecutionReport::new(route, realized_out))
+    }
+}
+
+/// The trader's fills among `(pallet, variant, fields)` of the batch's events
+fn fills<'a>(
+    dex: &AccountId32,
+    trader: &AccountId32,
+    events: impl Iterator<Item = (&'a str, &'a str, &'a [u8])>,
+) -> Vec<Fill> {
+    events
+        .filter_map(|(pallet, variant, fields)| match (pallet, variant) {
+            ("Contracts", "ContractEmitted") => match DexEvent::from_contract_emitted(dex, fields)? {
+                DexEvent::TradeExecuted {
+                    taker,
+                    base_token,
+                    amount,
+                    taker_fee,
+                    ..
+                } if &taker == trader => Some(Fill::Book {
+                    token_out: base_token.into(),
+                    amount,
+                    taker_fee,
+                }),
+                _ => None,
+            },
+            ("LiquidityPool", "Swap") => {
+                let swap = PoolSwap::from_fields(fields)?;
+                (&swap.who == trader).then_some(Fill::Pool {
+                    asset_out: swap.asset_out,
+                    amount_out: swap.amount_out,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
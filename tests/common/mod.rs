@@ -0,0 +1,173 @@
+// Harness for integration tests against a local substrate-contracts-node
+//
+// The tests are ignored by default. To run them:
+//   substrate-contracts-node --dev
+//   cargo contract build --release   (for each contract under runtime/)
+//   NOURISH_CONTRACTS_DIR=<dir with *.wasm> cargo test -- --ignored
+
+use codec::{Compact, Decode, Encode};
+use nourish_eigenlayer::node::dry_run;
+use sp_core::{crypto::AccountId32, hashing::blake2_256, sr25519, Pair};
+use sp_runtime::MultiAddress;
+use substrate_api_client::{
+    ac_primitives::{AssetRuntimeConfig, ExtrinsicSigner},
+    compose_extrinsic,
+    rpc::{JsonrpseeClient, Request},
+    rpc_params, Api, SubmitAndWatchUntilSuccess,
+};
+
+type NodeApi = Api<AssetRuntimeConfig, JsonrpseeClient>;
+
+const GAS_LIMIT: (u64, u64) = (100_000_000_000, 3_000_000);
+
+pub struct ContractsNode {
+    api: NodeApi,
+    signer: sr25519::Pair,
+}
+
+/// ink! selector: first four bytes of blake2-256 of the message name,
+/// `Trait::message` for trait messages
+pub fn selector(name: &str) -> [u8; 4] {
+    let hash = blake2_256(name.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+fn call_data(name: &str, args: impl Encode) -> Vec<u8> {
+    let mut data = selector(name).to_vec();
+    args.encode_to(&mut data);
+    data
+}
+
+impl ContractsNode {
+    /// Connect as `//Alice` to `CONTRACTS_NODE_URL` (default ws://127.0.0.1:9944)
+    pub fn connect() -> Self {
+        Self::connect_as("//Alice")
+    }
+
+    pub fn connect_as(uri: &str) -> Self {
+        let url = std::env::var("CONTRACTS_NODE_URL").unwrap_or_else(|_| "ws://127.0.0.1:9944".into());
+        let signer = sr25519::Pair::from_string(uri, None).expect("valid dev key");
+        let client = JsonrpseeClient::new(&url).expect("contracts node is running");
+        let mut api = NodeApi::new(client).expect("node metadata");
+        api.set_signer(ExtrinsicSigner::<AssetRuntimeConfig>::new(signer.clone()));
+        Self { api, signer }
+    }
+
+    pub fn account(&self) -> AccountId32 {
+        self.signer.public().into()
+    }
+
+    /// Upload `<NOURISH_CONTRACTS_DIR>/<name>.wasm` and instantiate it
+    pub fn deploy(&self, name: &str, constructor: &str, args: impl Encode) -> AccountId32 {
+        let dir = std::env::var("NOURISH_CONTRACTS_DIR").expect("NOURISH_CONTRACTS_DIR is set");
+        let code = std::fs::read(format!("{}/{}.wasm", dir, name)).expect("contract wasm is built");
+        // Weight { ref_time, proof_size } encodes as two compact u64s
+        let xt = compose_extrinsic!(
+            self.api,
+            "Contracts",
+            "instantiate_with_code",
+            Compact(0u128),
+            (Compact(GAS_LIMIT.0), Compact(GAS_LIMIT.1)),
+            None::<Compact<u128>>,
+            code,
+            call_data(constructor, args),
+            name.as_bytes().to_vec()
+        );
+        let report = self.api.submit_and_watch_extrinsic_until_success(xt, false).expect("instantiated");
+        // Contracts::Instantiated { deployer, contract }
+        let event = report
+            .events
+            .expect("events are reported")
+            .into_iter()
+            .find(|e| e.pallet_name() == "Contracts" && e.variant_name() == "Instantiated")
+            .expect("Instantiated event");
+        AccountId32::decode(&mut &event.field_bytes()[32..64]).expect("contract address")
+    }
+
+    /// Submit a state-changing message call
+    pub fn call(&self, contract: &AccountId32, message: &str, args: impl Encode) {
+        let xt = compose_extrinsic!(
+            self.api,
+            "Contracts",
+            "call",
+            MultiAddress::<AccountId32, ()>::Id(contract.clone()),
+            Compact(0u128),
+            (Compact(GAS_LIMIT.0), Compact(GAS_LIMIT.1)),
+            None::<Compact<u128>>,
+            call_data(message, args)
+        );
+        self.api.submit_and_watch_extrinsic_until_success(xt, false).expect("call succeeded");
+    }
+
+    /// Dry-run a message through the `ContractsApi_call` runtime API and decode its return value
+    pub fn query<R: Decode>(&self, contract: &AccountId32, message: &str, args: impl Encode) -> R {
+        let data = dry_run(&self.api, &self.account(), contract, call_data(message, args))
+            .unwrap_or_else(|e| panic!("{} dry run: {}", message, e));
+        R::decode(&mut &data[..]).expect("return value decodes")
+    }
+}
+
+impl ContractsNode {
+    /// SCALE encoding of the node's best header and its hash. The hash is
+    /// checked against the node's, so the encoding is byte-exact.
+    pub fn best_header(&self) -> (Vec<u8>, [u8; 32]) {
+        let json: serde_json::Value = self
+            .api
+            .client()
+            .request("chain_getHeader", rpc_params![])
+            .expect("best header");
+        let field = |name: &str| json[name].as_str().expect("header field").trim_start_matches("0x").to_owned();
+        let hash32 = |name: &str| <[u8; 32]>::try_from(hex_decode(&field(name))).expect("32-byte hash");
+        let number = u32::from_str_radix(&field("number"), 16).expect("block number");
+        // Digest logs come SCALE-encoded already
+        let logs: Vec<Vec<u8>> = json["digest"]["logs"]
+            .as_array()
+            .expect("digest logs")
+            .iter()
+            .map(|log| hex_decode(log.as_str().expect("log").trim_start_matches("0x")))
+            .collect();
+
+        let mut header = (hash32("parentHash"), Compact(number), hash32("stateRoot"), hash32("extrinsicsRoot")).encode();
+        Compact(logs.len() as u32).encode_to(&mut header);
+        logs.iter().for_each(|log| header.extend_from_slice(log));
+
+        let hash = blake2_256(&header);
+        let expected: String = self
+            .api
+            .client()
+            .request("chain_getBlockHash", rpc_params![number])
+            .expect("block hash");
+        assert_eq!(format!("0x{}", hex_encode(&hash)), expected, "header re-encodes to its hash");
+        (header, hash)
+    }
+
+    /// Value of `key` in the state of block `at` and the trie nodes proving it
+    pub fn read_proof(&self, key: &[u8], at: [u8; 32]) -> (Option<Vec<u8>>, Vec<Vec<u8>>) {
+        let (key, at) = (format!("0x{}", hex_encode(key)), format!("0x{}", hex_encode(&at)));
+        let value: Option<String> = self
+            .api
+            .client()
+            .request("state_getStorage", rpc_params![key.clone(), at.clone()])
+            .expect("storage value");
+        let proof: serde_json::Value = self
+            .api
+            .client()
+            .request("state_getReadProof", rpc_params![vec![key], at])
+            .expect("read proof");
+        let nodes = proof["proof"]
+            .as_array()
+            .expect("proof nodes")
+            .iter()
+            .map(|node| hex_decode(node.as_str().expect("node").trim_start_matches("0x")))
+            .collect();
+        (value.map(|v| hex_decode(v.trim_start_matches("0x"))), nodes)
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(s: &str) -> Vec<u8> {
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+}
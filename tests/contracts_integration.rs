@@ -0,0 +1,196 @@
+// End-to-end tests deploying the oracle, PSP22 tokens, DEX and unified pool contracts together
+// See tests/common/mod.rs for how to run them against a local node
+
+mod common;
+
+use codec::{Compact, Decode, Encode};
+use common::ContractsNode;
+use sp_core::{
+    crypto::AccountId32,
+    hashing::{blake2_256, twox_128},
+};
+
+// pqc_kyber (Kyber768) and pqc_dilithium (Dilithium3) public key sizes
+const KYBER_PUBLICKEYBYTES: usize = 1184;
+const DILITHIUM_PUBLICKEYBYTES: usize = 1952;
+
+// TokenId of the unified pool
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq)]
+enum Token {
+    NRSH,
+    ELXR,
+    IMRT,
+}
+
+#[derive(Decode, Debug, PartialEq, Eq)]
+struct PriceData {
+    price: u128,
+    confidence: u8,
+    timestamp: u64,
+    staleness: u64,
+}
+
+fn feed(name: &str) -> [u8; 32] {
+    blake2_256(name.as_bytes())
+}
+
+/// Oracle with Alice as the only validator and NRSH at $333, ELXR at $75
+fn deploy_oracle(node: &ContractsNode) -> AccountId32 {
+    let oracle = node.deploy("daemonless_oracle", "new", (1u32, 1u32, 0u128));
+    node.call(
+        &oracle,
+        "register_validator",
+        (1_000u128, [0u8; KYBER_PUBLICKEYBYTES], [0u8; DILITHIUM_PUBLICKEYBYTES]),
+    );
+    node.call(&oracle, "submit_price_update", (feed("NRSH/USD"), 333_000_000u128, 100u8));
+    node.call(&oracle, "submit_price_update", (feed("ELXR/USD"), 75_000_000u128, 100u8));
+    oracle
+}
+
+#[test]
+#[ignore = "requires a local substrate-contracts-node and built contracts"]
+fn oracle_consumers_read_prices() {
+    let node = ContractsNode::connect();
+    let oracle = deploy_oracle(&node);
+
+    let latest: Option<PriceData> = node.query(&oracle, "OracleConsumer::latest_price", feed("NRSH/USD"));
+    let latest = latest.expect("NRSH feed reached consensus");
+    assert_eq!(latest.price, 333_000_000);
+    assert_eq!(latest.confidence, 100);
+
+    // DEX prices NRSH in ELXR from the two USD feeds; its tokens are PSP22 addresses,
+    // pricing does not call them so any address will do
+    let (nrsh, elxr) = (AccountId32::new([0x10; 32]), AccountId32::new([0x20; 32]));
+    let dex = node.deploy("zero_spread_dex", "new", node.account());
+    node.call(&dex, "set_oracle", (oracle.clone(), 600_000u64));
+    node.call(&dex, "set_token_feed", (nrsh.clone(), feed("NRSH/USD")));
+    node.call(&dex, "set_token_feed", (elxr.clone(), feed("ELXR/USD")));
+    let price: Result<u128, u8> = node.query(&dex, "oracle_price", (nrsh, elxr));
+    assert_eq!(price, Ok(4_440_000));
+
+    // Unified pool values deposits in USD
+    let pool = node.deploy("unified_liquidity_pool", "new", (3_690u128, 1_000u128));
+    node.call(&pool, "set_oracle", (oracle.clone(), 600_000u64));
+    node.call(&pool, "set_token_feed", (Token::NRSH, feed("NRSH/USD")));
+    node.call(&pool, "set_token_feed", (Token::ELXR, feed("ELXR/USD")));
+    let value: Result<u128, u8> = node.query(&pool, "token_value", (Token::NRSH, 2_000_000u128));
+    assert_eq!(value, Ok(666_000_000));
+
+    // IMRT has no feed configured
+    let missing: Result<u128, u8> = node.query(&pool, "token_value", (Token::IMRT, 1u128));
+    assert!(missing.is_err());
+}
+
+// StateProof of the oracle
+#[derive(Encode, Clone)]
+struct StateProof {
+    source_chain: u32,
+    block_number: u32,
+    state_root: [u8; 32],
+    storage_key: Vec<u8>,
+    storage_value: Option<Vec<u8>>,
+    proof_nodes: Vec<Vec<u8>>,
+}
+
+#[test]
+#[ignore = "requires a local substrate-contracts-node and built contracts"]
+fn oracle_verifies_state_proofs_of_a_live_chain() {
+    let node = ContractsNode::connect();
+
+    // The node's own best block stands in for a parachain block: a real header,
+    // and a real proof of System::Number, which is that block's number
+    let (header, hash) = node.best_header();
+    let (_, number, state_root) = <([u8; 32], Compact<u32>, [u8; 32])>::decode(&mut &header[..]).unwrap();
+    let key = [twox_128(b"System"), twox_128(b"Number")].concat();
+    let (value, proof_nodes) = node.read_proof(&key, hash);
+    assert_eq!(value, Some(number.0.encode()));
+
+    let oracle = node.deploy("daemonless_oracle", "new", (1u32, 1u32, 0u128));
+    let keys = pqc_dilithium::Keypair::generate();
+    node.call(&oracle, "register_validator", (1_000u128, [0u8; KYBER_PUBLICKEYBYTES], keys.public));
+    node.call(&oracle, "approve_attester", node.account());
+    node.call(&oracle, "register_parachain", (2000u32, [0u8; KYBER_PUBLICKEYBYTES], Vec::<AccountId32>::new()));
+
+    let message = (2000u32, hash).encode();
+    node.call(&oracle, "submit_header", (2000u32, header, vec![(node.account(), keys.sign(&message))]));
+    let tracked: Option<([u8; 32], [u8; 32], [u8; 32], Vec<AccountId32>)> =
+        node.query(&oracle, "get_tracked_header", (2000u32, number.0));
+    assert_eq!(tracked.map(|(header_hash, _, root, _)| (header_hash, root)), Some((hash, state_root)));
+
+    let proof = StateProof {
+        source_chain: 2000,
+        block_number: number.0,
+        state_root,
+        storage_key: key,
+        storage_value: value,
+        proof_nodes,
+    };
+    let verified: Result<bool, u8> = node.query(&oracle, "verify_state_proof", (2000u32, proof.clone()));
+    assert_eq!(verified, Ok(true));
+
+    let forged = StateProof {
+        storage_value: Some((number.0 + 1).encode()),
+        ..proof
+    };
+    let verified: Result<bool, u8> = node.query(&oracle, "verify_state_proof", (2000u32, forged));
+    assert_eq!(verified, Ok(false));
+}
+
+/// NRSH, ELXR and IMRT as PSP22 tokens, the whole supply minted to the deployer
+fn deploy_tokens(node: &ContractsNode) -> [AccountId32; 3] {
+    ["NRSH", "ELXR", "IMRT"].map(|symbol| {
+        node.deploy(
+            "psp22_token",
+            "new",
+            (1_000_000_000u128, Some(symbol.as_bytes().to_vec()), Some(symbol.as_bytes().to_vec()), 6u8),
+        )
+    })
+}
+
+fn psp22_balance(node: &ContractsNode, token: &AccountId32, owner: &AccountId32) -> u128 {
+    node.query(token, "PSP22::balance_of", owner.clone())
+}
+
+#[test]
+#[ignore = "requires a local substrate-contracts-node and built contracts"]
+fn dex_and_pool_hold_psp22_tokens() {
+    let alice = ContractsNode::connect();
+    let bob = ContractsNode::connect_as("//Bob");
+    let [nrsh, elxr, imrt] = deploy_tokens(&alice);
+    alice.call(&elxr, "PSP22::transfer", (bob.account(), 1_000_000u128, Vec::<u8>::new()));
+
+    // Alice deposits NRSH and rests an ask of 1_000 NRSH at 4 ELXR each
+    let dex = alice.deploy("zero_spread_dex", "new", alice.account());
+    alice.call(&nrsh, "PSP22::approve", (dex.clone(), 1_000u128));
+    alice.call(&dex, "deposit", (nrsh.clone(), 1_000u128));
+    let deposited: u128 = alice.query(&dex, "balance_of", (alice.account(), nrsh.clone()));
+    assert_eq!(deposited, 1_000);
+    // OrderType::Limit, default lifetime
+    alice.call(&dex, "create_order", (nrsh.clone(), elxr.clone(), 1_000u128, 4_000u128, 1u8, None::<u32>));
+    assert_eq!(psp22_balance(&alice, &nrsh, &dex), 1_000);
+
+    // Bob buys half of it with ELXR pulled through his allowance
+    bob.call(&elxr, "PSP22::approve", (dex.clone(), 2_000u128));
+    bob.call(&dex, "create_order", (elxr.clone(), nrsh.clone(), 2_000u128, 500u128, 1u8, None::<u32>));
+    let (maker_rate, taker_rate): (u128, u128) = alice.query(&dex, "fee_rates", ());
+    assert_eq!(psp22_balance(&alice, &nrsh, &bob.account()), 500 - 500 * taker_rate / 1_000_000);
+    assert_eq!(psp22_balance(&alice, &elxr, &bob.account()), 1_000_000 - 2_000);
+    assert_eq!(
+        psp22_balance(&alice, &elxr, &alice.account()),
+        1_000_000_000 - 1_000_000 + 2_000 - 2_000 * maker_rate / 1_000_000
+    );
+    // The rest of the ask and the fees stay in the DEX
+    let nrsh_fees: u128 = alice.query(&dex, "collected_fees", nrsh.clone());
+    assert_eq!(psp22_balance(&alice, &nrsh, &dex), 500 + nrsh_fees);
+
+    // The pool's reserves are its PSP22 balances
+    let pool = alice.deploy("unified_liquidity_pool", "new", (3_690u128, 1_000u128));
+    for (token_id, token) in [(Token::NRSH, &nrsh), (Token::ELXR, &elxr), (Token::IMRT, &imrt)] {
+        alice.call(&pool, "set_token_contract", (token_id, token.clone()));
+        alice.call(token, "PSP22::approve", (pool.clone(), 50_000u128));
+        alice.call(&pool, "add_liquidity", (token_id, 50_000u128, 50_000u128));
+        let reserve: u128 = alice.query(&pool, "reserve", token_id);
+        assert_eq!(reserve, 50_000);
+        assert_eq!(psp22_balance(&alice, token, &pool), reserve);
+    }
+}